@@ -0,0 +1,193 @@
+use super::biquad::Biquad;
+use super::Stage;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Loudness is measured in blocks of this length (as in ITU-R BS.1770).
+const BLOCK_MS: f32 = 100.0;
+
+/// Blocks quieter than this never count towards the loudness (BS.1770
+/// absolute gate).
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AgcMode {
+    /// Short-term loudness (3 s) and gentle gain changes, for conversational speech
+    #[default]
+    Slow,
+    /// Momentary loudness (400 ms) and quick gain riding, broadcast style
+    Fast,
+}
+
+impl AgcMode {
+    /// Number of blocks averaged into the loudness measurement.
+    fn window_blocks(self) -> usize {
+        match self {
+            AgcMode::Slow => 30,
+            AgcMode::Fast => 4,
+        }
+    }
+
+    /// Fastest allowed gain change, in dB per second.
+    fn rate_db_per_second(self) -> f32 {
+        match self {
+            AgcMode::Slow => 3.0,
+            AgcMode::Fast => 12.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AgcParams {
+    pub enabled: bool,
+    /// Loudness the output is steered towards, in LUFS
+    pub target_lufs: f32,
+    /// Largest boost applied to quiet speakers, in dB
+    pub max_gain_db: f32,
+    /// Largest cut applied to loud speakers, in dB
+    pub max_cut_db: f32,
+    pub mode: AgcMode,
+}
+
+impl Default for AgcParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lufs: -18.0,
+            max_gain_db: 18.0,
+            max_cut_db: 12.0,
+            mode: AgcMode::Slow,
+        }
+    }
+}
+
+/// Automatic gain control towards a loudness target.
+///
+/// The input is K-weighted and its mean square collected in 100 ms blocks;
+/// the loudness of the last few blocks sets the gain needed to reach the
+/// target. Blocks where the input is marked inactive (the noise gate is
+/// closed) are left out of the measurement and the gain is held, so pauses
+/// do not make the AGC pump up the background noise.
+pub struct Agc {
+    sample_rate: f32,
+    shelf: Biquad,
+    highpass: Biquad,
+    block_len: usize,
+    block_fill: usize,
+    block_energy: f32,
+    block_active: usize,
+    blocks: VecDeque<f32>,
+    active: bool,
+    loudness: Option<f32>,
+    target_gain_db: f32,
+    gain_db: f32,
+}
+
+impl Agc {
+    pub fn new(sample_rate: f32) -> Self {
+        let block_len = (sample_rate * BLOCK_MS / 1000.0).round() as usize;
+
+        // BS.1770 K-weighting: head-related high shelf and low-frequency roll-off
+        Self {
+            sample_rate,
+            shelf: Biquad::high_shelf(sample_rate, 1681.97, 0.7072, 4.0),
+            highpass: Biquad::highpass(sample_rate, 38.14, 0.5003),
+            block_len,
+            block_fill: 0,
+            block_energy: 0.0,
+            block_active: 0,
+            blocks: VecDeque::new(),
+            active: true,
+            loudness: None,
+            target_gain_db: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Whether the input currently carries speech. While inactive the
+    /// measurement and the gain are frozen.
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    /// Measured input loudness in LUFS, once a block of active input was seen.
+    pub fn loudness_lufs(&self) -> Option<f32> {
+        self.loudness
+    }
+
+    /// Gain currently applied, in dB.
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    fn finish_block(&mut self, params: &AgcParams) {
+        // Only blocks that were mostly active describe the speaker's level
+        if self.block_active * 2 > self.block_len {
+            self.blocks.push_back(self.block_energy / self.block_active as f32);
+        }
+        self.block_fill = 0;
+        self.block_energy = 0.0;
+        self.block_active = 0;
+
+        let window = params.mode.window_blocks();
+        while self.blocks.len() > window {
+            self.blocks.pop_front();
+        }
+        if self.blocks.is_empty() {
+            return;
+        }
+
+        let recent = self.blocks.len().min(window);
+        let mean_square = self.blocks.iter().rev().take(recent).sum::<f32>() / recent as f32;
+        let loudness = -0.691 + 10.0 * (mean_square + 1e-12).log10();
+        self.loudness = Some(loudness);
+        if loudness > ABSOLUTE_GATE_LUFS {
+            self.target_gain_db = (params.target_lufs - loudness)
+                .clamp(-params.max_cut_db.max(0.0), params.max_gain_db.max(0.0));
+        }
+    }
+}
+
+impl Stage for Agc {
+    type Params = AgcParams;
+
+    fn process(&mut self, block: &mut [f32], params: &AgcParams) {
+        let step = params.mode.rate_db_per_second() / self.sample_rate;
+        // Keep the held gain inside the bounds if they were just narrowed
+        self.gain_db = self
+            .gain_db
+            .clamp(-params.max_cut_db.max(0.0), params.max_gain_db.max(0.0));
+
+        for sample in block.iter_mut() {
+            let weighted = self.highpass.process(self.shelf.process(*sample));
+            if self.active {
+                self.block_energy += weighted * weighted;
+                self.block_active += 1;
+            }
+            self.block_fill += 1;
+            if self.block_fill >= self.block_len {
+                self.finish_block(params);
+            }
+
+            if self.active {
+                self.gain_db += (self.target_gain_db - self.gain_db).clamp(-step, step);
+            }
+            *sample *= 10.0f32.powf(self.gain_db / 20.0);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.shelf.reset();
+        self.highpass.reset();
+        self.block_fill = 0;
+        self.block_energy = 0.0;
+        self.block_active = 0;
+        self.blocks.clear();
+        self.loudness = None;
+        self.target_gain_db = 0.0;
+        self.gain_db = 0.0;
+    }
+}
+
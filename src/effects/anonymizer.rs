@@ -0,0 +1,290 @@
+use super::formant::{envelope_at, SpectralEnvelope};
+use super::pitch_shift::{semitones_to_ratio, PitchAlgorithm, PitchShiftParams, MAX_SEMITONES};
+use super::psola::Psola;
+use super::rng::Rng;
+use super::stft::Stft;
+use super::Stage;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FRAME_SIZE: usize = 1024;
+const OVERLAP: usize = 4;
+
+/// Cepstral coefficients kept for the envelope, as in the formant shifter.
+const LIFTER: usize = 30;
+
+/// Largest boost or cut applied to a single bin while warping the envelope.
+const MAX_CORRECTION_DB: f32 = 24.0;
+
+/// Samples between updates of the pitch shift.
+const CONTROL_BLOCK: usize = 64;
+
+/// Ranges the seed picks the disguise from at full strength: the size of the
+/// pitch and formant moves in semitones and of the frequency warp, each with
+/// a random sign. The lower ends keep every seed clearly away from the
+/// original voice.
+const PITCH_RANGE: (f32, f32) = (2.0, 5.0);
+const FORMANT_RANGE: (f32, f32) = (1.0, 3.5);
+const WARP_RANGE: (f32, f32) = (0.04, 0.12);
+
+/// Largest drift of each setting at full variation.
+const PITCH_DRIFT: f32 = 1.5;
+const FORMANT_DRIFT: f32 = 1.0;
+const WARP_DRIFT: f32 = 0.03;
+
+/// Largest warp factor; beyond this vowels stop sounding like vowels.
+const MAX_WARP: f32 = 0.3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AnonymizerParams {
+    pub enabled: bool,
+    /// Seed the disguise is drawn from; a new one is picked (and shown) when
+    /// the anonymizer starts without one. The same seed and settings give the
+    /// same voice again.
+    pub seed: Option<u32>,
+    /// Scale of the pitch, formant and warp moves, 0.0 - 1.0
+    pub strength: f32,
+    /// Amount of slow wandering around the seeded settings, 0.0 - 1.0, so
+    /// that long recordings do not carry one fixed transform
+    pub variation: f32,
+    /// Time between the points the wandering moves through, in seconds
+    pub variation_s: f32,
+}
+
+impl Default for AnonymizerParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: None,
+            strength: 1.0,
+            variation: 0.0,
+            variation_s: 20.0,
+        }
+    }
+}
+
+/// A seed nobody chose, from the clock.
+pub fn fresh_seed() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    scramble((nanos ^ (nanos >> 32)) as u32)
+}
+
+/// Integer hash (the MurmurHash3 finalizer), so neighbouring seeds give
+/// unrelated disguises.
+fn scramble(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xc2b2_ae35);
+    x ^ (x >> 16)
+}
+
+/// The transform applied to the voice at one moment.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Disguise {
+    pub pitch_semitones: f32,
+    pub formant_semitones: f32,
+    /// Bilinear frequency warp factor; positive values stretch the lower
+    /// part of the spectrum upwards
+    pub warp: f32,
+}
+
+impl Disguise {
+    /// The disguise drawn from `seed` at full strength.
+    pub fn from_seed(seed: u32) -> Self {
+        let mut rng = Rng::new(scramble(seed));
+        let mut signed = |(min, max): (f32, f32)| {
+            let magnitude = min + (max - min) * rng.next_f32();
+            if rng.next_f32() < 0.5 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        };
+        Self {
+            pitch_semitones: signed(PITCH_RANGE),
+            formant_semitones: signed(FORMANT_RANGE),
+            warp: signed(WARP_RANGE),
+        }
+    }
+
+    fn scaled(self, amount: f32) -> Self {
+        Self {
+            pitch_semitones: self.pitch_semitones * amount,
+            formant_semitones: self.formant_semitones * amount,
+            warp: self.warp * amount,
+        }
+    }
+
+    fn plus(self, other: Self) -> Self {
+        Self {
+            pitch_semitones: self.pitch_semitones + other.pitch_semitones,
+            formant_semitones: self.formant_semitones + other.formant_semitones,
+            warp: self.warp + other.warp,
+        }
+    }
+
+    /// A random drift, each setting between minus and plus its largest drift.
+    fn drift(rng: &mut Rng) -> Self {
+        let mut next = |range: f32| range * (2.0 * rng.next_f32() - 1.0);
+        Self {
+            pitch_semitones: next(PITCH_DRIFT),
+            formant_semitones: next(FORMANT_DRIFT),
+            warp: next(WARP_DRIFT),
+        }
+    }
+}
+
+/// Point `omega` (0 - PI) moves to under the first-order all-pass warp with
+/// factor `alpha`; `-alpha` undoes it.
+fn warp_frequency(omega: f32, alpha: f32) -> f32 {
+    omega + 2.0 * (alpha * omega.sin()).atan2(1.0 - alpha * omega.cos())
+}
+
+/// Disguises a voice for privacy: the pitch is shifted (PSOLA), and the
+/// spectral envelope is both shifted and bent by a nonlinear frequency warp,
+/// all by amounts drawn from a seed. The warp moves every formant by a
+/// different ratio, so the vocal tract does not come back by shifting the
+/// pitch and formants back. With variation the settings wander slowly and
+/// smoothly along a path that also follows from the seed.
+pub struct Anonymizer {
+    sample_rate: f32,
+    seed: Option<u32>,
+    base: Disguise,
+    /// Drift path: the points passed and the position between them
+    drift_rng: Rng,
+    drift_from: Disguise,
+    drift_to: Disguise,
+    drift_position: f32,
+    current: Disguise,
+    psola: Psola,
+    shift: PitchShiftParams,
+    stft: Stft,
+    state: WarpState,
+}
+
+struct WarpState {
+    envelope: SpectralEnvelope,
+    magnitude: Vec<f32>,
+    log_envelope: Vec<f32>,
+}
+
+impl Anonymizer {
+    pub fn new(sample_rate: f32) -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+        Self {
+            sample_rate,
+            seed: None,
+            base: Disguise::default(),
+            drift_rng: Rng::new(1),
+            drift_from: Disguise::default(),
+            drift_to: Disguise::default(),
+            drift_position: 0.0,
+            current: Disguise::default(),
+            psola: Psola::new(sample_rate),
+            shift: PitchShiftParams {
+                enabled: true,
+                algorithm: PitchAlgorithm::Psola,
+                ..PitchShiftParams::default()
+            },
+            stft,
+            state: WarpState {
+                envelope: SpectralEnvelope::new(FRAME_SIZE, LIFTER),
+                magnitude: vec![0.0; bins],
+                log_envelope: vec![0.0; bins],
+            },
+        }
+    }
+
+    /// The transform applied to the last block.
+    pub fn current(&self) -> Disguise {
+        self.current
+    }
+
+    /// Draw the disguise and restart the drift path for `seed`.
+    fn configure(&mut self, seed: Option<u32>) {
+        if self.seed == seed {
+            return;
+        }
+        self.seed = seed;
+        let seed = seed.unwrap_or(0);
+        self.base = Disguise::from_seed(seed);
+        self.drift_rng = Rng::new(scramble(seed ^ 0x0a11_0f5e));
+        self.drift_from = Disguise::default();
+        self.drift_to = Disguise::drift(&mut self.drift_rng);
+        self.drift_position = 0.0;
+    }
+
+    /// Move along the drift path by `seconds` and return the drift there.
+    fn advance_drift(&mut self, seconds: f32, period_s: f32) -> Disguise {
+        self.drift_position += seconds / period_s.max(1.0);
+        while self.drift_position >= 1.0 {
+            self.drift_position -= 1.0;
+            self.drift_from = self.drift_to;
+            self.drift_to = Disguise::drift(&mut self.drift_rng);
+        }
+        // Raised-cosine easing keeps the changes free of corners
+        let t = 0.5 - 0.5 * (PI * self.drift_position).cos();
+        self.drift_from.scaled(1.0 - t).plus(self.drift_to.scaled(t))
+    }
+}
+
+impl Stage for Anonymizer {
+    type Params = AnonymizerParams;
+
+    fn process(&mut self, block: &mut [f32], params: &AnonymizerParams) {
+        self.configure(params.seed);
+        let strength = params.strength.clamp(0.0, 1.0);
+        let variation = params.variation.clamp(0.0, 1.0);
+
+        for chunk in block.chunks_mut(CONTROL_BLOCK) {
+            let seconds = chunk.len() as f32 / self.sample_rate;
+            let drift = self.advance_drift(seconds, params.variation_s);
+            self.current = self.base.scaled(strength).plus(drift.scaled(variation));
+            self.shift.semitones = self.current.pitch_semitones.clamp(-MAX_SEMITONES, MAX_SEMITONES);
+            self.psola.process(chunk, &self.shift);
+        }
+
+        let ratio = semitones_to_ratio(self.current.formant_semitones.clamp(-12.0, 12.0));
+        let warp = self.current.warp.clamp(-MAX_WARP, MAX_WARP);
+        let state = &mut self.state;
+        self.stft.process(block, |spectrum| state.warp(spectrum, ratio, warp));
+    }
+
+    fn latency(&self) -> usize {
+        self.psola.latency() + self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.psola.reset();
+        self.stft.reset();
+        // Start the drift path over, so a seed reproduces from the start
+        self.seed = None;
+    }
+}
+
+impl WarpState {
+    fn warp(&mut self, spectrum: &mut [Complex<f32>], ratio: f32, warp: f32) {
+        for (m, bin) in self.magnitude.iter_mut().zip(spectrum.iter()) {
+            *m = bin.norm();
+        }
+        self.envelope.estimate(&self.magnitude, &mut self.log_envelope);
+
+        // Each bin takes the level the envelope had where the shift and warp
+        // bring it from
+        let last = (spectrum.len() - 1) as f32;
+        let max_correction = MAX_CORRECTION_DB / 20.0 * std::f32::consts::LN_10;
+        for (k, bin) in spectrum.iter_mut().enumerate() {
+            let omega = warp_frequency(PI * k as f32 / last, -warp) / ratio;
+            let source = envelope_at(&self.log_envelope, omega / PI * last);
+            let correction = (source - self.log_envelope[k]).clamp(-max_correction, max_correction);
+            *bin *= correction.exp();
+        }
+    }
+}
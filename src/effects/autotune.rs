@@ -0,0 +1,148 @@
+use super::pitch_shift::{PitchAlgorithm, PitchShiftParams};
+use super::psola::Psola;
+use super::scale::{frequency_to_midi_note, Key, Scale};
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Samples between updates of the correction.
+const CONTROL_BLOCK: usize = 64;
+
+/// How much closer, in semitones, another scale note has to be before the
+/// target moves to it, so a voice between two notes does not flip between them.
+const NOTE_HYSTERESIS: f32 = 0.15;
+
+/// Extra retune time at full humanize once a note is held, in ms, and how
+/// long a note has to be held for all of it to apply.
+const HUMANIZE_MAX_MS: f32 = 400.0;
+const HUMANIZE_SETTLE_MS: f32 = 150.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutoTuneParams {
+    pub enabled: bool,
+    pub key: Key,
+    pub scale: Scale,
+    /// Time the pitch takes to move to the target note, in ms; 0 snaps
+    pub retune_ms: f32,
+    /// Slow the correction down on held notes, 0.0 - 1.0, so the natural
+    /// vibrato and drift survive while note changes are still corrected
+    pub humanize: f32,
+    /// Snap instantly and ignore humanize: the hard-tuned, stepped sound
+    pub robotic: bool,
+}
+
+impl Default for AutoTuneParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key: Key::C,
+            scale: Scale::Chromatic,
+            retune_ms: 50.0,
+            humanize: 0.0,
+            robotic: false,
+        }
+    }
+}
+
+/// Pitch correction: the PSOLA shifter's pitch tracker finds the sung note,
+/// and the shift moves it towards the nearest note of the scale.
+pub struct AutoTune {
+    sample_rate: f32,
+    psola: Psola,
+    shift: PitchShiftParams,
+    target: Option<i32>,
+    /// Correction currently applied, in semitones
+    correction: f32,
+    held_ms: f32,
+}
+
+impl AutoTune {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            psola: Psola::new(sample_rate),
+            shift: PitchShiftParams {
+                enabled: true,
+                algorithm: PitchAlgorithm::Psola,
+                ..PitchShiftParams::default()
+            },
+            target: None,
+            correction: 0.0,
+            held_ms: 0.0,
+        }
+    }
+
+    /// Pitch of the voice before correction, in Hz, while it is voiced.
+    pub fn detected(&self) -> Option<f32> {
+        self.psola.frequency()
+    }
+
+    /// Pitch after correction, in Hz, while the voice is voiced.
+    pub fn corrected(&self) -> Option<f32> {
+        self.detected().map(|frequency| frequency * 2.0f32.powf(self.correction / 12.0))
+    }
+
+    fn update(&mut self, params: &AutoTuneParams, elapsed_ms: f32) {
+        let Some(frequency) = self.psola.frequency() else {
+            // Start the next note from the natural pitch
+            self.target = None;
+            self.correction = 0.0;
+            return;
+        };
+        let note = frequency_to_midi_note(frequency);
+        let nearest = params.scale.nearest(params.key, note);
+        let target = match self.target {
+            Some(current)
+                if current != nearest
+                    && params.scale.contains(params.key, current)
+                    && (note - current as f32).abs() - (note - nearest as f32).abs() < NOTE_HYSTERESIS =>
+            {
+                current
+            }
+            _ => nearest,
+        };
+        if self.target != Some(target) {
+            self.target = Some(target);
+            self.held_ms = 0.0;
+        } else {
+            self.held_ms += elapsed_ms;
+        }
+
+        let wanted = target as f32 - note;
+        let retune_ms = if params.robotic {
+            0.0
+        } else {
+            let held = (self.held_ms / HUMANIZE_SETTLE_MS).min(1.0);
+            params.retune_ms.max(0.0) + params.humanize.clamp(0.0, 1.0) * HUMANIZE_MAX_MS * held
+        };
+        self.correction = if retune_ms <= 0.0 {
+            wanted
+        } else {
+            wanted + (self.correction - wanted) * (-elapsed_ms / retune_ms).exp()
+        };
+    }
+}
+
+impl Stage for AutoTune {
+    type Params = AutoTuneParams;
+
+    fn process(&mut self, block: &mut [f32], params: &AutoTuneParams) {
+        for chunk in block.chunks_mut(CONTROL_BLOCK) {
+            let elapsed_ms = chunk.len() as f32 * 1000.0 / self.sample_rate;
+            self.update(params, elapsed_ms);
+            self.shift.semitones = self.correction;
+            self.psola.process(chunk, &self.shift);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.psola.latency()
+    }
+
+    fn reset(&mut self) {
+        self.psola.reset();
+        self.target = None;
+        self.correction = 0.0;
+        self.held_ms = 0.0;
+    }
+}
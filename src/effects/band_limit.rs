@@ -0,0 +1,89 @@
+use super::biquad::Biquad;
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Butterworth Q for each of the two cascaded sections.
+const SECTION_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BandLimitParams {
+    pub enabled: bool,
+    /// High-pass corner in Hz
+    pub low_cut: f32,
+    /// Low-pass corner in Hz
+    pub high_cut: f32,
+    /// Saturation drive (1.0 = clean)
+    pub drive: f32,
+}
+
+impl Default for BandLimitParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low_cut: 300.0,
+            high_cut: 3400.0,
+            drive: 1.0,
+        }
+    }
+}
+
+/// Steep band-pass (24 dB/octave on each side) followed by soft saturation,
+/// used for radio, telephone and intercom style voices.
+pub struct BandLimiter {
+    sample_rate: f32,
+    tuned: Option<(f32, f32)>,
+    highpass: [Biquad; 2],
+    lowpass: [Biquad; 2],
+}
+
+impl BandLimiter {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            tuned: None,
+            highpass: [Biquad::default(); 2],
+            lowpass: [Biquad::default(); 2],
+        }
+    }
+
+    fn tune(&mut self, low_cut: f32, high_cut: f32) {
+        if self.tuned == Some((low_cut, high_cut)) {
+            return;
+        }
+        let highpass = Biquad::highpass(self.sample_rate, low_cut, SECTION_Q);
+        let lowpass = Biquad::lowpass(self.sample_rate, high_cut, SECTION_Q);
+        for section in self.highpass.iter_mut() {
+            section.retune(&highpass);
+        }
+        for section in self.lowpass.iter_mut() {
+            section.retune(&lowpass);
+        }
+        self.tuned = Some((low_cut, high_cut));
+    }
+}
+
+impl Stage for BandLimiter {
+    type Params = BandLimitParams;
+
+    fn process(&mut self, block: &mut [f32], params: &BandLimitParams) {
+        self.tune(params.low_cut, params.high_cut);
+        let drive = params.drive.max(1.0);
+        // Keep the level roughly constant as drive increases
+        let makeup = 1.0 / drive.tanh();
+
+        for sample in block.iter_mut() {
+            let mut x = *sample;
+            for section in self.highpass.iter_mut().chain(self.lowpass.iter_mut()) {
+                x = section.process(x);
+            }
+            *sample = if drive > 1.0 { (x * drive).tanh() * makeup / drive } else { x };
+        }
+    }
+
+    fn reset(&mut self) {
+        for section in self.highpass.iter_mut().chain(self.lowpass.iter_mut()) {
+            section.reset();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+
+/// Second-order IIR section (RBJ audio EQ cookbook), transposed direct form II.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn from_coefficients(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn lowpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        Self::from_coefficients(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn highpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        Self::from_coefficients(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    /// Band-pass with 0 dB gain at the centre frequency.
+    pub fn bandpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        Self::from_coefficients(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    /// Notch removing a narrow band around `frequency`.
+    pub fn notch(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        Self::from_coefficients(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    /// Bell boosting (or cutting) by `gain_db` around `frequency`.
+    pub fn peaking(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        Self::from_coefficients(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    /// Low shelf boosting (or cutting) by `gain_db` below `frequency`.
+    pub fn low_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let beta = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            a * ((a + 1.0) - (a - 1.0) * cos + beta),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - beta),
+            (a + 1.0) + (a - 1.0) * cos + beta,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - beta,
+        )
+    }
+
+    /// High shelf boosting (or cutting) by `gain_db` above `frequency`.
+    pub fn high_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let beta = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            a * ((a + 1.0) + (a - 1.0) * cos + beta),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - beta),
+            (a + 1.0) - (a - 1.0) * cos + beta,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - beta,
+        )
+    }
+
+    fn prewarp(sample_rate: f32, frequency: f32, q: f32) -> (f32, f32) {
+        let frequency = frequency.clamp(10.0, sample_rate * 0.49);
+        let omega = 2.0 * PI * frequency / sample_rate;
+        (omega.cos(), omega.sin() / (2.0 * q.max(0.01)))
+    }
+
+    /// Magnitude response at `frequency`, in dB.
+    pub fn magnitude_db(&self, sample_rate: f32, frequency: f32) -> f32 {
+        let omega = 2.0 * PI * frequency / sample_rate;
+        let z1 = Complex::from_polar(1.0, -omega);
+        let z2 = z1 * z1;
+        let numerator = self.b0 + z1 * self.b1 + z2 * self.b2;
+        let denominator = 1.0 + z1 * self.a1 + z2 * self.a2;
+        20.0 * (numerator.norm() / denominator.norm()).max(1e-9).log10()
+    }
+
+    /// Replace the coefficients with those of `other`, keeping the filter state
+    /// so parameter changes do not click.
+    pub fn retune(&mut self, other: &Biquad) {
+        self.b0 = other.b0;
+        self.b1 = other.b1;
+        self.b2 = other.b2;
+        self.a1 = other.a1;
+        self.a2 = other.a2;
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
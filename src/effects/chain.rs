@@ -0,0 +1,759 @@
+use super::agc::{Agc, AgcParams};
+use super::anonymizer::{fresh_seed, Anonymizer, AnonymizerParams, Disguise};
+use super::autotune::{AutoTune, AutoTuneParams};
+use super::band_limit::{BandLimitParams, BandLimiter};
+use super::conversion::{ConversionParams, VoiceConverter};
+use super::convolution::{ConvolutionParams, Convolver};
+use super::deess::{DeEsser, DeEsserParams, PlosiveParams, PlosiveSuppressor};
+use super::delay::{Delay, DelayParams};
+use super::denoise::{DenoiseParams, NoiseLearner, SpectralDenoiser};
+use super::distortion::{Bitcrusher, BitcrusherParams, Distortion, DistortionParams};
+use super::dynamics::{Compressor, CompressorParams, Limiter, LimiterParams};
+use super::echo::{EchoCanceller, EchoParams};
+use super::eq::{EqParams, Equalizer};
+use super::feedback::{FeedbackParams, FeedbackSuppressor, NotchStatus};
+use super::formant::{FormantParams, FormantShifter};
+use super::gate::{AutoGate, AutoGateParams};
+use super::harmonizer::{Harmonizer, HarmonizerParams};
+use super::morph::{Alignment, MorphPosition, MorphPresets};
+use super::modulation::{Chorus, ChorusParams, Flanger, FlangerParams, Tremolo, TremoloParams, Vibrato, VibratoParams};
+use super::pitch_shift::{PhaseVocoder, PitchAlgorithm, PitchShiftParams};
+use super::psola::Psola;
+use super::reverb::{Reverb, ReverbParams};
+use super::ring_mod::{RingModParams, RingModulator};
+use super::vad::{VadEvent, VadParams, VoiceActivityDetector};
+use super::vocoder::{ChannelVocoder, VocoderParams};
+use super::whisper::{Whisper, WhisperParams};
+use super::Slot;
+use crate::voices::VoiceSelection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Voice activity changes kept until the interfaces collect them.
+const MAX_VAD_EVENTS: usize = 64;
+
+/// Parameters for every stage in the processing chain.
+///
+/// The whole struct is serializable so it can be sent to the web interface and
+/// individual values can be addressed by dotted path (e.g. `pitch.semitones`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ChainSettings {
+    pub echo: EchoParams,
+    pub vad: VadParams,
+    pub denoise: DenoiseParams,
+    pub auto_gate: AutoGateParams,
+    pub plosive: PlosiveParams,
+    pub autotune: AutoTuneParams,
+    pub pitch: PitchShiftParams,
+    pub formant: FormantParams,
+    pub conversion: ConversionParams,
+    pub anonymizer: AnonymizerParams,
+    pub whisper: WhisperParams,
+    pub vocoder: VocoderParams,
+    pub ring_mod: RingModParams,
+    pub distortion: DistortionParams,
+    pub bitcrusher: BitcrusherParams,
+    pub band_limit: BandLimitParams,
+    pub eq: EqParams,
+    pub deesser: DeEsserParams,
+    pub agc: AgcParams,
+    pub feedback: FeedbackParams,
+    pub compressor: CompressorParams,
+    pub vibrato: VibratoParams,
+    pub chorus: ChorusParams,
+    pub flanger: FlangerParams,
+    pub tremolo: TremoloParams,
+    pub convolution: ConvolutionParams,
+    pub harmonizer: HarmonizerParams,
+    pub reverb: ReverbParams,
+    pub delay: DelayParams,
+    pub limiter: LimiterParams,
+}
+
+impl ChainSettings {
+    /// Set a single parameter addressed by a dotted path such as `pitch.semitones`.
+    pub fn set(&mut self, path: &str, value: Value) -> anyhow::Result<()> {
+        let mut tree = serde_json::to_value(&*self)?;
+        let pointer = format!("/{}", path.trim().replace('.', "/"));
+        let slot = tree
+            .pointer_mut(&pointer)
+            .ok_or_else(|| anyhow::anyhow!("Unknown parameter: {}", path))?;
+        *slot = value;
+        *self = serde_json::from_value(tree)?;
+        Ok(())
+    }
+
+    /// Formant parameters with the phase vocoder's envelope movement folded in
+    /// when `formant.preserve` is set.
+    fn effective_formant(&self) -> FormantParams {
+        let (vocoder_on, _) = self.pitch_stages();
+        let mut formant = self.formant.clone();
+        if formant.preserve && vocoder_on {
+            formant.semitones -= self.pitch.semitones;
+        }
+        formant
+    }
+
+    /// Whether the phase vocoder and PSOLA shifters should run, respectively.
+    fn pitch_stages(&self) -> (bool, bool) {
+        let enabled = self.pitch.enabled;
+        match self.pitch.algorithm {
+            PitchAlgorithm::PhaseVocoder => (enabled, false),
+            PitchAlgorithm::Psola => (false, enabled),
+        }
+    }
+}
+
+/// Snapshot of the chain shown in the terminal and web interfaces.
+#[derive(Serialize, Debug, Clone)]
+pub struct ChainStatus {
+    pub settings: ChainSettings,
+    pub voice: Option<VoiceSelection>,
+    pub latency_ms: f32,
+    /// Speaker-to-microphone delay and echo reduction found by the echo
+    /// canceller, once known, and whether it hears both ends talking
+    pub echo_delay_ms: Option<f32>,
+    pub echo_erle_db: Option<f32>,
+    pub double_talk: bool,
+    /// Whether the voice activity detector hears speech, while it runs
+    pub voice_active: Option<bool>,
+    /// Fundamental found by the pitch tracker, when one is running and the input is voiced
+    pub detected_pitch: Option<f32>,
+    /// Pitch after correction, while pitch correction runs and the input is voiced
+    pub corrected_pitch: Option<f32>,
+    /// Whether the noise suppressor has a learned noise profile
+    pub noise_profile: bool,
+    /// Progress of the noise profile capture (0.0 - 1.0) while learning
+    pub noise_learning: Option<f32>,
+    /// Noise floor tracked by the automatic gate, in dBFS, while it runs
+    pub noise_floor_db: Option<f32>,
+    pub gate_open: bool,
+    /// Combined EQ response in dB from 0 Hz to Nyquist, while the EQ is on
+    pub eq_response: Option<Vec<f32>>,
+    /// Loudness measured by the AGC (LUFS) and the gain it applies (dB), while it runs
+    pub loudness_lufs: Option<f32>,
+    pub agc_gain_db: Option<f32>,
+    /// Notches placed by the feedback suppressor, while it runs
+    pub feedback_notches: Vec<NotchStatus>,
+    /// Gain reduction of the compressor and limiter over the last block, in dB
+    pub compressor_reduction_db: f32,
+    pub limiter_reduction_db: f32,
+    /// Gain reduction of the de-esser's upper band and the plosive
+    /// suppressor's low band over the last block, in dB
+    pub deesser_reduction_db: f32,
+    pub plosive_reduction_db: f32,
+    /// Length of the loaded impulse response, in ms, while convolution runs
+    pub impulse_ms: Option<f32>,
+    /// Median pitch of the target voice and of the live speaker, in Hz,
+    /// while voice conversion runs
+    pub target_pitch: Option<f32>,
+    pub speaker_pitch: Option<f32>,
+    /// Pitch, formant and warp settings the anonymizer applies, while it runs
+    pub disguise: Option<Disguise>,
+    /// Position between presets A (0.0) and B (1.0), while morphing
+    pub morph_amount: Option<f32>,
+}
+
+/// Two presets being morphed between. When they differ in more than
+/// continuous numbers, a second chain runs the blend with B's switches and
+/// algorithms, and the two outputs are crossfaded.
+pub struct Morph {
+    presets: Arc<MorphPresets>,
+    amount: f32,
+    /// Crossfade position reached at the end of the last block
+    fade: f32,
+    partner: Option<Chain>,
+    partner_block: Vec<f32>,
+    own_alignment: Alignment,
+    partner_alignment: Alignment,
+}
+
+impl Morph {
+    /// A morph between `presets` at A, with the second chain built if the
+    /// presets need one. Built before it is handed to the chain, so the audio
+    /// thread never waits for the allocation.
+    pub fn new(sample_rate: f32, presets: Arc<MorphPresets>) -> Box<Self> {
+        let partner = (!presets.interpolable()).then(|| Chain::new(sample_rate));
+        Box::new(Self {
+            presets,
+            amount: 0.0,
+            fade: 0.0,
+            partner,
+            partner_block: Vec::new(),
+            own_alignment: Alignment::new(),
+            partner_alignment: Alignment::new(),
+        })
+    }
+}
+
+/// The ordered set of processing stages applied to the gated input signal.
+pub struct Chain {
+    pub settings: ChainSettings,
+    /// Voice character the current settings were built from, if any
+    pub voice: Option<VoiceSelection>,
+    sample_rate: f32,
+    input_active: bool,
+    speaking: bool,
+    vad_events: VecDeque<VadEvent>,
+    samples_processed: u64,
+    /// Impulse response file the convolver was last given, loaded or not
+    impulse_source: Option<String>,
+    /// Target voice recording the converter was last given, analysed or not
+    target_source: Option<String>,
+    /// Left/right difference of the delay's ping-pong echoes, aligned with
+    /// the limited mid signal, and the queue doing the alignment
+    stereo_side: Vec<f32>,
+    side_queue: VecDeque<f32>,
+    morph: Option<Box<Morph>>,
+    noise_learner: NoiseLearner,
+    echo: Slot<EchoCanceller>,
+    vad: Slot<VoiceActivityDetector>,
+    denoise: Slot<SpectralDenoiser>,
+    auto_gate: Slot<AutoGate>,
+    plosive: Slot<PlosiveSuppressor>,
+    autotune: Slot<AutoTune>,
+    phase_vocoder: Slot<PhaseVocoder>,
+    psola: Slot<Psola>,
+    formant: Slot<FormantShifter>,
+    conversion: Slot<VoiceConverter>,
+    anonymizer: Slot<Anonymizer>,
+    whisper: Slot<Whisper>,
+    vocoder: Slot<ChannelVocoder>,
+    ring_mod: Slot<RingModulator>,
+    distortion: Slot<Distortion>,
+    bitcrusher: Slot<Bitcrusher>,
+    band_limit: Slot<BandLimiter>,
+    eq: Slot<Equalizer>,
+    deesser: Slot<DeEsser>,
+    agc: Slot<Agc>,
+    feedback: Slot<FeedbackSuppressor>,
+    compressor: Slot<Compressor>,
+    vibrato: Slot<Vibrato>,
+    chorus: Slot<Chorus>,
+    flanger: Slot<Flanger>,
+    tremolo: Slot<Tremolo>,
+    convolution: Slot<Convolver>,
+    harmonizer: Slot<Harmonizer>,
+    reverb: Slot<Reverb>,
+    delay: Slot<Delay>,
+    limiter: Slot<Limiter>,
+}
+
+impl Chain {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            settings: ChainSettings::default(),
+            voice: None,
+            sample_rate,
+            input_active: true,
+            speaking: false,
+            vad_events: VecDeque::new(),
+            samples_processed: 0,
+            impulse_source: None,
+            target_source: None,
+            stereo_side: Vec::new(),
+            side_queue: VecDeque::new(),
+            morph: None,
+            noise_learner: NoiseLearner::new(),
+            echo: Slot::new(EchoCanceller::new(sample_rate)),
+            vad: Slot::new(VoiceActivityDetector::new(sample_rate)),
+            denoise: Slot::new(SpectralDenoiser::new()),
+            auto_gate: Slot::new(AutoGate::new(sample_rate)),
+            plosive: Slot::new(PlosiveSuppressor::new(sample_rate)),
+            autotune: Slot::new(AutoTune::new(sample_rate)),
+            phase_vocoder: Slot::new(PhaseVocoder::new()),
+            psola: Slot::new(Psola::new(sample_rate)),
+            formant: Slot::new(FormantShifter::new()),
+            conversion: Slot::new(VoiceConverter::new(sample_rate, None)),
+            anonymizer: Slot::new(Anonymizer::new(sample_rate)),
+            whisper: Slot::new(Whisper::new()),
+            vocoder: Slot::new(ChannelVocoder::new(sample_rate)),
+            ring_mod: Slot::new(RingModulator::new(sample_rate)),
+            distortion: Slot::new(Distortion::new(sample_rate)),
+            bitcrusher: Slot::new(Bitcrusher::new(sample_rate)),
+            band_limit: Slot::new(BandLimiter::new(sample_rate)),
+            eq: Slot::new(Equalizer::new(sample_rate)),
+            deesser: Slot::new(DeEsser::new(sample_rate)),
+            agc: Slot::new(Agc::new(sample_rate)),
+            feedback: Slot::new(FeedbackSuppressor::new(sample_rate)),
+            compressor: Slot::new(Compressor::new(sample_rate)),
+            vibrato: Slot::new(Vibrato::new(sample_rate)),
+            chorus: Slot::new(Chorus::new(sample_rate)),
+            flanger: Slot::new(Flanger::new(sample_rate)),
+            tremolo: Slot::new(Tremolo::new(sample_rate)),
+            convolution: Slot::new(Convolver::new(&[])),
+            harmonizer: Slot::new(Harmonizer::new(sample_rate)),
+            reverb: Slot::new(Reverb::new(sample_rate)),
+            delay: Slot::new(Delay::new(sample_rate)),
+            limiter: Slot::new(Limiter::new(sample_rate)),
+        }
+    }
+
+    pub fn process(&mut self, block: &mut [f32]) {
+        let Some(mut morph) = self.morph.take() else {
+            self.run_stages(block);
+            return;
+        };
+        let Some(partner) = morph.partner.as_mut() else {
+            self.run_stages(block);
+            self.morph = Some(morph);
+            return;
+        };
+
+        morph.partner_block.clear();
+        morph.partner_block.extend_from_slice(block);
+        partner.process(&mut morph.partner_block);
+        self.run_stages(block);
+
+        // Line the two outputs up on the later one, then crossfade
+        let own_latency = self.stages_latency();
+        let partner_latency = partner.latency();
+        let latency = own_latency.max(partner_latency);
+        let stereo = !self.stereo_side.is_empty() || !partner.stereo_side.is_empty();
+        let mut partner_side = std::mem::take(&mut partner.stereo_side);
+        morph.own_alignment.process(latency - own_latency, block, &mut self.stereo_side);
+        morph.partner_alignment.process(latency - partner_latency, &mut morph.partner_block, &mut partner_side);
+
+        // Ramp over the block so a jump of the crossfader does not click
+        let (start, end) = (morph.fade, morph.amount);
+        let len = block.len();
+        for (i, (own, other)) in block.iter_mut().zip(&morph.partner_block).enumerate() {
+            let fade = start + (end - start) * (i + 1) as f32 / len as f32;
+            *own = *own * (1.0 - fade) + other * fade;
+            self.stereo_side[i] = self.stereo_side[i] * (1.0 - fade) + partner_side[i] * fade;
+        }
+        if !stereo {
+            self.stereo_side.clear();
+        }
+        partner.stereo_side = partner_side;
+        morph.fade = end;
+        self.morph = Some(morph);
+    }
+
+    fn run_stages(&mut self, block: &mut [f32]) {
+        self.echo.run(self.settings.echo.enabled, block, &self.settings.echo);
+        self.detect_voice(block);
+
+        // Room tone is only captured while nobody speaks
+        if !self.speaking {
+            if let Some(profile) = self.noise_learner.push(block) {
+                self.settings.denoise.profile = Some(profile);
+                self.settings.denoise.enabled = true;
+            }
+        }
+
+        // The seed is picked once and kept in the settings, where the
+        // interfaces show it and presets save it
+        let anonymizer = &mut self.settings.anonymizer;
+        if anonymizer.enabled && anonymizer.seed.is_none() {
+            anonymizer.seed = Some(fresh_seed());
+        }
+
+        let settings = &self.settings;
+        let (vocoder_on, psola_on) = settings.pitch_stages();
+        self.denoise.run(settings.denoise.enabled, block, &settings.denoise);
+        self.auto_gate.stage.set_speech(self.speaking);
+        self.auto_gate.run(settings.auto_gate.enabled, block, &settings.auto_gate);
+        self.plosive.run(settings.plosive.enabled, block, &settings.plosive);
+        self.autotune.run(settings.autotune.enabled, block, &settings.autotune);
+        self.phase_vocoder.run(vocoder_on, block, &settings.pitch);
+        self.psola.run(psola_on, block, &settings.pitch);
+        self.formant.run(settings.formant.enabled, block, &settings.effective_formant());
+        self.conversion.run(self.conversion_on(), block, &settings.conversion);
+        self.anonymizer.run(settings.anonymizer.enabled, block, &settings.anonymizer);
+        self.whisper.run(settings.whisper.enabled, block, &settings.whisper);
+        self.vocoder.run(settings.vocoder.enabled, block, &settings.vocoder);
+        self.ring_mod.run(settings.ring_mod.enabled, block, &settings.ring_mod);
+        self.distortion.run(settings.distortion.enabled, block, &settings.distortion);
+        self.bitcrusher.run(settings.bitcrusher.enabled, block, &settings.bitcrusher);
+        self.band_limit.run(settings.band_limit.enabled, block, &settings.band_limit);
+        self.eq.run(settings.eq.enabled, block, &settings.eq);
+        self.deesser.run(settings.deesser.enabled, block, &settings.deesser);
+        let gate_open = !settings.auto_gate.enabled || self.auto_gate.stage.is_open();
+        let voice_present = !settings.vad.enabled || self.speaking;
+        self.agc.stage.set_active(self.input_active && gate_open && voice_present);
+        self.agc.run(settings.agc.enabled, block, &settings.agc);
+        self.feedback.run(settings.feedback.enabled, block, &settings.feedback);
+        self.compressor.run(settings.compressor.enabled, block, &settings.compressor);
+        self.vibrato.run(settings.vibrato.enabled, block, &settings.vibrato);
+        self.chorus.run(settings.chorus.enabled, block, &settings.chorus);
+        self.flanger.run(settings.flanger.enabled, block, &settings.flanger);
+        self.tremolo.run(settings.tremolo.enabled, block, &settings.tremolo);
+        self.convolution.run(self.convolution_on(), block, &settings.convolution);
+        self.harmonizer.run(settings.harmonizer.enabled, block, &settings.harmonizer);
+        self.reverb.run(settings.reverb.enabled, block, &settings.reverb);
+        self.delay.run(settings.delay.enabled, block, &settings.delay);
+        self.limiter.run(settings.limiter.enabled, block, &settings.limiter);
+        self.align_stereo_side(block.len());
+        self.samples_processed += block.len() as u64;
+    }
+
+    /// The side signal skips the limiter: delay it by the lookahead and
+    /// follow the gain reduction so the left and right channels stay in step.
+    /// The harmonies' side also skips the reverb and delay, so it takes on
+    /// their dry share to keep its balance with the mid.
+    fn align_stereo_side(&mut self, len: usize) {
+        self.stereo_side.clear();
+        let (delay, reverb) = (&self.settings.delay, &self.settings.reverb);
+        let ping_pong = delay.enabled && delay.ping_pong;
+        let harmonies = self.settings.harmonizer.enabled;
+        if !(ping_pong || harmonies) {
+            self.side_queue.clear();
+            return;
+        }
+
+        let limiter_on = self.settings.limiter.enabled;
+        let lookahead = self.limiter.latency(limiter_on);
+        let gain = if limiter_on {
+            10.0f32.powf(-self.limiter.stage.gain_reduction_db() / 20.0)
+        } else {
+            1.0
+        };
+        let dry_share = |enabled: bool, mix: f32| if enabled { 1.0 - mix.clamp(0.0, 1.0) } else { 1.0 };
+        let harmony_gain = dry_share(reverb.enabled, reverb.mix) * dry_share(delay.enabled, delay.mix);
+        for i in 0..len {
+            let echo = if ping_pong { self.delay.stage.side()[i] } else { 0.0 };
+            let harmony = if harmonies { self.harmonizer.stage.side()[i] * harmony_gain } else { 0.0 };
+            self.side_queue.push_back(echo + harmony);
+        }
+        while self.side_queue.len() < lookahead + len {
+            self.side_queue.push_front(0.0);
+        }
+        while self.side_queue.len() > lookahead + len {
+            self.side_queue.pop_front();
+        }
+        self.stereo_side
+            .extend(self.side_queue.drain(..len).map(|side| side * gain));
+    }
+
+    /// Left minus right over two for the last processed block, when the delay
+    /// bounces its echoes between the channels or the harmonizer pans its
+    /// voices; empty otherwise. A stereo
+    /// output plays the block plus and minus this.
+    pub fn stereo_side(&self) -> &[f32] {
+        &self.stereo_side
+    }
+
+    /// Run the voice activity detector and queue its start/stop events.
+    fn detect_voice(&mut self, block: &mut [f32]) {
+        let enabled = self.settings.vad.enabled;
+        self.vad.run(enabled, block, &self.settings.vad);
+
+        let mut changes: Vec<(usize, bool)> = if enabled {
+            self.vad.stage.transitions().to_vec()
+        } else {
+            Vec::new()
+        };
+        // Switching the detector off ends any speech in progress
+        if !enabled && self.speaking {
+            changes.push((0, false));
+        }
+        for (offset, speaking) in changes {
+            if self.vad_events.len() >= MAX_VAD_EVENTS {
+                self.vad_events.pop_front();
+            }
+            self.vad_events.push_back(VadEvent {
+                speaking,
+                time_s: (self.samples_processed + offset as u64) as f32 / self.sample_rate,
+            });
+            self.speaking = speaking;
+        }
+    }
+
+    /// Voice activity changes since the last call, oldest first.
+    pub fn take_vad_events(&mut self) -> Vec<VadEvent> {
+        self.vad_events.drain(..).collect()
+    }
+
+    /// Hand the chain the samples just sent to the speakers, the far-end
+    /// reference of the echo canceller.
+    pub fn push_echo_reference(&mut self, samples: &[f32]) {
+        self.echo.stage.push_reference(samples);
+        if let Some(partner) = self.morph_partner() {
+            partner.push_echo_reference(samples);
+        }
+    }
+
+    /// Tell the chain whether the input currently carries signal, as judged by
+    /// the envelope noise gate in the input callback.
+    pub fn set_input_active(&mut self, active: bool) {
+        self.input_active = active;
+        if let Some(partner) = self.morph_partner() {
+            partner.set_input_active(active);
+        }
+    }
+
+    /// Replace the settings with a voice character, or restore the defaults
+    /// when `selection` is `None`. A learned noise profile belongs to the room,
+    /// not the voice, and is kept.
+    pub fn apply_voice(&mut self, selection: Option<VoiceSelection>) {
+        let profile = self.settings.denoise.profile.take();
+        self.settings = match &selection {
+            Some(selection) => selection.voice.settings(&selection.macros),
+            None => ChainSettings::default(),
+        };
+        self.settings.denoise.profile = profile;
+        self.voice = selection;
+        self.morph = None;
+    }
+
+    /// Replace the settings with a preset. A learned noise profile is kept
+    /// unless the preset brings its own.
+    pub fn apply_preset(&mut self, settings: ChainSettings) {
+        replace_settings(&mut self.settings, settings);
+        self.voice = None;
+        self.morph = None;
+    }
+
+    /// Install a morph built with [`Morph::new`] and return the one it
+    /// replaces, to be dropped once the chain is unlocked. The settings follow
+    /// with the first [`Chain::set_morph`].
+    pub fn start_morph(&mut self, mut morph: Box<Morph>) -> Option<Box<Morph>> {
+        if let Some(partner) = morph.partner.as_mut() {
+            partner.input_active = self.input_active;
+        }
+        self.voice = None;
+        self.morph.replace(morph)
+    }
+
+    /// Move the morph to a position blended from its presets. Positions of
+    /// a morph that has since been replaced or stopped are ignored.
+    pub fn set_morph(&mut self, position: MorphPosition) {
+        let Some(morph) = self.morph.as_mut() else {
+            return;
+        };
+        if !Arc::ptr_eq(&morph.presets, &position.presets) {
+            return;
+        }
+        morph.amount = position.amount;
+        replace_settings(&mut self.settings, position.own);
+        if let (Some(partner), Some(settings)) = (morph.partner.as_mut(), position.partner) {
+            replace_settings(&mut partner.settings, settings);
+        }
+    }
+
+    /// End the morph, keeping the settings at the current position. The
+    /// morph is returned to be dropped once the chain is unlocked.
+    pub fn stop_morph(&mut self) -> Option<Box<Morph>> {
+        self.morph.take()
+    }
+
+    /// Presets of the running morph, to blend new positions from.
+    pub fn morph_presets(&self) -> Option<Arc<MorphPresets>> {
+        self.morph.as_ref().map(|morph| Arc::clone(&morph.presets))
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Position of the morph, while one is running.
+    pub fn morph_amount(&self) -> Option<f32> {
+        self.morph.as_ref().map(|morph| morph.amount)
+    }
+
+    fn morph_partner(&mut self) -> Option<&mut Chain> {
+        self.morph.as_mut()?.partner.as_mut()
+    }
+
+    /// Start recording `seconds` of room tone; once complete the averaged
+    /// spectrum becomes the noise suppressor's profile and the suppressor is
+    /// switched on.
+    pub fn learn_noise(&mut self, seconds: f32) {
+        self.noise_learner.start(seconds, self.sample_rate);
+        if let Some(partner) = self.morph_partner() {
+            partner.learn_noise(seconds);
+        }
+    }
+
+    /// Remove the notches placed by the feedback suppressor, e.g. after the
+    /// microphone or speakers were moved.
+    pub fn reset_feedback(&mut self) {
+        self.feedback.stage.clear();
+        if let Some(partner) = self.morph_partner() {
+            partner.reset_feedback();
+        }
+    }
+
+    /// Impulse response file named in the settings that the convolver does
+    /// not have yet, or that the morph partner's convolver does not have.
+    pub fn pending_impulse(&self) -> Option<String> {
+        self.own_pending_impulse().or_else(|| {
+            let partner = self.morph.as_ref()?.partner.as_ref()?;
+            partner.pending_impulse()
+        })
+    }
+
+    fn own_pending_impulse(&self) -> Option<String> {
+        let wanted = self.settings.convolution.impulse.as_ref()?;
+        (self.impulse_source.as_ref() != Some(wanted)).then(|| wanted.clone())
+    }
+
+    /// Hand the convolver prepared for `source` to the chain; a convolver
+    /// without a response (the file could not be read) keeps the stage off.
+    pub fn install_impulse(&mut self, source: String, convolver: Convolver) {
+        if self.own_pending_impulse().as_ref() != Some(&source) {
+            if let Some(partner) = self.morph_partner() {
+                return partner.install_impulse(source, convolver);
+            }
+        }
+        self.convolution = Slot::new(convolver);
+        self.impulse_source = Some(source);
+    }
+
+    /// Target voice recording named in the settings that the converter does
+    /// not have yet, or that the morph partner's converter does not have.
+    pub fn pending_target(&self) -> Option<String> {
+        self.own_pending_target().or_else(|| {
+            let partner = self.morph.as_ref()?.partner.as_ref()?;
+            partner.pending_target()
+        })
+    }
+
+    fn own_pending_target(&self) -> Option<String> {
+        let wanted = self.settings.conversion.target.as_ref()?;
+        (self.target_source.as_ref() != Some(wanted)).then(|| wanted.clone())
+    }
+
+    /// Hand the converter prepared for `source` to the chain; a converter
+    /// without a target (the recording could not be used) keeps the stage off.
+    pub fn install_target(&mut self, source: String, converter: VoiceConverter) {
+        if self.own_pending_target().as_ref() != Some(&source) {
+            if let Some(partner) = self.morph_partner() {
+                return partner.install_target(source, converter);
+            }
+        }
+        self.conversion = Slot::new(converter);
+        self.target_source = Some(source);
+    }
+
+    fn conversion_on(&self) -> bool {
+        self.settings.conversion.enabled
+            && self.settings.conversion.target.is_some()
+            && self.conversion.stage.is_loaded()
+    }
+
+    fn convolution_on(&self) -> bool {
+        self.settings.convolution.enabled
+            && self.settings.convolution.impulse.is_some()
+            && self.convolution.stage.is_loaded()
+    }
+
+    /// Progress of the noise profile capture (0.0 - 1.0), or `None` when idle.
+    pub fn noise_learning(&self) -> Option<f32> {
+        self.noise_learner.progress()
+    }
+
+    /// Whether the envelope noise gate in the input callback should let the
+    /// signal through untouched: while the suppressor or the automatic gate
+    /// replaces it, while the echo canceller needs the echo unaltered to model
+    /// it, and while learning so the room tone is captured at its real level.
+    pub fn gate_bypassed(&self) -> bool {
+        let denoise = &self.settings.denoise;
+        (denoise.enabled && denoise.bypass_gate)
+            || self.settings.auto_gate.enabled
+            || self.settings.echo.enabled
+            || self.noise_learning().is_some()
+    }
+
+    /// Total delay added by the enabled stages, in samples; while morphing,
+    /// that of the slower of the two chains.
+    pub fn latency(&self) -> usize {
+        let partner = self.morph.as_ref().and_then(|morph| morph.partner.as_ref());
+        let partner_latency = partner.map_or(0, |partner| partner.latency());
+        self.stages_latency().max(partner_latency)
+    }
+
+    fn stages_latency(&self) -> usize {
+        let (vocoder_on, psola_on) = self.settings.pitch_stages();
+        self.echo.latency(self.settings.echo.enabled)
+            + self.denoise.latency(self.settings.denoise.enabled)
+            + self.auto_gate.latency(self.settings.auto_gate.enabled)
+            + self.autotune.latency(self.settings.autotune.enabled)
+            + self.phase_vocoder.latency(vocoder_on)
+            + self.psola.latency(psola_on)
+            + self.formant.latency(self.settings.formant.enabled)
+            + self.conversion.latency(self.conversion_on())
+            + self.anonymizer.latency(self.settings.anonymizer.enabled)
+            + self.whisper.latency(self.settings.whisper.enabled)
+            + self.distortion.latency(self.settings.distortion.enabled)
+            + self.vibrato.latency(self.settings.vibrato.enabled)
+            + self.convolution.latency(self.convolution_on())
+            + self.limiter.latency(self.settings.limiter.enabled)
+    }
+
+    pub fn latency_ms(&self) -> f32 {
+        self.latency() as f32 * 1000.0 / self.sample_rate
+    }
+
+    pub fn status(&self) -> ChainStatus {
+        let (_, psola_on) = self.settings.pitch_stages();
+        let mut settings = self.settings.clone();
+        // The profile is hundreds of numbers; the interfaces only need to know it exists
+        let noise_profile = settings.denoise.profile.take().is_some();
+        let echo_on = settings.echo.enabled;
+        let vad_on = settings.vad.enabled;
+        let auto_gate_on = settings.auto_gate.enabled;
+        let eq_response = settings.eq.enabled.then(|| settings.eq.response(self.sample_rate));
+        let agc_on = settings.agc.enabled;
+        let feedback_on = settings.feedback.enabled;
+        let compressor_on = settings.compressor.enabled;
+        let limiter_on = settings.limiter.enabled;
+        let deesser_on = settings.deesser.enabled;
+        let plosive_on = settings.plosive.enabled;
+        let autotune_on = settings.autotune.enabled;
+        let anonymizer_on = settings.anonymizer.enabled;
+        let detected_pitch = if autotune_on {
+            self.autotune.stage.detected()
+        } else if psola_on {
+            self.psola.stage.frequency()
+        } else {
+            None
+        };
+        let impulse_ms = self
+            .convolution_on()
+            .then(|| self.convolution.stage.impulse_len() as f32 * 1000.0 / self.sample_rate);
+        let conversion_on = self.conversion_on();
+        ChainStatus {
+            settings,
+            voice: self.voice,
+            latency_ms: self.latency_ms(),
+            echo_delay_ms: if echo_on { self.echo.stage.delay_ms() } else { None },
+            echo_erle_db: if echo_on { self.echo.stage.erle_db() } else { None },
+            double_talk: echo_on && self.echo.stage.double_talk(),
+            voice_active: vad_on.then_some(self.speaking),
+            detected_pitch,
+            corrected_pitch: if autotune_on { self.autotune.stage.corrected() } else { None },
+            noise_profile,
+            noise_learning: self.noise_learning(),
+            noise_floor_db: if auto_gate_on { self.auto_gate.stage.noise_floor_db() } else { None },
+            gate_open: auto_gate_on && self.auto_gate.stage.is_open(),
+            eq_response,
+            loudness_lufs: if agc_on { self.agc.stage.loudness_lufs() } else { None },
+            agc_gain_db: agc_on.then(|| self.agc.stage.gain_db()),
+            feedback_notches: if feedback_on { self.feedback.stage.notches() } else { Vec::new() },
+            compressor_reduction_db: if compressor_on { self.compressor.stage.gain_reduction_db() } else { 0.0 },
+            limiter_reduction_db: if limiter_on { self.limiter.stage.gain_reduction_db() } else { 0.0 },
+            deesser_reduction_db: if deesser_on { self.deesser.stage.gain_reduction_db() } else { 0.0 },
+            plosive_reduction_db: if plosive_on { self.plosive.stage.gain_reduction_db() } else { 0.0 },
+            impulse_ms,
+            target_pitch: self.conversion.stage.target().filter(|_| conversion_on).map(|target| target.median_pitch()),
+            speaker_pitch: if conversion_on { self.conversion.stage.speaker_pitch() } else { None },
+            disguise: anonymizer_on.then(|| self.anonymizer.stage.current()),
+            morph_amount: self.morph_amount(),
+        }
+    }
+}
+
+/// Put blended settings in place, keeping a learned noise profile the
+/// presets do not have.
+fn replace_settings(settings: &mut ChainSettings, blended: ChainSettings) {
+    let profile = settings.denoise.profile.take();
+    *settings = blended;
+    if settings.denoise.profile.is_none() {
+        settings.denoise.profile = profile;
+    }
+}
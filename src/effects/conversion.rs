@@ -0,0 +1,404 @@
+use super::formant::SpectralEnvelope;
+use super::pitch_detect::PitchTracker;
+use super::pitch_shift::{PitchAlgorithm, PitchShiftParams, MAX_SEMITONES};
+use super::psola::Psola;
+use super::scale::frequency_to_midi_note;
+use super::stft::Stft;
+use super::Stage;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const FRAME_SIZE: usize = 1024;
+const OVERLAP: usize = 4;
+
+/// Cepstral coefficients kept for the envelopes, as in the formant shifter.
+const LIFTER: usize = 30;
+
+/// Largest boost or cut the envelope mapping applies to a single bin.
+const MAX_CORRECTION_DB: f32 = 18.0;
+
+/// Pitch range searched in both the recording and the live voice, in Hz.
+const MIN_FREQUENCY: f32 = 70.0;
+const MAX_FREQUENCY: f32 = 500.0;
+
+/// Frames of the recording more than this far below its loudest frame are
+/// pauses and breaths, and are left out of the average envelope, in dB.
+const SPEECH_RANGE_DB: f32 = 30.0;
+
+/// Live frames quieter than this never update the speaker's envelope, in dBFS.
+const SILENCE_DB: f32 = -55.0;
+
+/// Samples between updates of the pitch shift, and between the pitch
+/// estimates kept for the speaker's statistics.
+const CONTROL_BLOCK: usize = 64;
+const PITCH_INTERVAL: usize = 512;
+
+/// Samples of the recording passed to the analysis STFT at a time.
+const ANALYSIS_BLOCK: usize = 4096;
+
+/// Estimates of the speaker's pitch needed before it is moved, so the first
+/// syllable does not set the median on its own.
+const MIN_PITCH_ESTIMATES: usize = 8;
+
+/// Longest time the speaker's envelope and pitch are averaged over, in seconds.
+const MAX_ADAPT_S: f32 = 60.0;
+
+/// Limits of the factor scaling the speaker's pitch range to the target's.
+const MAX_RANGE_RATIO: f32 = 4.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ConversionParams {
+    pub enabled: bool,
+    /// WAV recording of the target voice; a minute of plain speech gives
+    /// stable statistics, a few seconds already work
+    pub target: Option<String>,
+    /// How far the speaker's average spectral envelope (timbre) moves to the
+    /// target's, 0.0 - 1.0
+    pub envelope: f32,
+    /// How far the speaker's median pitch moves to the target's, 0.0 - 1.0
+    pub pitch: f32,
+    /// How far the width of the speaker's intonation moves to the target's,
+    /// 0.0 - 1.0
+    pub range: f32,
+    /// Time over which the speaker's envelope and pitch are averaged, in seconds
+    pub adapt_s: f32,
+}
+
+impl Default for ConversionParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: None,
+            envelope: 1.0,
+            pitch: 1.0,
+            range: 0.5,
+            adapt_s: 5.0,
+        }
+    }
+}
+
+/// Long-term statistics of a voice: its average spectral envelope and the
+/// median and spread of its pitch.
+#[derive(Debug, Clone)]
+pub struct VoiceProfile {
+    /// Natural-log envelope per bin, with its mean over the bins removed so
+    /// only the shape is compared
+    envelope: Vec<f32>,
+    /// Median pitch, as a MIDI note number
+    median_note: f32,
+    /// Distance between the upper and lower quartile of the pitch, in semitones
+    range: f32,
+}
+
+impl VoiceProfile {
+    /// Analyse a recording of speech at `sample_rate`, or `None` when it holds
+    /// no voiced speech.
+    pub fn analyze(samples: &[f32], sample_rate: f32) -> Option<Self> {
+        let mut tracker = PitchTracker::new(sample_rate, MIN_FREQUENCY, MAX_FREQUENCY);
+        let mut notes = Vec::new();
+        for chunk in samples.chunks(PITCH_INTERVAL) {
+            chunk.iter().for_each(|&sample| tracker.push(sample));
+            notes.extend(tracker.frequency().map(frequency_to_midi_note));
+        }
+        if notes.len() < MIN_PITCH_ESTIMATES {
+            return None;
+        }
+
+        // Two passes over the recording, so that no frame has to be kept: the
+        // first finds the loudest frame, the second averages the envelopes of
+        // the frames close enough to it to be speech
+        let mut loudest = 0.0f32;
+        for_each_frame(samples, |magnitude| loudest = loudest.max(energy(magnitude)));
+        let threshold = loudest * 10.0f32.powf(-SPEECH_RANGE_DB / 10.0);
+
+        let mut estimator = SpectralEnvelope::new(FRAME_SIZE, LIFTER);
+        let mut envelope = vec![0.0; FRAME_SIZE / 2 + 1];
+        let mut average = vec![0.0; envelope.len()];
+        let mut speech_frames = 0;
+        for_each_frame(samples, |magnitude| {
+            if energy(magnitude) > threshold {
+                estimator.estimate(magnitude, &mut envelope);
+                average.iter_mut().zip(&envelope).for_each(|(total, value)| *total += value);
+                speech_frames += 1;
+            }
+        });
+        let count = speech_frames.max(1) as f32;
+        average.iter_mut().for_each(|total| *total /= count);
+        remove_mean(&mut average);
+
+        let (median_note, range) = pitch_statistics(&mut notes);
+        Some(Self { envelope: average, median_note, range })
+    }
+
+    /// Median pitch in Hz.
+    pub fn median_pitch(&self) -> f32 {
+        440.0 * 2.0f32.powf((self.median_note - 69.0) / 12.0)
+    }
+
+    /// Spread of the pitch (interquartile range) in semitones.
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+}
+
+/// Run `samples` through a fresh STFT a block at a time, handing over the
+/// magnitude spectrum of every frame.
+fn for_each_frame(samples: &[f32], mut on_frame: impl FnMut(&[f32])) {
+    let mut stft = Stft::new(FRAME_SIZE, OVERLAP);
+    let mut magnitude = vec![0.0; stft.bins()];
+    let mut block = vec![0.0; ANALYSIS_BLOCK];
+    for chunk in samples.chunks(ANALYSIS_BLOCK) {
+        let block = &mut block[..chunk.len()];
+        block.copy_from_slice(chunk);
+        stft.process(block, |spectrum| {
+            for (m, bin) in magnitude.iter_mut().zip(spectrum.iter()) {
+                *m = bin.norm();
+            }
+            on_frame(&magnitude);
+        });
+    }
+}
+
+fn energy(magnitude: &[f32]) -> f32 {
+    magnitude.iter().map(|m| m * m).sum()
+}
+
+/// Pitch estimates kept to cover `adapt_s` seconds.
+fn history_len(adapt_s: f32, sample_rate: f32) -> usize {
+    ((adapt_s * sample_rate / PITCH_INTERVAL as f32) as usize).max(1)
+}
+
+fn remove_mean(envelope: &mut [f32]) {
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    envelope.iter_mut().for_each(|value| *value -= mean);
+}
+
+/// Median and interquartile range of `notes`, which get reordered.
+fn pitch_statistics(notes: &mut [f32]) -> (f32, f32) {
+    let last = (notes.len() - 1) as f32;
+    let index = |q: f32| (last * q).round() as usize;
+    let (lower, middle, upper) = (index(0.25), index(0.5), index(0.75));
+    let (below, median, above) = notes.select_nth_unstable_by(middle, f32::total_cmp);
+    let median = *median;
+    let lower = if lower < middle {
+        *below.select_nth_unstable_by(lower, f32::total_cmp).1
+    } else {
+        median
+    };
+    let upper = if upper > middle {
+        *above.select_nth_unstable_by(upper - middle - 1, f32::total_cmp).1
+    } else {
+        median
+    };
+    (median, upper - lower)
+}
+
+/// Moves the speaker's voice towards a recorded target voice, without any
+/// trained model: the pitch is shifted (PSOLA) so that the speaker's median
+/// pitch lands on the target's and the intonation widens or narrows to its
+/// range, then a slowly adapting filter maps the speaker's long-term average
+/// spectral envelope onto the target's, carrying over the vocal tract
+/// resonances and brightness. Moment-to-moment changes of the envelope, the
+/// vowels and consonants, pass through.
+pub struct VoiceConverter {
+    sample_rate: f32,
+    target: Option<VoiceProfile>,
+    psola: Psola,
+    shift: PitchShiftParams,
+    /// Recent pitch estimates of the speaker, as MIDI note numbers
+    notes: VecDeque<f32>,
+    /// Copy of `notes` the statistics are taken from, kept so that updating
+    /// them does not allocate
+    ordered: Vec<f32>,
+    /// Speaker's median pitch and range over `notes`
+    speaker: Option<(f32, f32)>,
+    since_estimate: usize,
+    stft: Stft,
+    state: EnvelopeState,
+}
+
+struct EnvelopeState {
+    estimator: SpectralEnvelope,
+    magnitude: Vec<f32>,
+    frame_envelope: Vec<f32>,
+    /// Running average of the speaker's log envelope, once speech was heard
+    average: Option<Vec<f32>>,
+    shape: Vec<f32>,
+    correction: Vec<f32>,
+    /// Running averages of the speech energy before and after the mapping
+    energy_in: f32,
+    energy_out: f32,
+}
+
+impl VoiceConverter {
+    /// A converter towards `target`; without one the stage passes the voice
+    /// through.
+    pub fn new(sample_rate: f32, target: Option<VoiceProfile>) -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+        let max_history = history_len(MAX_ADAPT_S, sample_rate);
+        Self {
+            sample_rate,
+            target,
+            psola: Psola::new(sample_rate),
+            shift: PitchShiftParams {
+                enabled: true,
+                algorithm: PitchAlgorithm::Psola,
+                ..PitchShiftParams::default()
+            },
+            notes: VecDeque::with_capacity(max_history),
+            ordered: Vec::with_capacity(max_history),
+            speaker: None,
+            since_estimate: 0,
+            stft,
+            state: EnvelopeState {
+                estimator: SpectralEnvelope::new(FRAME_SIZE, LIFTER),
+                magnitude: vec![0.0; bins],
+                frame_envelope: vec![0.0; bins],
+                average: None,
+                shape: vec![0.0; bins],
+                correction: vec![0.0; bins],
+                energy_in: 0.0,
+                energy_out: 0.0,
+            },
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.target.is_some()
+    }
+
+    pub fn target(&self) -> Option<&VoiceProfile> {
+        self.target.as_ref()
+    }
+
+    /// Speaker's median pitch in Hz, once enough of the voice was heard.
+    pub fn speaker_pitch(&self) -> Option<f32> {
+        self.speaker.map(|(median, _)| 440.0 * 2.0f32.powf((median - 69.0) / 12.0))
+    }
+
+    /// Keep the latest pitch estimate for the speaker's statistics.
+    fn track_pitch(&mut self, len: usize, history: usize) {
+        self.since_estimate += len;
+        if self.since_estimate < PITCH_INTERVAL {
+            return;
+        }
+        self.since_estimate -= PITCH_INTERVAL;
+        let Some(frequency) = self.psola.frequency() else {
+            return;
+        };
+        while self.notes.len() >= history {
+            self.notes.pop_front();
+        }
+        self.notes.push_back(frequency_to_midi_note(frequency));
+        if self.notes.len() >= MIN_PITCH_ESTIMATES {
+            self.ordered.clear();
+            self.ordered.extend(self.notes.iter().copied());
+            self.speaker = Some(pitch_statistics(&mut self.ordered));
+        }
+    }
+
+    /// Shift in semitones for the note now sung.
+    fn pitch_shift(&self, params: &ConversionParams, target: &VoiceProfile) -> f32 {
+        let (Some(frequency), Some((median, range))) = (self.psola.frequency(), self.speaker) else {
+            return self.shift.semitones;
+        };
+        let deviation = frequency_to_midi_note(frequency) - median;
+        let ratio = (target.range / range.max(0.1)).clamp(1.0 / MAX_RANGE_RATIO, MAX_RANGE_RATIO);
+        let scaled = deviation * ratio.powf(params.range.clamp(0.0, 1.0));
+        let shift = params.pitch.clamp(0.0, 1.0) * (target.median_note - median) + scaled - deviation;
+        shift.clamp(-MAX_SEMITONES, MAX_SEMITONES)
+    }
+}
+
+impl Stage for VoiceConverter {
+    type Params = ConversionParams;
+
+    fn process(&mut self, block: &mut [f32], params: &ConversionParams) {
+        let Some(target) = self.target.take() else {
+            return;
+        };
+        let adapt_s = params.adapt_s.clamp(0.5, MAX_ADAPT_S);
+        let history = history_len(adapt_s, self.sample_rate);
+
+        for chunk in block.chunks_mut(CONTROL_BLOCK) {
+            self.track_pitch(chunk.len(), history);
+            self.shift.semitones = self.pitch_shift(params, &target);
+            self.psola.process(chunk, &self.shift);
+        }
+
+        let frames_per_s = self.sample_rate / self.stft.hop() as f32;
+        let smoothing = (-1.0 / (adapt_s * frames_per_s)).exp();
+        let amount = params.envelope.clamp(0.0, 1.0);
+        let state = &mut self.state;
+        self.stft.process(block, |spectrum| state.map(spectrum, &target.envelope, amount, smoothing));
+        self.target = Some(target);
+    }
+
+    fn latency(&self) -> usize {
+        self.psola.latency() + self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.psola.reset();
+        self.shift.semitones = 0.0;
+        self.notes.clear();
+        self.speaker = None;
+        self.since_estimate = 0;
+        self.stft.reset();
+        self.state.average = None;
+        self.state.energy_in = 0.0;
+        self.state.energy_out = 0.0;
+    }
+}
+
+impl EnvelopeState {
+    fn map(&mut self, spectrum: &mut [Complex<f32>], target: &[f32], amount: f32, smoothing: f32) {
+        for (m, bin) in self.magnitude.iter_mut().zip(spectrum.iter()) {
+            *m = bin.norm();
+        }
+
+        // Only speech updates the average, so pauses do not pull it towards
+        // the room's noise
+        let energy: f32 = self.magnitude.iter().map(|m| m * m).sum();
+        let level_db = 10.0 * (energy / (FRAME_SIZE * FRAME_SIZE) as f32 + 1e-12).log10();
+        if level_db > SILENCE_DB {
+            self.estimator.estimate(&self.magnitude, &mut self.frame_envelope);
+            match self.average.as_mut() {
+                Some(average) => {
+                    for (avg, &value) in average.iter_mut().zip(&self.frame_envelope) {
+                        *avg = value + (*avg - value) * smoothing;
+                    }
+                }
+                None => self.average = Some(self.frame_envelope.clone()),
+            }
+        }
+        let Some(average) = self.average.as_ref() else {
+            return;
+        };
+
+        self.shape.copy_from_slice(average);
+        remove_mean(&mut self.shape);
+        let max_correction = MAX_CORRECTION_DB / 20.0 * std::f32::consts::LN_10;
+        for ((correction, &speaker), &wanted) in self.correction.iter_mut().zip(&self.shape).zip(target) {
+            *correction = (amount * (wanted - speaker)).clamp(-max_correction, max_correction);
+        }
+
+        // Keep the long-term level: the envelopes are compared by shape only
+        if level_db > SILENCE_DB {
+            let filtered: f32 = self.magnitude.iter().zip(&self.correction).map(|(m, c)| (m * c.exp()).powi(2)).sum();
+            self.energy_in = energy + (self.energy_in - energy) * smoothing;
+            self.energy_out = filtered + (self.energy_out - filtered) * smoothing;
+        }
+        let makeup = if self.energy_in > 0.0 && self.energy_out > 0.0 {
+            0.5 * (self.energy_in / self.energy_out).ln()
+        } else {
+            0.0
+        };
+        for (bin, &correction) in spectrum.iter_mut().zip(&self.correction) {
+            *bin *= (correction + makeup).exp();
+        }
+    }
+}
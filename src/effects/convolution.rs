@@ -0,0 +1,301 @@
+use super::Stage;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Block sizes of the partition tiers. The first sets the latency (2.9 ms at
+/// 44.1 kHz); later parts of the response use larger, cheaper blocks. A tier
+/// with block size B starts B - HEAD_BLOCK samples into the response, so its
+/// output is always ready before it is due.
+const TIER_BLOCKS: [usize; 3] = [128, 1024, 8192];
+const HEAD_BLOCK: usize = TIER_BLOCKS[0];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ConvolutionParams {
+    pub enabled: bool,
+    /// Impulse response WAV file; loaded in the background when it changes
+    pub impulse: Option<String>,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (convolved only)
+    pub mix: f32,
+    /// Gain applied to the convolved signal, in dB
+    pub gain_db: f32,
+}
+
+impl Default for ConvolutionParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            impulse: None,
+            mix: 1.0,
+            gain_db: 0.0,
+        }
+    }
+}
+
+/// One tier of uniformly partitioned overlap-save convolution over a segment
+/// of the impulse response, with a frequency-domain delay line of past input
+/// blocks.
+struct Tier {
+    block: usize,
+    /// Position of the segment in the impulse response, in samples
+    offset: usize,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    partitions: Vec<Vec<Complex<f32>>>,
+    history: Vec<Vec<Complex<f32>>>,
+    newest: usize,
+    /// Previous and current input block
+    frame: Vec<f32>,
+    filled: usize,
+    buffer: Vec<f32>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl Tier {
+    fn new(planner: &mut RealFftPlanner<f32>, segment: &[f32], block: usize, offset: usize) -> Self {
+        let size = 2 * block;
+        let forward = planner.plan_fft_forward(size);
+        let inverse = planner.plan_fft_inverse(size);
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+        let mut buffer = forward.make_input_vec();
+        let mut scratch = vec![Complex::new(0.0, 0.0); scratch_len];
+
+        // The inverse transform is unnormalised; fold its scale into the filter
+        let scale = 1.0 / size as f32;
+        let partitions: Vec<Vec<Complex<f32>>> = segment
+            .chunks(block)
+            .map(|chunk| {
+                buffer.fill(0.0);
+                for (b, &h) in buffer.iter_mut().zip(chunk) {
+                    *b = h * scale;
+                }
+                let mut spectrum = forward.make_output_vec();
+                forward
+                    .process_with_scratch(&mut buffer, &mut spectrum, &mut scratch)
+                    .expect("buffer sizes match the plan");
+                spectrum
+            })
+            .collect();
+
+        Self {
+            block,
+            offset,
+            history: vec![forward.make_output_vec(); partitions.len()],
+            newest: 0,
+            frame: vec![0.0; size],
+            filled: 0,
+            accumulator: forward.make_output_vec(),
+            partitions,
+            buffer,
+            scratch,
+            forward,
+            inverse,
+        }
+    }
+
+    /// Append input; true once a whole block has been collected.
+    fn push(&mut self, input: &[f32]) -> bool {
+        let start = self.block + self.filled;
+        self.frame[start..start + input.len()].copy_from_slice(input);
+        self.filled += input.len();
+        self.filled == self.block
+    }
+
+    /// Convolve the collected block and add the result to `ring`, where
+    /// `block_start` is the ring position of the block's first input sample.
+    fn convolve(&mut self, ring: &mut [f32], block_start: usize) {
+        let count = self.partitions.len();
+        self.newest = (self.newest + 1) % count;
+        self.buffer.copy_from_slice(&self.frame);
+        let newest = &mut self.history[self.newest];
+        if self
+            .forward
+            .process_with_scratch(&mut self.buffer, newest, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        self.accumulator.fill(Complex::new(0.0, 0.0));
+        for (p, partition) in self.partitions.iter().enumerate() {
+            let input = &self.history[(self.newest + count - p) % count];
+            for ((acc, x), h) in self.accumulator.iter_mut().zip(input).zip(partition) {
+                *acc += x * h;
+            }
+        }
+        // DC and Nyquist are real for real signals; drop rounding residue
+        let last = self.accumulator.len() - 1;
+        self.accumulator[0].im = 0.0;
+        self.accumulator[last].im = 0.0;
+
+        if self
+            .inverse
+            .process_with_scratch(&mut self.accumulator, &mut self.buffer, &mut self.scratch)
+            .is_ok()
+        {
+            // Overlap-save: the second half holds the circular-free output
+            let len = ring.len();
+            for (i, &y) in self.buffer[self.block..].iter().enumerate() {
+                ring[(block_start + self.offset + i) % len] += y;
+            }
+        }
+
+        self.frame.copy_within(self.block.., 0);
+        self.filled = 0;
+    }
+
+    fn reset(&mut self) {
+        for spectrum in self.history.iter_mut() {
+            spectrum.fill(Complex::new(0.0, 0.0));
+        }
+        self.frame.fill(0.0);
+        self.filled = 0;
+    }
+}
+
+/// Non-uniformly partitioned FFT convolution with an impulse response.
+///
+/// The start of the response is convolved in short blocks for low latency,
+/// the rest in progressively longer ones to keep long reverbs affordable.
+/// Each tier adds its part of the output into a shared ring buffer, ahead of
+/// the time it is played.
+pub struct Convolver {
+    tiers: Vec<Tier>,
+    length: usize,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    position: usize,
+    ring: Vec<f32>,
+    /// Ring position of the current head block
+    clock: usize,
+}
+
+impl Convolver {
+    /// Prepare the partition spectra for `impulse`; an empty response gives a
+    /// convolver with nothing loaded.
+    pub fn new(impulse: &[f32]) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let mut tiers = Vec::new();
+        for (k, &block) in TIER_BLOCKS.iter().enumerate() {
+            let start = block - HEAD_BLOCK;
+            let end = TIER_BLOCKS
+                .get(k + 1)
+                .map_or(impulse.len(), |next| next - HEAD_BLOCK)
+                .min(impulse.len());
+            if start < end {
+                tiers.push(Tier::new(&mut planner, &impulse[start..end], block, start));
+            }
+        }
+        let reach = tiers.iter().map(|tier| tier.offset + tier.block).max().unwrap_or(0);
+
+        Self {
+            tiers,
+            length: impulse.len(),
+            input: vec![0.0; HEAD_BLOCK],
+            output: vec![0.0; HEAD_BLOCK],
+            position: 0,
+            ring: vec![0.0; reach + 2 * HEAD_BLOCK],
+            clock: 0,
+        }
+    }
+
+    /// Whether an impulse response is loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.length > 0
+    }
+
+    /// Length of the loaded impulse response, in samples.
+    pub fn impulse_len(&self) -> usize {
+        self.length
+    }
+
+    fn run_block(&mut self, params: &ConvolutionParams) {
+        let len = self.ring.len();
+        for tier in self.tiers.iter_mut() {
+            if tier.push(&self.input) {
+                let block_start = (self.clock + len + HEAD_BLOCK - tier.block) % len;
+                tier.convolve(&mut self.ring, block_start);
+            }
+        }
+
+        let mix = params.mix.clamp(0.0, 1.0);
+        let wet_gain = mix * 10.0f32.powf(params.gain_db / 20.0);
+        for (i, (output, &dry)) in self.output.iter_mut().zip(&self.input).enumerate() {
+            let slot = &mut self.ring[(self.clock + i) % len];
+            *output = dry * (1.0 - mix) + *slot * wet_gain;
+            *slot = 0.0;
+        }
+        self.clock = (self.clock + HEAD_BLOCK) % len;
+    }
+}
+
+impl Stage for Convolver {
+    type Params = ConvolutionParams;
+
+    fn process(&mut self, block: &mut [f32], params: &ConvolutionParams) {
+        for sample in block.iter_mut() {
+            self.input[self.position] = *sample;
+            *sample = self.output[self.position];
+            self.position += 1;
+            if self.position == HEAD_BLOCK {
+                self.position = 0;
+                self.run_block(params);
+            }
+        }
+    }
+
+    fn latency(&self) -> usize {
+        HEAD_BLOCK
+    }
+
+    fn reset(&mut self) {
+        for tier in self.tiers.iter_mut() {
+            tier.reset();
+        }
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.position = 0;
+        self.ring.fill(0.0);
+        self.clock = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::rng::Rng;
+
+    #[test]
+    fn matches_direct_convolution() {
+        let mut rng = Rng::new(3);
+        // Long enough to reach the second tier
+        let impulse: Vec<f32> = (0..1500).map(|i| (rng.next_f32() - 0.5) * (-(i as f32) / 400.0).exp()).collect();
+        let input: Vec<f32> = (0..6000).map(|_| rng.next_f32() - 0.5).collect();
+
+        let mut convolver = Convolver::new(&impulse);
+        assert!(convolver.is_loaded());
+        assert_eq!(convolver.impulse_len(), impulse.len());
+        let params = ConvolutionParams { enabled: true, ..ConvolutionParams::default() };
+        let mut output = Vec::new();
+        for chunk in input.chunks(100) {
+            let mut block = chunk.to_vec();
+            convolver.process(&mut block, &params);
+            output.extend(block);
+        }
+
+        let latency = convolver.latency();
+        for n in 0..input.len() - latency {
+            let expected: f32 = impulse.iter().take(n + 1).enumerate().map(|(k, h)| h * input[n - k]).sum();
+            let actual = output[n + latency];
+            assert!((expected - actual).abs() < 1e-3, "sample {}: expected {}, got {}", n, expected, actual);
+        }
+    }
+
+    #[test]
+    fn empty_impulse_loads_nothing() {
+        assert!(!Convolver::new(&[]).is_loaded());
+    }
+}
@@ -0,0 +1,242 @@
+use super::biquad::Biquad;
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Gain reduction above the de-esser threshold, as a compression ratio.
+const DEESS_RATIO: f32 = 4.0;
+
+/// Detector and gain times: sibilants and pops start within a millisecond,
+/// the release keeps the following vowel from pumping.
+const DEESS_ATTACK_MS: f32 = 1.0;
+const DEESS_RELEASE_MS: f32 = 50.0;
+const PLOSIVE_ATTACK_MS: f32 = 0.5;
+const PLOSIVE_RELEASE_MS: f32 = 80.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DeEsserParams {
+    pub enabled: bool,
+    /// Split frequency; only the band above it is turned down, in Hz
+    pub frequency: f32,
+    /// Level of the upper band above which it is reduced, in dBFS
+    pub threshold_db: f32,
+    /// Largest reduction of the upper band, in dB
+    pub max_reduction_db: f32,
+}
+
+impl Default for DeEsserParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: 6000.0,
+            threshold_db: -30.0,
+            max_reduction_db: 12.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PlosiveParams {
+    pub enabled: bool,
+    /// Split frequency; only the band below it is turned down, in Hz
+    pub cutoff: f32,
+    /// Level of the low band that can be a pop, in dBFS
+    pub threshold_db: f32,
+    /// Largest reduction of the low band, in dB
+    pub max_reduction_db: f32,
+}
+
+impl Default for PlosiveParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cutoff: 150.0,
+            threshold_db: -30.0,
+            max_reduction_db: 18.0,
+        }
+    }
+}
+
+fn to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-9).log10()
+}
+
+fn coefficient(ms: f32, sample_rate: f32) -> f32 {
+    (-1000.0 / (ms * sample_rate)).exp()
+}
+
+/// Second-order Linkwitz-Riley split. The low band and the inverted high band
+/// add up to an all-pass, so untouched bands keep their level, and unlike a
+/// "input minus low-pass" split the high band holds no leakage of low tones.
+struct BandSplit {
+    sample_rate: f32,
+    lowpass: Biquad,
+    highpass: Biquad,
+    tuned: f32,
+}
+
+impl BandSplit {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            lowpass: Biquad::default(),
+            highpass: Biquad::default(),
+            tuned: 0.0,
+        }
+    }
+
+    fn tune(&mut self, frequency: f32) {
+        let frequency = frequency.clamp(20.0, self.sample_rate * 0.45);
+        if frequency != self.tuned {
+            self.lowpass.retune(&Biquad::lowpass(self.sample_rate, frequency, 0.5));
+            self.highpass.retune(&Biquad::highpass(self.sample_rate, frequency, 0.5));
+            self.tuned = frequency;
+        }
+    }
+
+    /// Low and high band of one sample.
+    fn split(&mut self, input: f32) -> (f32, f32) {
+        (self.lowpass.process(input), -self.highpass.process(input))
+    }
+
+    fn reset(&mut self) {
+        self.lowpass.reset();
+        self.highpass.reset();
+    }
+}
+
+/// Attack/release envelope follower on the absolute value.
+struct Envelope {
+    level: f32,
+}
+
+impl Envelope {
+    fn follow(&mut self, input: f32, attack: f32, release: f32) -> f32 {
+        let input = input.abs();
+        let coeff = if input > self.level { attack } else { release };
+        self.level = input + (self.level - input) * coeff;
+        self.level
+    }
+}
+
+/// Split-band de-esser: the band above the split frequency is compressed
+/// when it gets loud, the rest of the voice is left alone.
+pub struct DeEsser {
+    sample_rate: f32,
+    split: BandSplit,
+    envelope: Envelope,
+    block_reduction_db: f32,
+}
+
+impl DeEsser {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            split: BandSplit::new(sample_rate),
+            envelope: Envelope { level: 0.0 },
+            block_reduction_db: 0.0,
+        }
+    }
+
+    /// Largest reduction of the upper band during the last block, in dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.block_reduction_db
+    }
+}
+
+impl Stage for DeEsser {
+    type Params = DeEsserParams;
+
+    fn process(&mut self, block: &mut [f32], params: &DeEsserParams) {
+        self.split.tune(params.frequency);
+        let attack = coefficient(DEESS_ATTACK_MS, self.sample_rate);
+        let release = coefficient(DEESS_RELEASE_MS, self.sample_rate);
+        let max_reduction = params.max_reduction_db.max(0.0);
+        let mut block_reduction: f32 = 0.0;
+
+        for sample in block.iter_mut() {
+            let (low, high) = self.split.split(*sample);
+            let level_db = to_db(self.envelope.follow(high, attack, release));
+            let reduction = ((level_db - params.threshold_db) * (1.0 - 1.0 / DEESS_RATIO))
+                .clamp(0.0, max_reduction);
+            block_reduction = block_reduction.max(reduction);
+            *sample = low + high * 10.0f32.powf(-reduction / 20.0);
+        }
+
+        self.block_reduction_db = block_reduction;
+    }
+
+    fn reset(&mut self) {
+        self.split.reset();
+        self.envelope.level = 0.0;
+        self.block_reduction_db = 0.0;
+    }
+}
+
+/// Plosive (mic pop) suppressor.
+///
+/// A pop is a burst of energy below the cutoff that towers over the rest of
+/// the spectrum; voiced speech always carries harmonics above it. While the
+/// low band is louder than the threshold and the upper band, it is turned
+/// down to the upper band's level.
+pub struct PlosiveSuppressor {
+    sample_rate: f32,
+    split: BandSplit,
+    low_envelope: Envelope,
+    high_envelope: Envelope,
+    reduction_db: f32,
+    block_reduction_db: f32,
+}
+
+impl PlosiveSuppressor {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            split: BandSplit::new(sample_rate),
+            low_envelope: Envelope { level: 0.0 },
+            high_envelope: Envelope { level: 0.0 },
+            reduction_db: 0.0,
+            block_reduction_db: 0.0,
+        }
+    }
+
+    /// Largest reduction of the low band during the last block, in dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.block_reduction_db
+    }
+}
+
+impl Stage for PlosiveSuppressor {
+    type Params = PlosiveParams;
+
+    fn process(&mut self, block: &mut [f32], params: &PlosiveParams) {
+        self.split.tune(params.cutoff);
+        let attack = coefficient(PLOSIVE_ATTACK_MS, self.sample_rate);
+        let release = coefficient(PLOSIVE_RELEASE_MS, self.sample_rate);
+        let max_reduction = params.max_reduction_db.max(0.0);
+        let mut block_reduction: f32 = 0.0;
+
+        for sample in block.iter_mut() {
+            let (low, high) = self.split.split(*sample);
+            let low_db = to_db(self.low_envelope.follow(low, attack, release));
+            let high_db = to_db(self.high_envelope.follow(high, attack, release));
+            let target = (low_db - params.threshold_db.max(high_db)).clamp(0.0, max_reduction);
+
+            let coeff = if target > self.reduction_db { attack } else { release };
+            self.reduction_db = target + (self.reduction_db - target) * coeff;
+            block_reduction = block_reduction.max(self.reduction_db);
+            *sample = low * 10.0f32.powf(-self.reduction_db / 20.0) + high;
+        }
+
+        self.block_reduction_db = block_reduction;
+    }
+
+    fn reset(&mut self) {
+        self.split.reset();
+        self.low_envelope.level = 0.0;
+        self.high_envelope.level = 0.0;
+        self.reduction_db = 0.0;
+        self.block_reduction_db = 0.0;
+    }
+}
@@ -0,0 +1,224 @@
+use super::biquad::Biquad;
+use super::modulation::FractionalDelay;
+use super::{Stage, BLOCK_CAPACITY};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_1_SQRT_2;
+
+/// Longest delay time, in ms.
+pub const MAX_DELAY_MS: f32 = 2000.0;
+
+/// Time constant with which the read position follows a new delay time. The
+/// echoes bend in pitch like a tape delay instead of jumping and clicking.
+const GLIDE_SECONDS: f32 = 0.1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteValue {
+    Whole,
+    Half,
+    Quarter,
+    DottedQuarter,
+    TripletQuarter,
+    #[default]
+    Eighth,
+    DottedEighth,
+    TripletEighth,
+    Sixteenth,
+}
+
+impl NoteValue {
+    /// Length in quarter-note beats.
+    pub fn beats(self) -> f32 {
+        match self {
+            NoteValue::Whole => 4.0,
+            NoteValue::Half => 2.0,
+            NoteValue::Quarter => 1.0,
+            NoteValue::DottedQuarter => 1.5,
+            NoteValue::TripletQuarter => 2.0 / 3.0,
+            NoteValue::Eighth => 0.5,
+            NoteValue::DottedEighth => 0.75,
+            NoteValue::TripletEighth => 1.0 / 3.0,
+            NoteValue::Sixteenth => 0.25,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DelayParams {
+    pub enabled: bool,
+    /// Delay time when not synced to a tempo, in ms
+    pub time_ms: f32,
+    /// Take the delay time from `note` at `bpm` instead of `time_ms`
+    pub sync: bool,
+    pub bpm: f32,
+    pub note: NoteValue,
+    /// Share of each echo fed back into the next, 0.0 - 0.95
+    pub feedback: f32,
+    /// Low-pass in the feedback path; every repeat gets darker, in Hz
+    pub high_cut: f32,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (echoes only)
+    pub mix: f32,
+    /// Bounce the echoes between the left and right channel on stereo outputs
+    pub ping_pong: bool,
+}
+
+impl Default for DelayParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time_ms: 350.0,
+            sync: false,
+            bpm: 120.0,
+            note: NoteValue::Eighth,
+            feedback: 0.35,
+            high_cut: 5000.0,
+            mix: 0.3,
+            ping_pong: false,
+        }
+    }
+}
+
+impl DelayParams {
+    /// Effective delay time, in ms.
+    pub fn delay_ms(&self) -> f32 {
+        let ms = if self.sync {
+            60_000.0 / self.bpm.clamp(20.0, 300.0) * self.note.beats()
+        } else {
+            self.time_ms
+        };
+        ms.clamp(1.0, MAX_DELAY_MS)
+    }
+}
+
+struct Line {
+    delay: FractionalDelay,
+    high_cut: Biquad,
+}
+
+impl Line {
+    fn new(max_delay: usize) -> Self {
+        Self {
+            delay: FractionalDelay::new(max_delay),
+            high_cut: Biquad::default(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delay.reset();
+        self.high_cut.reset();
+    }
+}
+
+/// Feedback delay with a darkening feedback path and optional ping-pong.
+///
+/// The chain is mono, so the stage returns the mid signal in the block and
+/// keeps the left/right difference of ping-pong echoes in `side`, from which
+/// a stereo output is rebuilt as mid + side and mid - side.
+pub struct Delay {
+    sample_rate: f32,
+    left: Line,
+    right: Line,
+    delay: f32,
+    tuned_high_cut: f32,
+    side: Vec<f32>,
+}
+
+impl Delay {
+    pub fn new(sample_rate: f32) -> Self {
+        let capacity = (MAX_DELAY_MS * sample_rate / 1000.0) as usize + 1;
+        Self {
+            sample_rate,
+            left: Line::new(capacity),
+            right: Line::new(capacity),
+            delay: 0.0,
+            tuned_high_cut: 0.0,
+            side: Vec::with_capacity(BLOCK_CAPACITY),
+        }
+    }
+
+    /// Left minus right over two for the last block; zero without ping-pong.
+    pub fn side(&self) -> &[f32] {
+        &self.side
+    }
+}
+
+impl Stage for Delay {
+    type Params = DelayParams;
+
+    fn process(&mut self, block: &mut [f32], params: &DelayParams) {
+        let high_cut = params.high_cut.clamp(200.0, self.sample_rate * 0.45);
+        if high_cut != self.tuned_high_cut {
+            let filter = Biquad::lowpass(self.sample_rate, high_cut, FRAC_1_SQRT_2);
+            self.left.high_cut.retune(&filter);
+            self.right.high_cut.retune(&filter);
+            self.tuned_high_cut = high_cut;
+        }
+
+        let target = params.delay_ms() * self.sample_rate / 1000.0;
+        if self.delay == 0.0 {
+            self.delay = target;
+        }
+        let glide = 1.0 - (-1.0 / (GLIDE_SECONDS * self.sample_rate)).exp();
+        let feedback = params.feedback.clamp(0.0, 0.95);
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        self.side.clear();
+        for sample in block.iter_mut() {
+            self.delay += (target - self.delay) * glide;
+            let dry = *sample;
+            let left = self.left.delay.read(self.delay);
+
+            let wet = if params.ping_pong {
+                // The input starts on the left; each repeat crosses over
+                let right = self.right.delay.read(self.delay);
+                let to_left = dry + feedback * self.right.high_cut.process(right);
+                let to_right = feedback * self.left.high_cut.process(left);
+                self.left.delay.write(to_left);
+                self.right.delay.write(to_right);
+                self.side.push(mix * 0.5 * (left - right));
+                0.5 * (left + right)
+            } else {
+                let to_left = dry + feedback * self.left.high_cut.process(left);
+                self.left.delay.write(to_left);
+                self.side.push(0.0);
+                left
+            };
+            *sample = dry * (1.0 - mix) + wet * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+        self.delay = 0.0;
+        self.side.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_time_is_used_unless_synced() {
+        let params = DelayParams { time_ms: 250.0, ..DelayParams::default() };
+        assert_eq!(params.delay_ms(), 250.0);
+    }
+
+    #[test]
+    fn synced_time_follows_the_tempo() {
+        let params = DelayParams { sync: true, bpm: 120.0, note: NoteValue::Quarter, ..DelayParams::default() };
+        assert_eq!(params.delay_ms(), 500.0);
+        let params = DelayParams { note: NoteValue::DottedEighth, ..params };
+        assert_eq!(params.delay_ms(), 375.0);
+    }
+
+    #[test]
+    fn delay_time_is_limited() {
+        let params = DelayParams { sync: true, bpm: 40.0, note: NoteValue::Whole, ..DelayParams::default() };
+        assert_eq!(params.delay_ms(), MAX_DELAY_MS);
+        let params = DelayParams { time_ms: 0.0, ..DelayParams::default() };
+        assert_eq!(params.delay_ms(), 1.0);
+    }
+}
@@ -0,0 +1,285 @@
+use super::stft::Stft;
+use super::Stage;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+
+const FRAME_SIZE: usize = 1024;
+const OVERLAP: usize = 4;
+
+/// Minimum statistics search: the minimum of the smoothed power is tracked over
+/// `SUBWINDOWS` windows of `SUBWINDOW_FRAMES` frames each (about 1.5 s at
+/// 44.1 kHz), long enough to bridge gaps between words.
+const SUBWINDOWS: usize = 8;
+const SUBWINDOW_FRAMES: usize = 32;
+
+/// Smoothing of the periodogram before the minimum search.
+const POWER_SMOOTHING: f32 = 0.85;
+
+/// The minimum of a smoothed noise periodogram sits below its mean; this
+/// factor compensates for that bias.
+const MINIMUM_BIAS: f32 = 1.5;
+
+/// Shortest and longest room tone capture, in seconds.
+pub const MIN_LEARN_SECONDS: f32 = 0.5;
+pub const MAX_LEARN_SECONDS: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DenoiseParams {
+    pub enabled: bool,
+    /// Maximum attenuation applied to noise-only bins, in dB
+    pub reduction_db: f32,
+    /// Decision-directed SNR smoothing (0.9 - 0.99); higher values suppress
+    /// musical noise at the cost of slightly smeared onsets
+    pub smoothing: f32,
+    /// Bypass the envelope noise gate while the suppressor runs
+    pub bypass_gate: bool,
+    /// Noise power per bin learned from room tone. When present it is used as
+    /// the noise estimate; the adaptive tracker only raises it if the noise
+    /// gets louder than it was while learning.
+    pub profile: Option<Vec<f32>>,
+}
+
+impl Default for DenoiseParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reduction_db: 20.0,
+            smoothing: 0.98,
+            bypass_gate: false,
+            profile: None,
+        }
+    }
+}
+
+/// STFT noise suppressor.
+///
+/// The noise power of every bin is estimated with minimum statistics (the
+/// minimum of the smoothed power over the last ~1.5 s), and a Wiener gain is
+/// computed from a decision-directed a priori SNR estimate. The gain is floored
+/// at `-reduction_db` and smoothed across neighbouring bins, which keeps the
+/// residual noise steady instead of "musical".
+pub struct SpectralDenoiser {
+    stft: Stft,
+    state: DenoiseState,
+}
+
+struct DenoiseState {
+    smoothed_power: Vec<f32>,
+    subwindow_minimum: Vec<f32>,
+    window_minima: Vec<Vec<f32>>,
+    frames_in_subwindow: usize,
+    subwindow_index: usize,
+    noise: Vec<f32>,
+    previous_clean: Vec<f32>,
+    gain: Vec<f32>,
+    smoothed_gain: Vec<f32>,
+    initialized: bool,
+}
+
+impl SpectralDenoiser {
+    pub fn new() -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+
+        Self {
+            stft,
+            state: DenoiseState {
+                smoothed_power: vec![0.0; bins],
+                subwindow_minimum: vec![f32::MAX; bins],
+                window_minima: vec![vec![f32::MAX; bins]; SUBWINDOWS],
+                frames_in_subwindow: 0,
+                subwindow_index: 0,
+                noise: vec![0.0; bins],
+                previous_clean: vec![0.0; bins],
+                gain: vec![1.0; bins],
+                smoothed_gain: vec![1.0; bins],
+                initialized: false,
+            },
+        }
+    }
+}
+
+impl Default for SpectralDenoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stage for SpectralDenoiser {
+    type Params = DenoiseParams;
+
+    fn process(&mut self, block: &mut [f32], params: &DenoiseParams) {
+        let floor = 10.0f32.powf(-params.reduction_db.clamp(0.0, 60.0) / 20.0);
+        let smoothing = params.smoothing.clamp(0.0, 0.999);
+        let profile = params
+            .profile
+            .as_deref()
+            .filter(|profile| profile.len() == self.stft.bins());
+        let state = &mut self.state;
+        self.stft.process(block, |spectrum| state.suppress(spectrum, profile, floor, smoothing));
+    }
+
+    fn latency(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+        self.state.initialized = false;
+    }
+}
+
+impl DenoiseState {
+    fn suppress(
+        &mut self,
+        spectrum: &mut [Complex<f32>],
+        profile: Option<&[f32]>,
+        floor: f32,
+        smoothing: f32,
+    ) {
+        if !self.initialized {
+            for (k, bin) in spectrum.iter().enumerate() {
+                let power = bin.norm_sqr();
+                self.smoothed_power[k] = power;
+                self.noise[k] = profile.map_or(power, |profile| profile[k]);
+                self.previous_clean[k] = 0.0;
+            }
+            self.subwindow_minimum.fill(f32::MAX);
+            for minima in self.window_minima.iter_mut() {
+                minima.fill(f32::MAX);
+            }
+            self.initialized = true;
+        }
+
+        self.track_noise(spectrum, profile);
+
+        for (k, bin) in spectrum.iter().enumerate() {
+            let noise = self.noise[k].max(1e-12);
+            let posterior_snr = bin.norm_sqr() / noise;
+            let prior_snr = smoothing * self.previous_clean[k] / noise
+                + (1.0 - smoothing) * (posterior_snr - 1.0).max(0.0);
+            let gain = (prior_snr / (1.0 + prior_snr)).max(floor);
+
+            self.gain[k] = gain;
+            self.previous_clean[k] = gain * gain * bin.norm_sqr();
+        }
+
+        // Smooth the gains across frequency to avoid isolated musical-noise tones
+        let bins = spectrum.len();
+        for k in 0..bins {
+            let low = k.saturating_sub(1);
+            let high = (k + 1).min(bins - 1);
+            self.smoothed_gain[k] = (self.gain[low] + 2.0 * self.gain[k] + self.gain[high]) / 4.0;
+        }
+
+        for (bin, &gain) in spectrum.iter_mut().zip(&self.smoothed_gain) {
+            *bin *= gain;
+        }
+    }
+
+    /// Minimum statistics noise power tracking, never below the learned profile.
+    fn track_noise(&mut self, spectrum: &[Complex<f32>], profile: Option<&[f32]>) {
+        for (k, bin) in spectrum.iter().enumerate() {
+            self.smoothed_power[k] = POWER_SMOOTHING * self.smoothed_power[k]
+                + (1.0 - POWER_SMOOTHING) * bin.norm_sqr();
+            self.subwindow_minimum[k] = self.subwindow_minimum[k].min(self.smoothed_power[k]);
+        }
+
+        self.frames_in_subwindow += 1;
+        if self.frames_in_subwindow >= SUBWINDOW_FRAMES {
+            self.frames_in_subwindow = 0;
+            self.window_minima[self.subwindow_index].copy_from_slice(&self.subwindow_minimum);
+            self.subwindow_index = (self.subwindow_index + 1) % SUBWINDOWS;
+            self.subwindow_minimum.fill(f32::MAX);
+        }
+
+        for k in 0..self.noise.len() {
+            let window_minimum = self
+                .window_minima
+                .iter()
+                .fold(self.subwindow_minimum[k], |min, minima| min.min(minima[k]));
+            let tracked = if window_minimum < f32::MAX {
+                window_minimum * MINIMUM_BIAS
+            } else {
+                self.noise[k]
+            };
+            self.noise[k] = match profile {
+                Some(profile) => tracked.max(profile[k]),
+                None => tracked,
+            };
+        }
+    }
+}
+
+/// Captures a noise profile by averaging the power of every bin over a stretch
+/// of room tone, using the same framing as [`SpectralDenoiser`].
+pub struct NoiseLearner {
+    stft: Stft,
+    scratch: Vec<f32>,
+    sum: Vec<f32>,
+    frames: usize,
+    target_frames: usize,
+}
+
+impl NoiseLearner {
+    pub fn new() -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+
+        Self {
+            stft,
+            scratch: Vec::new(),
+            sum: vec![0.0; bins],
+            frames: 0,
+            target_frames: 0,
+        }
+    }
+
+    /// Start capturing `seconds` of input (limited to the capture range),
+    /// abandoning any capture in progress.
+    pub fn start(&mut self, seconds: f32, sample_rate: f32) {
+        let frames = seconds.clamp(MIN_LEARN_SECONDS, MAX_LEARN_SECONDS) * sample_rate / self.stft.hop() as f32;
+        self.stft.reset();
+        self.sum.fill(0.0);
+        self.frames = 0;
+        self.target_frames = frames.ceil() as usize;
+    }
+
+    /// Fraction of the capture completed, or `None` when not learning.
+    pub fn progress(&self) -> Option<f32> {
+        (self.target_frames > 0).then(|| self.frames as f32 / self.target_frames as f32)
+    }
+
+    /// Analyze a block of input. Returns the averaged per-bin noise power once
+    /// the capture is complete.
+    pub fn push(&mut self, block: &[f32]) -> Option<Vec<f32>> {
+        if self.target_frames == 0 {
+            return None;
+        }
+
+        self.scratch.clear();
+        self.scratch.extend_from_slice(block);
+        let sum = &mut self.sum;
+        let frames = &mut self.frames;
+        self.stft.process(&mut self.scratch, |spectrum| {
+            for (total, bin) in sum.iter_mut().zip(spectrum.iter()) {
+                *total += bin.norm_sqr();
+            }
+            *frames += 1;
+        });
+
+        if self.frames < self.target_frames {
+            return None;
+        }
+        self.target_frames = 0;
+        let count = self.frames as f32;
+        Some(self.sum.iter().map(|total| total / count).collect())
+    }
+}
+
+impl Default for NoiseLearner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,376 @@
+use super::{Stage, BLOCK_CAPACITY};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Length of the half-band filters of each 2x oversampling step. Of the form
+/// 4k + 1, so the filter delay is a whole number of samples at both rates.
+const HALFBAND_TAPS: usize = 33;
+
+/// Number of 2x steps at the highest oversampling (X4).
+const MAX_STEPS: usize = 2;
+
+/// Offset of the tube curve's operating point; the asymmetry adds the even
+/// harmonics of a triode, the resulting DC is filtered out afterwards.
+const TUBE_BIAS: f32 = 0.3;
+
+/// Corner of the DC blocker after the waveshaper, in Hz.
+const DC_BLOCK_HZ: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    /// Symmetric tanh saturation, odd harmonics only
+    #[default]
+    SoftClip,
+    /// Asymmetric saturation with even harmonics
+    Tube,
+    /// Wavefolder: peaks above full scale are reflected back down
+    Fold,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Oversampling {
+    Off,
+    #[default]
+    X2,
+    X4,
+}
+
+impl Oversampling {
+    fn stages(self) -> usize {
+        match self {
+            Oversampling::Off => 0,
+            Oversampling::X2 => 1,
+            Oversampling::X4 => 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DistortionParams {
+    pub enabled: bool,
+    pub shape: Shape,
+    /// Gain into the waveshaper, in dB
+    pub drive_db: f32,
+    /// Gain after the waveshaper, in dB
+    pub output_db: f32,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (distorted only)
+    pub mix: f32,
+    /// Run the waveshaper at a multiple of the sample rate, so the harmonics
+    /// it creates above Nyquist are filtered out instead of folding back
+    pub oversampling: Oversampling,
+}
+
+impl Default for DistortionParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shape: Shape::SoftClip,
+            drive_db: 12.0,
+            output_db: -6.0,
+            mix: 1.0,
+            oversampling: Oversampling::X2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BitcrusherParams {
+    pub enabled: bool,
+    /// Resolution of the output, 1 - 16 bits; fractions give in-between steps
+    pub bits: f32,
+    /// Rate at which new samples are taken and held, in Hz
+    pub sample_rate: f32,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (crushed only)
+    pub mix: f32,
+}
+
+impl Default for BitcrusherParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bits: 8.0,
+            sample_rate: 8000.0,
+            mix: 1.0,
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+/// Blackman-windowed half-band low-pass with unity gain at DC; every other
+/// tap but the centre one is zero.
+fn halfband_taps() -> Vec<f32> {
+    let centre = (HALFBAND_TAPS / 2) as f32;
+    let mut taps: Vec<f32> = (0..HALFBAND_TAPS)
+        .map(|n| {
+            let t = n as f32 - centre;
+            let sinc = if t == 0.0 { 1.0 } else { (0.5 * PI * t).sin() / (0.5 * PI * t) };
+            let w = 2.0 * PI * n as f32 / (HALFBAND_TAPS - 1) as f32;
+            let window = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f32 = taps.iter().sum();
+    for tap in taps.iter_mut() {
+        *tap /= sum;
+    }
+    taps
+}
+
+/// FIR filter over a sliding window of input samples.
+struct Halfband {
+    history: VecDeque<f32>,
+}
+
+impl Halfband {
+    fn new() -> Self {
+        Self {
+            history: VecDeque::from(vec![0.0; HALFBAND_TAPS]),
+        }
+    }
+
+    fn push(&mut self, input: f32) {
+        self.history.pop_front();
+        self.history.push_back(input);
+    }
+
+    fn output(&self, taps: &[f32]) -> f32 {
+        self.history.iter().zip(taps).map(|(x, h)| x * h).sum()
+    }
+
+    fn reset(&mut self) {
+        self.history.iter_mut().for_each(|x| *x = 0.0);
+    }
+}
+
+/// One 2x step: zero-stuffing interpolator on the way up, filtering
+/// decimator on the way down.
+struct Step {
+    up: Halfband,
+    down: Halfband,
+}
+
+/// Delay of `stages` up and down filter steps, in samples at the base rate.
+fn oversampling_latency(stages: usize) -> usize {
+    // Each step delays by HALFBAND_TAPS - 1 samples at its raised rate
+    (0..stages).map(|k| (HALFBAND_TAPS - 1) >> (k + 1)).sum()
+}
+
+/// Cascade of 2x steps around a nonlinear function. The steps and buffers
+/// for the highest rate are allocated up front; the first `active` steps run.
+struct Oversampler {
+    taps: Vec<f32>,
+    steps: Vec<Step>,
+    active: usize,
+    up: Vec<f32>,
+    scratch: Vec<f32>,
+}
+
+impl Oversampler {
+    fn new() -> Self {
+        Self {
+            taps: halfband_taps(),
+            steps: (0..MAX_STEPS).map(|_| Step { up: Halfband::new(), down: Halfband::new() }).collect(),
+            active: 0,
+            up: Vec::with_capacity(BLOCK_CAPACITY << MAX_STEPS),
+            scratch: Vec::with_capacity(BLOCK_CAPACITY << MAX_STEPS),
+        }
+    }
+
+    /// Switch the number of 2x steps, starting from silence.
+    fn set_stages(&mut self, stages: usize) {
+        self.active = stages.min(MAX_STEPS);
+        self.reset();
+    }
+
+    /// Delay of the up and down filters, in samples at the base rate.
+    fn latency(&self) -> usize {
+        oversampling_latency(self.active)
+    }
+
+    fn process(&mut self, block: &mut [f32], mut shape: impl FnMut(f32) -> f32) {
+        if self.active == 0 {
+            block.iter_mut().for_each(|x| *x = shape(*x));
+            return;
+        }
+
+        self.up.clear();
+        self.up.extend_from_slice(block);
+        for step in self.steps[..self.active].iter_mut() {
+            self.scratch.clear();
+            for &x in self.up.iter() {
+                // The zero in between halves the level; the gain of 2 restores it
+                step.up.push(2.0 * x);
+                self.scratch.push(step.up.output(&self.taps));
+                step.up.push(0.0);
+                self.scratch.push(step.up.output(&self.taps));
+            }
+            std::mem::swap(&mut self.up, &mut self.scratch);
+        }
+
+        self.up.iter_mut().for_each(|x| *x = shape(*x));
+
+        for step in self.steps[..self.active].iter_mut().rev() {
+            self.scratch.clear();
+            for pair in self.up.chunks(2) {
+                // Keep the outputs in phase with the original samples
+                step.down.push(pair[0]);
+                self.scratch.push(step.down.output(&self.taps));
+                step.down.push(pair[1]);
+            }
+            std::mem::swap(&mut self.up, &mut self.scratch);
+        }
+        block.copy_from_slice(&self.up);
+    }
+
+    fn reset(&mut self) {
+        for step in self.steps.iter_mut() {
+            step.up.reset();
+            step.down.reset();
+        }
+    }
+}
+
+fn waveshape(shape: Shape, x: f32) -> f32 {
+    match shape {
+        Shape::SoftClip => x.tanh(),
+        Shape::Tube => (x + TUBE_BIAS).tanh() - TUBE_BIAS.tanh(),
+        Shape::Fold => {
+            // Triangle wave through (0, 0) with period 4: reflects at +-1
+            let t = (x + 1.0).rem_euclid(4.0);
+            if t < 2.0 {
+                t - 1.0
+            } else {
+                3.0 - t
+            }
+        }
+    }
+}
+
+/// Waveshaping saturation, optionally oversampled against aliasing.
+///
+/// The dry signal is delayed by the oversampling filters so that blending it
+/// with the distorted one does not comb-filter.
+pub struct Distortion {
+    sample_rate: f32,
+    oversampler: Oversampler,
+    oversampling: Option<Oversampling>,
+    dry: VecDeque<f32>,
+    dry_block: Vec<f32>,
+    dc_input: f32,
+    dc_output: f32,
+}
+
+impl Distortion {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            oversampler: Oversampler::new(),
+            oversampling: None,
+            dry: VecDeque::with_capacity(oversampling_latency(MAX_STEPS) + 1),
+            dry_block: Vec::with_capacity(BLOCK_CAPACITY),
+            dc_input: 0.0,
+            dc_output: 0.0,
+        }
+    }
+
+    fn configure(&mut self, oversampling: Oversampling) {
+        if self.oversampling != Some(oversampling) {
+            self.oversampler.set_stages(oversampling.stages());
+            self.dry.clear();
+            self.dry.resize(self.oversampler.latency(), 0.0);
+            self.oversampling = Some(oversampling);
+        }
+    }
+}
+
+impl Stage for Distortion {
+    type Params = DistortionParams;
+
+    fn process(&mut self, block: &mut [f32], params: &DistortionParams) {
+        self.configure(params.oversampling);
+        let drive = db_to_gain(params.drive_db.clamp(0.0, 48.0));
+        let output = db_to_gain(params.output_db.clamp(-48.0, 12.0));
+        let mix = params.mix.clamp(0.0, 1.0);
+        let dc_coeff = (-2.0 * PI * DC_BLOCK_HZ / self.sample_rate).exp();
+
+        self.dry_block.clear();
+        for &x in block.iter() {
+            self.dry.push_back(x);
+            self.dry_block.push(self.dry.pop_front().unwrap_or(0.0));
+        }
+
+        let shape = params.shape;
+        self.oversampler.process(block, |x| waveshape(shape, x * drive));
+
+        for (sample, &dry) in block.iter_mut().zip(&self.dry_block) {
+            // One-pole DC blocker for the tube curve's offset
+            let blocked = *sample - self.dc_input + dc_coeff * self.dc_output;
+            self.dc_input = *sample;
+            self.dc_output = blocked;
+            *sample = dry * (1.0 - mix) + blocked * output * mix;
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.oversampler.latency()
+    }
+
+    fn reset(&mut self) {
+        self.oversampler.reset();
+        self.dry.iter_mut().for_each(|x| *x = 0.0);
+        self.dc_input = 0.0;
+        self.dc_output = 0.0;
+    }
+}
+
+/// Lo-fi degradation: samples are held at a lower rate, without filtering,
+/// so the aliasing of old samplers and games is part of the sound, and
+/// quantised to fewer bits.
+pub struct Bitcrusher {
+    sample_rate: f32,
+    phase: f32,
+    held: f32,
+}
+
+impl Bitcrusher {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            phase: 1.0,
+            held: 0.0,
+        }
+    }
+}
+
+impl Stage for Bitcrusher {
+    type Params = BitcrusherParams;
+
+    fn process(&mut self, block: &mut [f32], params: &BitcrusherParams) {
+        let step = params.sample_rate.clamp(100.0, self.sample_rate) / self.sample_rate;
+        // Half the steps on each side of zero
+        let levels = 2.0f32.powf(params.bits.clamp(1.0, 16.0) - 1.0);
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        for sample in block.iter_mut() {
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+                self.held = (*sample * levels).round() / levels;
+            }
+            self.phase += step;
+            *sample = *sample * (1.0 - mix) + self.held * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phase = 1.0;
+        self.held = 0.0;
+    }
+}
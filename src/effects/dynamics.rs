@@ -0,0 +1,294 @@
+use super::Stage;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Limiter lookahead: the gain is fully down before a peak leaves the delay line.
+const LIMITER_LOOKAHEAD_MS: f32 = 1.5;
+
+/// True-peak detection interpolates `OVERSAMPLING` points per sample with a
+/// windowed-sinc kernel of `INTERPOLATION_TAPS` input samples.
+const OVERSAMPLING: usize = 4;
+const INTERPOLATION_TAPS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CompressorParams {
+    pub enabled: bool,
+    /// Level above which the gain is reduced, in dBFS
+    pub threshold_db: f32,
+    /// Input to output level ratio above the threshold
+    pub ratio: f32,
+    /// Width of the soft knee around the threshold, in dB
+    pub knee_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Gain added after compression, in dB
+    pub makeup_db: f32,
+}
+
+impl Default for CompressorParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -20.0,
+            ratio: 4.0,
+            knee_db: 6.0,
+            attack_ms: 10.0,
+            release_ms: 120.0,
+            makeup_db: 6.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LimiterParams {
+    pub enabled: bool,
+    /// Highest true-peak level let through, in dBFS
+    pub ceiling_db: f32,
+    pub release_ms: f32,
+}
+
+impl Default for LimiterParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ceiling_db: -1.0,
+            release_ms: 80.0,
+        }
+    }
+}
+
+fn to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+fn from_db(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+fn coefficient(ms: f32, sample_rate: f32) -> f32 {
+    (-1000.0 / (ms.max(0.01) * sample_rate)).exp()
+}
+
+/// Feed-forward compressor with a soft knee, working on the peak level in dB.
+///
+/// Gain reduction is smoothed with separate attack and release times, so the
+/// compressor follows syllables without pumping on every cycle of the voice.
+pub struct Compressor {
+    sample_rate: f32,
+    reduction_db: f32,
+    block_reduction_db: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            reduction_db: 0.0,
+            block_reduction_db: 0.0,
+        }
+    }
+
+    /// Largest gain reduction applied during the last block, in dB (positive).
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.block_reduction_db
+    }
+}
+
+/// Static curve: output level for `level_db`, with a quadratic knee of
+/// `knee_db` centred on the threshold.
+fn compress_curve(level_db: f32, threshold_db: f32, ratio: f32, knee_db: f32) -> f32 {
+    let over = level_db - threshold_db;
+    if 2.0 * over < -knee_db {
+        level_db
+    } else if 2.0 * over.abs() <= knee_db && knee_db > 0.0 {
+        let x = over + knee_db / 2.0;
+        level_db + (1.0 / ratio - 1.0) * x * x / (2.0 * knee_db)
+    } else {
+        threshold_db + over / ratio
+    }
+}
+
+impl Stage for Compressor {
+    type Params = CompressorParams;
+
+    fn process(&mut self, block: &mut [f32], params: &CompressorParams) {
+        let ratio = params.ratio.max(1.0);
+        let knee = params.knee_db.max(0.0);
+        let attack = coefficient(params.attack_ms, self.sample_rate);
+        let release = coefficient(params.release_ms, self.sample_rate);
+        let mut block_reduction: f32 = 0.0;
+
+        for sample in block.iter_mut() {
+            let level_db = to_db(sample.abs());
+            let target = level_db - compress_curve(level_db, params.threshold_db, ratio, knee);
+            let coeff = if target > self.reduction_db { attack } else { release };
+            self.reduction_db = target + (self.reduction_db - target) * coeff;
+            block_reduction = block_reduction.max(self.reduction_db);
+
+            *sample *= from_db(params.makeup_db - self.reduction_db);
+        }
+
+        self.block_reduction_db = block_reduction;
+    }
+
+    fn reset(&mut self) {
+        self.reduction_db = 0.0;
+        self.block_reduction_db = 0.0;
+    }
+}
+
+/// Inter-sample peak estimate from windowed-sinc interpolation at
+/// `OVERSAMPLING` times the sample rate. Reports the peak between the samples
+/// pushed `INTERPOLATION_TAPS / 2` calls and one call less earlier.
+struct TruePeak {
+    kernels: [[f32; INTERPOLATION_TAPS]; OVERSAMPLING],
+    history: [f32; INTERPOLATION_TAPS],
+}
+
+impl TruePeak {
+    fn new() -> Self {
+        let half = (INTERPOLATION_TAPS / 2) as f32;
+        let mut kernels = [[0.0; INTERPOLATION_TAPS]; OVERSAMPLING];
+        for (phase, kernel) in kernels.iter_mut().enumerate() {
+            for (k, tap) in kernel.iter_mut().enumerate() {
+                let distance = half - 1.0 + phase as f32 / OVERSAMPLING as f32 - k as f32;
+                let sinc = if distance.abs() < 1e-6 {
+                    1.0
+                } else {
+                    (PI * distance).sin() / (PI * distance)
+                };
+                let window = 0.5 + 0.5 * (PI * distance / half).cos();
+                *tap = sinc * window;
+            }
+        }
+
+        Self {
+            kernels,
+            history: [0.0; INTERPOLATION_TAPS],
+        }
+    }
+
+    fn delay(&self) -> usize {
+        INTERPOLATION_TAPS / 2
+    }
+
+    fn push(&mut self, sample: f32) -> f32 {
+        self.history.copy_within(1.., 0);
+        self.history[INTERPOLATION_TAPS - 1] = sample;
+
+        self.kernels
+            .iter()
+            .map(|kernel| {
+                kernel
+                    .iter()
+                    .zip(&self.history)
+                    .map(|(tap, x)| tap * x)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+    }
+}
+
+/// Lookahead brick-wall limiter on true-peak levels.
+///
+/// The gain needed to keep each (interpolated) peak under the ceiling is held
+/// for the lookahead time and then box-filtered over the same length, which
+/// ramps the gain smoothly down before the peak reaches the output and
+/// guarantees it is low enough when it does.
+pub struct Limiter {
+    sample_rate: f32,
+    true_peak: TruePeak,
+    lookahead: usize,
+    required: Vec<f32>,
+    smoothing: Vec<f32>,
+    delay_line: Vec<f32>,
+    position: usize,
+    released: f32,
+    block_reduction_db: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: f32) -> Self {
+        let lookahead = (sample_rate * LIMITER_LOOKAHEAD_MS / 1000.0).round().max(1.0) as usize;
+        let true_peak = TruePeak::new();
+        let delay = lookahead - 1 + true_peak.delay();
+
+        Self {
+            sample_rate,
+            true_peak,
+            lookahead,
+            required: vec![1.0; lookahead],
+            smoothing: vec![1.0; lookahead],
+            delay_line: vec![0.0; delay + 1],
+            position: 0,
+            released: 1.0,
+            block_reduction_db: 0.0,
+        }
+    }
+
+    /// Largest gain reduction applied during the last block, in dB (positive).
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.block_reduction_db
+    }
+}
+
+impl Stage for Limiter {
+    type Params = LimiterParams;
+
+    fn process(&mut self, block: &mut [f32], params: &LimiterParams) {
+        let ceiling = from_db(params.ceiling_db.min(0.0));
+        let release = coefficient(params.release_ms, self.sample_rate);
+        let mut lowest_gain: f32 = 1.0;
+
+        for sample in block.iter_mut() {
+            let peak = self.true_peak.push(*sample);
+            let required = if peak > ceiling { ceiling / peak } else { 1.0 };
+
+            // Hold the lowest required gain over the lookahead window
+            let slot = self.position % self.lookahead;
+            self.required[slot] = required;
+            let held = self.required.iter().copied().fold(1.0, f32::min);
+
+            // Release slowly, but never above the held gain
+            self.released = if held < self.released {
+                held
+            } else {
+                held + (self.released - held) * release
+            };
+
+            self.smoothing[slot] = self.released;
+            let gain = (self.smoothing.iter().sum::<f32>() / self.lookahead as f32).min(1.0);
+            lowest_gain = lowest_gain.min(gain);
+
+            let len = self.delay_line.len();
+            let write = self.position % len;
+            self.delay_line[write] = *sample;
+            *sample = self.delay_line[(write + 1) % len] * gain;
+            self.position = self.position.wrapping_add(1);
+        }
+
+        self.block_reduction_db = -to_db(lowest_gain);
+    }
+
+    fn latency(&self) -> usize {
+        self.delay_line.len() - 1
+    }
+
+    fn reset(&mut self) {
+        self.true_peak.reset();
+        self.required.fill(1.0);
+        self.smoothing.fill(1.0);
+        self.delay_line.fill(0.0);
+        self.position = 0;
+        self.released = 1.0;
+        self.block_reduction_db = 0.0;
+    }
+}
+
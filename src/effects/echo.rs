@@ -0,0 +1,560 @@
+use super::Stage;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Samples per adaptive filter block and filter partition; also the latency.
+const BLOCK: usize = 256;
+const FFT_SIZE: usize = 2 * BLOCK;
+const MAX_PARTITIONS: usize = 32;
+
+/// Far-end samples kept for alignment, about 1.5 s at 44.1 kHz.
+const REFERENCE_LEN: usize = 1 << 16;
+
+/// The reference is read this far behind the newest far-end sample, so
+/// callback scheduling jitter rarely makes the canceller read ahead of it.
+/// Device buffering alone delays the echo by more than this.
+const ANCHOR_SLACK: usize = 256;
+
+/// When the two streams drift further apart than this, the alignment is
+/// started over.
+const REANCHOR_TOLERANCE: i64 = 4096;
+
+/// Filter taps placed ahead of the estimated delay, so small delay errors and
+/// early reflections are still covered.
+const DELAY_MARGIN: usize = BLOCK;
+
+/// Delay estimation: GCC-PHAT between the decimated microphone signal and the
+/// reference, over `DELAY_WINDOW` decimated samples and lags up to `MAX_LAG`.
+const DECIMATION: usize = 4;
+const DELAY_WINDOW: usize = 8192;
+const MAX_LAG: usize = 4096;
+const CORRELATION_SIZE: usize = 16384;
+const ESTIMATE_SECONDS: f32 = 0.5;
+
+/// Correlation peak needed over the mean correlation to trust a delay estimate.
+const DELAY_CONFIDENCE: f32 = 8.0;
+
+/// Reference blocks quieter than this are not used for adaptation, in dBFS.
+const FAR_ACTIVE_DB: f32 = -55.0;
+
+/// Echo reduction the filter must reach before double-talk is detected.
+const CONVERGED_ERLE_DB: f32 = 6.0;
+
+/// Speed at which the residual noise floor estimate may rise, in dB per second.
+const FLOOR_RISE_DB_PER_SECOND: f32 = 3.0;
+
+/// Time double-talk is held after it was last detected, and time after which
+/// continuous "double-talk" is taken as a changed echo path instead.
+const DOUBLE_TALK_HOLD_SECONDS: f32 = 0.1;
+const PATH_CHANGE_SECONDS: f32 = 1.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EchoParams {
+    pub enabled: bool,
+    /// Adaptation step size (0.05 - 1.0); higher converges faster but leaves
+    /// more residual echo
+    pub step_size: f32,
+    /// Length of the echo tail covered by the filter, in ms
+    pub tail_ms: f32,
+    /// Drop of the echo reduction below its long-term value that counts as
+    /// double-talk, in dB
+    pub double_talk_db: f32,
+}
+
+impl Default for EchoParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            step_size: 0.5,
+            tail_ms: 80.0,
+            double_talk_db: 6.0,
+        }
+    }
+}
+
+/// Acoustic echo canceller using the played output as far-end reference.
+///
+/// A partitioned-block frequency-domain adaptive filter (MDF) models the path
+/// from the speaker to the microphone and subtracts the predicted echo. The
+/// bulk delay between both streams is found by cross-correlation, so the
+/// filter only needs to cover the room's echo tail. Adaptation stops while
+/// the far end is silent or the near end is talking at the same time, which
+/// is detected as a sudden drop of the achieved echo reduction.
+pub struct EchoCanceller {
+    sample_rate: f32,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    scratch: Vec<Complex<f32>>,
+    frame: Vec<f32>,
+    reference: Vec<f32>,
+    reference_written: u64,
+    mic_count: u64,
+    /// Reference index aligned with microphone sample 0 (before the delay)
+    base: Option<i64>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    fill: usize,
+    partitions: Vec<Vec<Complex<f32>>>,
+    weights: Vec<Vec<Complex<f32>>>,
+    newest: usize,
+    power: Vec<f32>,
+    error_spectrum: Vec<Complex<f32>>,
+    echo_spectrum: Vec<Complex<f32>>,
+    gradient: Vec<Complex<f32>>,
+    delay: DelayEstimator,
+    erle_db: f32,
+    converged: bool,
+    /// Lowest recent residual power, the near end's background noise
+    error_floor: f32,
+    double_talk_hold: usize,
+    double_talk_blocks: usize,
+}
+
+impl EchoCanceller {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(FFT_SIZE);
+        let inverse = planner.plan_fft_inverse(FFT_SIZE);
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+        let bins = FFT_SIZE / 2 + 1;
+        let spectrum = vec![Complex::new(0.0, 0.0); bins];
+
+        Self {
+            sample_rate,
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            frame: vec![0.0; FFT_SIZE],
+            reference: vec![0.0; REFERENCE_LEN],
+            reference_written: 0,
+            mic_count: 0,
+            base: None,
+            input: vec![0.0; BLOCK],
+            output: vec![0.0; BLOCK],
+            fill: 0,
+            partitions: vec![spectrum.clone(); MAX_PARTITIONS],
+            weights: vec![spectrum.clone(); MAX_PARTITIONS],
+            newest: 0,
+            power: vec![0.0; bins],
+            error_spectrum: spectrum.clone(),
+            echo_spectrum: spectrum.clone(),
+            gradient: spectrum,
+            delay: DelayEstimator::new(&mut planner),
+            erle_db: 0.0,
+            converged: false,
+            error_floor: 1.0,
+            double_talk_hold: 0,
+            double_talk_blocks: 0,
+            forward,
+            inverse,
+        }
+    }
+
+    /// Append samples sent to the speakers. They are matched against the
+    /// microphone signal in the following `process` calls.
+    pub fn push_reference(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.reference[(self.reference_written % REFERENCE_LEN as u64) as usize] = sample;
+            self.reference_written += 1;
+        }
+    }
+
+    /// Estimated delay from the speaker output to the microphone, in ms.
+    pub fn delay_ms(&self) -> Option<f32> {
+        self.delay.estimated.then(|| {
+            (self.delay.samples + ANCHOR_SLACK) as f32 * 1000.0 / self.sample_rate
+        })
+    }
+
+    /// Long-term echo return loss enhancement (echo reduction) in dB, once
+    /// the filter has converged.
+    pub fn erle_db(&self) -> Option<f32> {
+        self.converged.then_some(self.erle_db)
+    }
+
+    pub fn double_talk(&self) -> bool {
+        self.double_talk_hold > 0
+    }
+
+    fn reference_at(&self, index: i64) -> f32 {
+        // Samples older than the ring or not written yet read as silence
+        let written = self.reference_written as i64;
+        if index < 0 || index >= written || written - index > REFERENCE_LEN as i64 {
+            0.0
+        } else {
+            self.reference[(index as u64 % REFERENCE_LEN as u64) as usize]
+        }
+    }
+
+    fn clear_filter(&mut self) {
+        for partition in self.partitions.iter_mut().chain(self.weights.iter_mut()) {
+            partition.fill(Complex::new(0.0, 0.0));
+        }
+        self.power.fill(0.0);
+        self.erle_db = 0.0;
+        self.converged = false;
+        self.double_talk_hold = 0;
+        self.double_talk_blocks = 0;
+    }
+
+    /// Keep the reference index of the current microphone sample within
+    /// reach of the newest far-end sample, starting over when they drift apart.
+    fn align(&mut self, block_len: usize) {
+        let observed = self.reference_written as i64
+            - (self.mic_count + block_len as u64) as i64
+            - ANCHOR_SLACK as i64;
+        let drifted = self.base.is_none_or(|base| (observed - base).abs() > REANCHOR_TOLERANCE);
+        if drifted {
+            self.base = Some(observed);
+            self.delay.reset();
+            self.clear_filter();
+        }
+    }
+
+    fn process_block(&mut self, params: &EchoParams) {
+        let base = self.base.unwrap_or(0);
+        let block_start = (self.mic_count - BLOCK as u64) as i64;
+        let partitions = ((params.tail_ms.max(1.0) * self.sample_rate / 1000.0) as usize)
+            .div_ceil(BLOCK)
+            .clamp(1, MAX_PARTITIONS);
+        let n = FFT_SIZE as f32;
+
+        // Feed the delay estimator with the undelayed reference
+        for i in 0..BLOCK {
+            let reference = self.reference_at(block_start + i as i64 + base);
+            // Small changes are covered by the taps ahead of the delay; moving
+            // the reference under converged weights would undo the adaptation
+            if let Some(delay) = self.delay.push(self.input[i], reference, self.sample_rate) {
+                if delay.abs_diff(self.delay.samples) > DELAY_MARGIN / 2 {
+                    self.delay.samples = delay;
+                    self.clear_filter();
+                }
+            }
+        }
+
+        // Newest reference frame: previous and current block at the filter's first tap
+        let first_tap = self.delay.samples.saturating_sub(DELAY_MARGIN) as i64;
+        let start = block_start + base - first_tap;
+        let mut far_energy = 0.0;
+        for i in 0..FFT_SIZE {
+            let sample = self.reference_at(start - BLOCK as i64 + i as i64);
+            if i >= BLOCK {
+                far_energy += sample * sample;
+            }
+            self.frame[i] = sample;
+        }
+        self.newest = (self.newest + MAX_PARTITIONS - 1) % MAX_PARTITIONS;
+        let newest = self.newest;
+        if self
+            .forward
+            .process_with_scratch(&mut self.frame, &mut self.partitions[newest], &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        // Echo estimate: sum of every partition's weights times its reference frame
+        self.echo_spectrum.fill(Complex::new(0.0, 0.0));
+        for p in 0..partitions {
+            let frame = &self.partitions[(newest + p) % MAX_PARTITIONS];
+            for ((echo, w), x) in self.echo_spectrum.iter_mut().zip(&self.weights[p]).zip(frame) {
+                *echo += w * x;
+            }
+        }
+        if self
+            .inverse
+            .process_with_scratch(&mut self.echo_spectrum, &mut self.frame, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        let mut mic_energy = 0.0;
+        let mut error_energy = 0.0;
+        for i in 0..BLOCK {
+            let echo = self.frame[BLOCK + i] / n;
+            let error = self.input[i] - echo;
+            mic_energy += self.input[i] * self.input[i];
+            error_energy += error * error;
+            self.output[i] = error;
+        }
+
+        let blocks_per_second = self.sample_rate / BLOCK as f32;
+        let error_power = error_energy / BLOCK as f32;
+        self.error_floor = if error_power < self.error_floor {
+            error_power
+        } else {
+            self.error_floor * 10.0f32.powf(FLOOR_RISE_DB_PER_SECOND / 10.0 / blocks_per_second)
+        };
+
+        // Double-talk: the residual is well above what the converged filter
+        // usually leaves of the echo on top of the background noise
+        let far_active = 10.0 * (far_energy / BLOCK as f32 + 1e-12).log10() > FAR_ACTIVE_DB;
+        let block_erle = 10.0 * ((mic_energy + 1e-12) / (error_energy + 1e-12)).log10();
+        let expected = mic_energy / BLOCK as f32 * 10.0f32.powf(-self.erle_db / 10.0) + self.error_floor;
+        let margin = 10.0f32.powf(params.double_talk_db.max(0.0) / 10.0);
+        if far_active && self.converged && error_power > expected * margin {
+            self.double_talk_hold = (DOUBLE_TALK_HOLD_SECONDS * blocks_per_second) as usize;
+        } else {
+            self.double_talk_hold = self.double_talk_hold.saturating_sub(1);
+        }
+        if self.double_talk_hold > 0 {
+            self.double_talk_blocks += 1;
+            // Nobody talks over the far end this long: the room changed
+            if self.double_talk_blocks as f32 > PATH_CHANGE_SECONDS * blocks_per_second {
+                self.converged = false;
+                self.double_talk_hold = 0;
+                self.double_talk_blocks = 0;
+            }
+        } else {
+            self.double_talk_blocks = 0;
+        }
+
+        if !far_active || self.double_talk_hold > 0 {
+            return;
+        }
+
+        self.erle_db = 0.95 * self.erle_db + 0.05 * block_erle;
+        self.converged = self.erle_db > CONVERGED_ERLE_DB;
+
+        for (power, x) in self.power.iter_mut().zip(&self.partitions[newest]) {
+            *power = 0.9 * *power + 0.1 * x.norm_sqr();
+        }
+        let regularization = 1e-6 * n * n;
+
+        self.frame[..BLOCK].fill(0.0);
+        self.frame[BLOCK..].copy_from_slice(&self.output);
+        if self
+            .forward
+            .process_with_scratch(&mut self.frame, &mut self.error_spectrum, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        // Normalized gradient per partition, constrained to the first half of
+        // the taps so the filter stays a linear (not circular) convolution
+        let step = params.step_size.clamp(0.05, 1.0) / partitions as f32;
+        for p in 0..partitions {
+            let frame = &self.partitions[(newest + p) % MAX_PARTITIONS];
+            for (((g, x), e), power) in self
+                .gradient
+                .iter_mut()
+                .zip(frame)
+                .zip(&self.error_spectrum)
+                .zip(&self.power)
+            {
+                *g = x.conj() * e * (step / (power + regularization));
+            }
+            let last = self.gradient.len() - 1;
+            self.gradient[0].im = 0.0;
+            self.gradient[last].im = 0.0;
+            if self
+                .inverse
+                .process_with_scratch(&mut self.gradient, &mut self.frame, &mut self.scratch)
+                .is_err()
+            {
+                return;
+            }
+            for sample in self.frame[..BLOCK].iter_mut() {
+                *sample /= n;
+            }
+            self.frame[BLOCK..].fill(0.0);
+            if self
+                .forward
+                .process_with_scratch(&mut self.frame, &mut self.gradient, &mut self.scratch)
+                .is_err()
+            {
+                return;
+            }
+            for (w, g) in self.weights[p].iter_mut().zip(&self.gradient) {
+                *w += g;
+            }
+        }
+    }
+}
+
+impl Stage for EchoCanceller {
+    type Params = EchoParams;
+
+    fn process(&mut self, block: &mut [f32], params: &EchoParams) {
+        self.align(block.len());
+
+        for sample in block.iter_mut() {
+            self.input[self.fill] = *sample;
+            *sample = self.output[self.fill];
+            self.fill += 1;
+            self.mic_count += 1;
+
+            if self.fill == BLOCK {
+                self.fill = 0;
+                self.process_block(params);
+            }
+        }
+    }
+
+    fn latency(&self) -> usize {
+        BLOCK
+    }
+
+    fn reset(&mut self) {
+        self.base = None;
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+        self.error_floor = 1.0;
+        self.delay.reset();
+        self.clear_filter();
+    }
+}
+
+/// Bulk delay between the reference and the microphone from GCC-PHAT over
+/// decimated copies of both signals.
+struct DelayEstimator {
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    mic: Vec<f32>,
+    reference: Vec<f32>,
+    written: usize,
+    mic_sum: f32,
+    reference_sum: f32,
+    phase: usize,
+    since_estimate: usize,
+    mic_frame: Vec<f32>,
+    reference_frame: Vec<f32>,
+    mic_spectrum: Vec<Complex<f32>>,
+    reference_spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    pending: Option<usize>,
+    /// Current delay in samples (the lag of the echo behind the aligned reference)
+    samples: usize,
+    estimated: bool,
+}
+
+impl DelayEstimator {
+    fn new(planner: &mut RealFftPlanner<f32>) -> Self {
+        let forward = planner.plan_fft_forward(CORRELATION_SIZE);
+        let inverse = planner.plan_fft_inverse(CORRELATION_SIZE);
+        let history = DELAY_WINDOW + MAX_LAG;
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+
+        Self {
+            mic: vec![0.0; history],
+            reference: vec![0.0; history],
+            written: 0,
+            mic_sum: 0.0,
+            reference_sum: 0.0,
+            phase: 0,
+            since_estimate: 0,
+            mic_frame: forward.make_input_vec(),
+            reference_frame: forward.make_input_vec(),
+            mic_spectrum: forward.make_output_vec(),
+            reference_spectrum: forward.make_output_vec(),
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            pending: None,
+            samples: DELAY_MARGIN,
+            estimated: false,
+            forward,
+            inverse,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mic.fill(0.0);
+        self.reference.fill(0.0);
+        self.written = 0;
+        self.mic_sum = 0.0;
+        self.reference_sum = 0.0;
+        self.phase = 0;
+        self.since_estimate = 0;
+        self.pending = None;
+        self.samples = DELAY_MARGIN;
+        self.estimated = false;
+    }
+
+    /// Add one sample of each signal; returns a new delay in samples once two
+    /// consecutive estimates agree.
+    fn push(&mut self, mic: f32, reference: f32, sample_rate: f32) -> Option<usize> {
+        self.mic_sum += mic;
+        self.reference_sum += reference;
+        self.phase += 1;
+        if self.phase < DECIMATION {
+            return None;
+        }
+
+        let len = self.mic.len();
+        self.mic[self.written % len] = self.mic_sum / DECIMATION as f32;
+        self.reference[self.written % len] = self.reference_sum / DECIMATION as f32;
+        self.written += 1;
+        self.mic_sum = 0.0;
+        self.reference_sum = 0.0;
+        self.phase = 0;
+        self.since_estimate += 1;
+
+        let interval = (ESTIMATE_SECONDS * sample_rate / DECIMATION as f32) as usize;
+        if self.written < len || self.since_estimate < interval {
+            return None;
+        }
+        self.since_estimate = 0;
+
+        let lag = self.estimate()?;
+        let agreed = self.pending.is_some_and(|pending| pending.abs_diff(lag) <= 2);
+        self.pending = Some(lag);
+        if agreed {
+            self.estimated = true;
+            Some(lag * DECIMATION)
+        } else {
+            None
+        }
+    }
+
+    fn estimate(&mut self) -> Option<usize> {
+        let len = self.mic.len();
+        let oldest = self.written % len;
+
+        // Reference over the window plus the lag range, microphone over the window
+        self.reference_frame.fill(0.0);
+        self.mic_frame.fill(0.0);
+        let mut reference_energy = 0.0;
+        for i in 0..len {
+            let reference = self.reference[(oldest + i) % len];
+            self.reference_frame[i] = reference;
+            reference_energy += reference * reference;
+        }
+        for i in 0..DELAY_WINDOW {
+            self.mic_frame[i] = self.mic[(oldest + MAX_LAG + i) % len];
+        }
+        let far_db = 10.0 * (reference_energy / len as f32 + 1e-12).log10();
+        if far_db < FAR_ACTIVE_DB {
+            return None;
+        }
+
+        self.forward
+            .process_with_scratch(&mut self.reference_frame, &mut self.reference_spectrum, &mut self.scratch)
+            .ok()?;
+        self.forward
+            .process_with_scratch(&mut self.mic_frame, &mut self.mic_spectrum, &mut self.scratch)
+            .ok()?;
+        for (r, m) in self.reference_spectrum.iter_mut().zip(&self.mic_spectrum) {
+            let cross = m.conj() * *r;
+            *r = cross / (cross.norm() + 1e-12);
+        }
+        let last = self.reference_spectrum.len() - 1;
+        self.reference_spectrum[0].im = 0.0;
+        self.reference_spectrum[last].im = 0.0;
+        self.inverse
+            .process_with_scratch(&mut self.reference_spectrum, &mut self.reference_frame, &mut self.scratch)
+            .ok()?;
+
+        // Correlation at offset `tau` pairs the microphone with the reference
+        // `MAX_LAG - tau` decimated samples earlier
+        let correlation = &self.reference_frame[..=MAX_LAG];
+        let (tau, peak) = correlation
+            .iter()
+            .enumerate()
+            .fold((0, f32::MIN), |best, (tau, &c)| if c > best.1 { (tau, c) } else { best });
+        let mean = correlation.iter().map(|c| c.abs()).sum::<f32>() / correlation.len() as f32;
+        (peak > DELAY_CONFIDENCE * mean).then_some(MAX_LAG - tau)
+    }
+}
@@ -0,0 +1,260 @@
+use super::biquad::Biquad;
+use super::Stage;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Upper limit on the number of bands, keeping the per-sample cost bounded.
+pub const MAX_BANDS: usize = 8;
+
+/// Points of the response curve reported to the interfaces, spread linearly
+/// from 0 Hz to Nyquist like the spectrum display.
+pub const RESPONSE_POINTS: usize = 256;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BandType {
+    LowShelf,
+    HighShelf,
+    Peaking,
+    LowPass,
+    HighPass,
+    Notch,
+}
+
+impl BandType {
+    pub const ALL: [BandType; 6] = [
+        BandType::LowShelf,
+        BandType::HighShelf,
+        BandType::Peaking,
+        BandType::LowPass,
+        BandType::HighPass,
+        BandType::Notch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BandType::LowShelf => "low_shelf",
+            BandType::HighShelf => "high_shelf",
+            BandType::Peaking => "peaking",
+            BandType::LowPass => "low_pass",
+            BandType::HighPass => "high_pass",
+            BandType::Notch => "notch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BandType> {
+        Self::ALL.into_iter().find(|band_type| band_type.name() == name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EqBand {
+    pub band_type: BandType,
+    /// Centre or corner frequency in Hz
+    pub frequency: f32,
+    /// Boost or cut in dB (shelves and peaking bands only)
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self {
+            band_type: BandType::Peaking,
+            frequency: 1000.0,
+            gain_db: 0.0,
+            q: 1.0,
+        }
+    }
+}
+
+impl EqBand {
+    fn new(band_type: BandType, frequency: f32) -> Self {
+        Self {
+            band_type,
+            frequency,
+            q: std::f32::consts::FRAC_1_SQRT_2,
+            ..Self::default()
+        }
+    }
+
+    pub fn biquad(&self, sample_rate: f32) -> Biquad {
+        let q = self.q.clamp(0.1, 20.0);
+        let gain = self.gain_db.clamp(-24.0, 24.0);
+        match self.band_type {
+            BandType::LowShelf => Biquad::low_shelf(sample_rate, self.frequency, q, gain),
+            BandType::HighShelf => Biquad::high_shelf(sample_rate, self.frequency, q, gain),
+            BandType::Peaking => Biquad::peaking(sample_rate, self.frequency, q, gain),
+            BandType::LowPass => Biquad::lowpass(sample_rate, self.frequency, q),
+            BandType::HighPass => Biquad::highpass(sample_rate, self.frequency, q),
+            BandType::Notch => Biquad::notch(sample_rate, self.frequency, q),
+        }
+    }
+}
+
+/// Parses `type:frequency[:gain_db[:q]]`, e.g. `peaking:2500:3:1.4`. Empty
+/// fields keep their defaults, as in `notch:6000::8`.
+impl FromStr for EqBand {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let mut parts = text.trim().split(':');
+        let name = parts.next().unwrap_or_default();
+        let band_type = BandType::from_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown EQ band type: {}", name))?;
+        let mut number = |what: &str| -> anyhow::Result<Option<f32>> {
+            parts
+                .next()
+                .filter(|value| !value.is_empty())
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid EQ band {}: {}", what, value))
+                })
+                .transpose()
+        };
+        let frequency = number("frequency")?
+            .ok_or_else(|| anyhow::anyhow!("EQ band needs a frequency, e.g. peaking:1000:3:1"))?;
+        let mut band = EqBand::new(band_type, frequency);
+        if let Some(gain_db) = number("gain")? {
+            band.gain_db = gain_db;
+        }
+        if let Some(q) = number("Q")? {
+            band.q = q;
+        }
+        Ok(band)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EqParams {
+    pub enabled: bool,
+    pub bands: Vec<EqBand>,
+}
+
+impl Default for EqParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: vec![
+                EqBand::new(BandType::LowShelf, 120.0),
+                EqBand { frequency: 500.0, ..EqBand::default() },
+                EqBand { frequency: 2500.0, ..EqBand::default() },
+                EqBand::new(BandType::HighShelf, 8000.0),
+            ],
+        }
+    }
+}
+
+impl EqParams {
+    /// Combined magnitude response in dB at `RESPONSE_POINTS` frequencies
+    /// spaced evenly from 0 Hz to Nyquist.
+    pub fn response(&self, sample_rate: f32) -> Vec<f32> {
+        let sections: Vec<Biquad> = self
+            .bands
+            .iter()
+            .take(MAX_BANDS)
+            .map(|band| band.biquad(sample_rate))
+            .collect();
+        (0..RESPONSE_POINTS)
+            .map(|i| {
+                let frequency = i as f32 / RESPONSE_POINTS as f32 * sample_rate / 2.0;
+                sections
+                    .iter()
+                    .map(|section| section.magnitude_db(sample_rate, frequency))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// Cascade of RBJ biquads, one per band.
+pub struct Equalizer {
+    sample_rate: f32,
+    tuned: Vec<EqBand>,
+    /// One section per possible band, the first `active` in use
+    sections: Vec<Biquad>,
+    active: usize,
+}
+
+impl Equalizer {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            tuned: Vec::with_capacity(MAX_BANDS),
+            sections: vec![Biquad::default(); MAX_BANDS],
+            active: 0,
+        }
+    }
+
+    fn tune(&mut self, bands: &[EqBand]) {
+        let bands = &bands[..bands.len().min(MAX_BANDS)];
+        if self.tuned == bands {
+            return;
+        }
+        // Bands that still exist keep their filter state so edits do not
+        // click; added bands start from rest
+        for (i, (section, band)) in self.sections.iter_mut().zip(bands).enumerate() {
+            if i >= self.active {
+                section.reset();
+            }
+            section.retune(&band.biquad(self.sample_rate));
+        }
+        self.active = bands.len();
+        self.tuned.clear();
+        self.tuned.extend_from_slice(bands);
+    }
+}
+
+impl Stage for Equalizer {
+    type Params = EqParams;
+
+    fn process(&mut self, block: &mut [f32], params: &EqParams) {
+        self.tune(&params.bands);
+        for sample in block.iter_mut() {
+            *sample = self.sections[..self.active]
+                .iter_mut()
+                .fold(*sample, |value, section| section.process(value));
+        }
+    }
+
+    fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_full_band() {
+        let band: EqBand = "peaking:1000:3:2".parse().unwrap();
+        assert_eq!(band.band_type, BandType::Peaking);
+        assert_eq!(band.frequency, 1000.0);
+        assert_eq!(band.gain_db, 3.0);
+        assert_eq!(band.q, 2.0);
+    }
+
+    #[test]
+    fn missing_gain_and_q_keep_their_defaults() {
+        let defaults = EqBand::new(BandType::LowShelf, 200.0);
+        let band: EqBand = "low_shelf:200".parse().unwrap();
+        assert_eq!(band, defaults);
+        let band: EqBand = "low_shelf:200::0.5".parse().unwrap();
+        assert_eq!(band.gain_db, defaults.gain_db);
+        assert_eq!(band.q, 0.5);
+    }
+
+    #[test]
+    fn rejects_malformed_bands() {
+        assert!("bell:1000".parse::<EqBand>().is_err());
+        assert!("peaking".parse::<EqBand>().is_err());
+        assert!("peaking:loud".parse::<EqBand>().is_err());
+        assert!("peaking:1000:x".parse::<EqBand>().is_err());
+    }
+}
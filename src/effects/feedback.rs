@@ -0,0 +1,350 @@
+use super::biquad::Biquad;
+use super::Stage;
+use realfft::{RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Analysis frames: about 21 Hz resolution at 44.1 kHz, updated every 12 ms.
+const FRAME_SIZE: usize = 2048;
+const HOP: usize = 512;
+
+/// Frequency range searched for feedback; below it room modes and voice
+/// fundamentals dominate, above it little acoustic gain is left.
+const MIN_FREQUENCY: f32 = 150.0;
+const MAX_FREQUENCY: f32 = 10000.0;
+
+/// Neighbourhood compared against a peak, skipping the Hann main lobe.
+const NEIGHBOUR_NEAR: usize = 3;
+const NEIGHBOUR_FAR: usize = 8;
+
+/// Peaks quieter than this are never treated as feedback.
+const MIN_LEVEL_DB: f32 = -50.0;
+
+/// Only peaks within this distance of the loudest bin are considered; a howl
+/// dominates the spectrum.
+const DOMINANCE_DB: f32 = 6.0;
+
+/// Time a peak has to stay on the same frequency before it is notched.
+/// Voiced speech glides its harmonics well within this time.
+const PERSISTENCE_SECONDS: f32 = 0.4;
+
+/// Frames a tracked peak may be missing before it is forgotten.
+const MAX_MISSED_FRAMES: usize = 2;
+
+/// Depth of a fresh notch and the extra depth added each time the same
+/// frequency triggers again.
+const INITIAL_DEPTH_DB: f32 = 6.0;
+const DEEPEN_DB: f32 = 3.0;
+
+/// Detections this close (relative) to an existing notch refine that notch.
+const SAME_NOTCH_RATIO: f32 = 0.03;
+
+/// Speed at which notch depth changes, avoiding clicks.
+const DEPTH_RATE_DB_PER_SECOND: f32 = 60.0;
+
+pub const MAX_NOTCHES: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FeedbackParams {
+    pub enabled: bool,
+    /// How far a peak must stand above its neighbouring bins to count as a
+    /// howl, in dB; lower values react earlier but risk notching sung notes
+    pub sensitivity_db: f32,
+    /// Deepest cut a single notch may reach, in dB
+    pub max_depth_db: f32,
+    /// Notch bandwidth as Q; higher values cut less of the voice
+    pub q: f32,
+}
+
+impl Default for FeedbackParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sensitivity_db: 20.0,
+            max_depth_db: 18.0,
+            q: 30.0,
+        }
+    }
+}
+
+/// A deployed notch as shown in the interfaces.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NotchStatus {
+    pub frequency: f32,
+    pub depth_db: f32,
+}
+
+struct Candidate {
+    bin: f32,
+    frames: usize,
+    missed: usize,
+}
+
+struct Notch {
+    frequency: f32,
+    target_depth_db: f32,
+    depth_db: f32,
+    filter: Biquad,
+    tuned: Option<(f32, f32, f32)>,
+    deployed_at: u64,
+}
+
+/// Detects acoustic feedback and cuts it with narrow notches.
+///
+/// The signal is analysed with a Hann-windowed FFT; a peak that stands well
+/// above its neighbours, dominates the spectrum and stays on one frequency
+/// for a while gets a notch. A notch that is triggered again is deepened.
+/// When all notches are in use, the oldest one is moved to the new frequency.
+/// Notches stay in place until the suppressor is reset.
+pub struct FeedbackSuppressor {
+    sample_rate: f32,
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    history: Vec<f32>,
+    write_pos: usize,
+    since_analysis: usize,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    power: Vec<f32>,
+    /// Peaks found in the current frame and candidates confirmed as feedback,
+    /// kept between frames so the analysis does not allocate
+    peaks: Vec<f32>,
+    confirmed: Vec<f32>,
+    candidates: Vec<Candidate>,
+    notches: Vec<Notch>,
+    frames_analysed: u64,
+}
+
+impl FeedbackSuppressor {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(FRAME_SIZE);
+        let window = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+
+        Self {
+            sample_rate,
+            window,
+            history: vec![0.0; FRAME_SIZE],
+            write_pos: 0,
+            since_analysis: 0,
+            frame: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            power: vec![0.0; FRAME_SIZE / 2 + 1],
+            peaks: Vec::with_capacity(FRAME_SIZE / 2 + 1),
+            confirmed: Vec::with_capacity(FRAME_SIZE / 2 + 1),
+            candidates: Vec::with_capacity(FRAME_SIZE / 2 + 1),
+            notches: Vec::with_capacity(MAX_NOTCHES),
+            frames_analysed: 0,
+            fft,
+        }
+    }
+
+    /// Active notches, lowest frequency first.
+    pub fn notches(&self) -> Vec<NotchStatus> {
+        let mut notches: Vec<NotchStatus> = self
+            .notches
+            .iter()
+            .map(|notch| NotchStatus {
+                frequency: notch.frequency,
+                depth_db: notch.depth_db,
+            })
+            .collect();
+        notches.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+        notches
+    }
+
+    /// Remove every notch and forget the tracked peaks.
+    pub fn clear(&mut self) {
+        self.notches.clear();
+        self.candidates.clear();
+    }
+
+    fn analyze(&mut self, params: &FeedbackParams) {
+        for (i, (sample, w)) in self.frame.iter_mut().zip(&self.window).enumerate() {
+            *sample = self.history[(self.write_pos + i) % FRAME_SIZE] * w;
+        }
+        if self
+            .fft
+            .process_with_scratch(&mut self.frame, &mut self.spectrum, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+        self.frames_analysed += 1;
+
+        for (p, bin) in self.power.iter_mut().zip(&self.spectrum) {
+            *p = bin.norm_sqr();
+        }
+
+        let resolution = self.sample_rate / FRAME_SIZE as f32;
+        let low = ((MIN_FREQUENCY / resolution) as usize).max(NEIGHBOUR_FAR);
+        let high = ((MAX_FREQUENCY / resolution) as usize).min(self.power.len() - NEIGHBOUR_FAR - 1);
+        let loudest = self.power[low..=high].iter().copied().fold(0.0, f32::max);
+        // Amplitude of a full-scale sine in this analysis, for dBFS levels
+        let full_scale = (FRAME_SIZE as f32 / 4.0).powi(2);
+
+        self.peaks.clear();
+        for k in low..=high {
+            let p = self.power[k];
+            if p < self.power[k - 1] || p < self.power[k + 1] {
+                continue;
+            }
+            if 10.0 * (p / loudest.max(1e-20)).log10() < -DOMINANCE_DB {
+                continue;
+            }
+            if 10.0 * (p / full_scale + 1e-20).log10() < MIN_LEVEL_DB {
+                continue;
+            }
+            let neighbours: f32 = (NEIGHBOUR_NEAR..=NEIGHBOUR_FAR)
+                .map(|d| self.power[k - d] + self.power[k + d])
+                .sum::<f32>()
+                / (2 * (NEIGHBOUR_FAR - NEIGHBOUR_NEAR + 1)) as f32;
+            if 10.0 * (p / neighbours.max(1e-20)).log10() < params.sensitivity_db {
+                continue;
+            }
+            let peak = k as f32 + self.peak_offset(k);
+            self.peaks.push(peak);
+        }
+
+        let peaks = std::mem::take(&mut self.peaks);
+        self.track(&peaks, params);
+        self.peaks = peaks;
+    }
+
+    /// Parabolic interpolation of the peak position on the log spectrum.
+    fn peak_offset(&self, k: usize) -> f32 {
+        let (a, b, c) = (
+            (self.power[k - 1] + 1e-20).ln(),
+            (self.power[k] + 1e-20).ln(),
+            (self.power[k + 1] + 1e-20).ln(),
+        );
+        let denominator = a - 2.0 * b + c;
+        if denominator.abs() < f32::EPSILON {
+            0.0
+        } else {
+            (0.5 * (a - c) / denominator).clamp(-0.5, 0.5)
+        }
+    }
+
+    fn track(&mut self, peaks: &[f32], params: &FeedbackParams) {
+        for candidate in self.candidates.iter_mut() {
+            candidate.missed += 1;
+        }
+        for &peak in peaks {
+            match self
+                .candidates
+                .iter_mut()
+                .find(|candidate| (candidate.bin - peak).abs() <= 1.0)
+            {
+                Some(candidate) => {
+                    candidate.bin = peak;
+                    candidate.frames += 1;
+                    candidate.missed = 0;
+                }
+                None => self.candidates.push(Candidate {
+                    bin: peak,
+                    frames: 1,
+                    missed: 0,
+                }),
+            }
+        }
+        self.candidates.retain(|candidate| candidate.missed <= MAX_MISSED_FRAMES);
+
+        let persistence = (PERSISTENCE_SECONDS * self.sample_rate / HOP as f32) as usize;
+        let resolution = self.sample_rate / FRAME_SIZE as f32;
+        self.confirmed.clear();
+        let confirmed = &mut self.confirmed;
+        self.candidates.retain(|candidate| {
+            let howling = candidate.frames >= persistence;
+            if howling {
+                confirmed.push(candidate.bin * resolution);
+            }
+            !howling
+        });
+        for i in 0..self.confirmed.len() {
+            self.deploy(self.confirmed[i], params);
+        }
+    }
+
+    fn deploy(&mut self, frequency: f32, params: &FeedbackParams) {
+        let max_depth = params.max_depth_db.max(INITIAL_DEPTH_DB);
+        if let Some(notch) = self
+            .notches
+            .iter_mut()
+            .find(|notch| (notch.frequency - frequency).abs() <= notch.frequency * SAME_NOTCH_RATIO)
+        {
+            notch.frequency = 0.5 * (notch.frequency + frequency);
+            notch.target_depth_db = (notch.target_depth_db + DEEPEN_DB).min(max_depth);
+            return;
+        }
+
+        let notch = Notch {
+            frequency,
+            target_depth_db: INITIAL_DEPTH_DB,
+            depth_db: 0.0,
+            filter: Biquad::default(),
+            tuned: None,
+            deployed_at: self.frames_analysed,
+        };
+        if self.notches.len() < MAX_NOTCHES {
+            self.notches.push(notch);
+        } else if let Some(oldest) = self.notches.iter_mut().min_by_key(|notch| notch.deployed_at) {
+            *oldest = notch;
+        }
+    }
+}
+
+impl Stage for FeedbackSuppressor {
+    type Params = FeedbackParams;
+
+    fn process(&mut self, block: &mut [f32], params: &FeedbackParams) {
+        // Move notch depths towards their targets once per block
+        let step = DEPTH_RATE_DB_PER_SECOND * block.len() as f32 / self.sample_rate;
+        let q = params.q.clamp(2.0, 100.0);
+        for notch in self.notches.iter_mut() {
+            notch.depth_db += (notch.target_depth_db - notch.depth_db).clamp(-step, step);
+            let tuning = (notch.frequency, notch.depth_db, q);
+            if notch.tuned != Some(tuning) {
+                notch
+                    .filter
+                    .retune(&Biquad::peaking(self.sample_rate, notch.frequency, q, -notch.depth_db));
+                notch.tuned = Some(tuning);
+            }
+        }
+
+        for sample in block.iter_mut() {
+            // Analyse the signal after the notches, so a notch that is not
+            // deep enough yet is detected again and deepened
+            let output = self
+                .notches
+                .iter_mut()
+                .fold(*sample, |value, notch| notch.filter.process(value));
+            *sample = output;
+
+            self.history[self.write_pos] = output;
+            self.write_pos = (self.write_pos + 1) % FRAME_SIZE;
+            self.since_analysis += 1;
+            if self.since_analysis >= HOP {
+                self.since_analysis = 0;
+                self.analyze(params);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write_pos = 0;
+        self.since_analysis = 0;
+        self.candidates.clear();
+        for notch in self.notches.iter_mut() {
+            notch.filter.reset();
+        }
+    }
+}
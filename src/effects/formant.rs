@@ -0,0 +1,190 @@
+use super::pitch_shift::semitones_to_ratio;
+use super::stft::Stft;
+use super::Stage;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const FRAME_SIZE: usize = 1024;
+const OVERLAP: usize = 4;
+
+/// Cepstral coefficients kept when smoothing the log spectrum. At 44.1 kHz this
+/// is a quefrency cutoff of about 0.7 ms, below the period of any speaking voice.
+const LIFTER: usize = 30;
+
+/// Largest boost or cut applied to a single bin while warping the envelope.
+const MAX_CORRECTION_DB: f32 = 24.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FormantParams {
+    pub enabled: bool,
+    /// Envelope shift in semitones (-12..+12); positive values give a smaller,
+    /// brighter vocal tract
+    pub semitones: f32,
+    /// Undo the envelope movement of the phase vocoder, so formants stay where
+    /// they were in the original voice (plus `semitones`)
+    pub preserve: bool,
+}
+
+impl Default for FormantParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            semitones: 0.0,
+            preserve: false,
+        }
+    }
+}
+
+/// Smooth spectral envelope estimation by cepstral liftering.
+pub struct SpectralEnvelope {
+    lifter: usize,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    forward: Arc<dyn RealToComplex<f32>>,
+    log_spectrum: Vec<Complex<f32>>,
+    cepstrum: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl SpectralEnvelope {
+    pub fn new(frame_size: usize, lifter: usize) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let inverse = planner.plan_fft_inverse(frame_size);
+        let forward = planner.plan_fft_forward(frame_size);
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+
+        Self {
+            lifter,
+            log_spectrum: inverse.make_input_vec(),
+            cepstrum: inverse.make_output_vec(),
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            inverse,
+            forward,
+        }
+    }
+
+    /// Estimate the natural-log envelope of `magnitude` into `envelope`.
+    pub fn estimate(&mut self, magnitude: &[f32], envelope: &mut [f32]) {
+        let frame_size = self.cepstrum.len();
+
+        for (bin, &m) in self.log_spectrum.iter_mut().zip(magnitude) {
+            *bin = Complex::new((m + 1e-9).ln(), 0.0);
+        }
+        if self
+            .inverse
+            .process_with_scratch(&mut self.log_spectrum, &mut self.cepstrum, &mut self.scratch)
+            .is_err()
+        {
+            envelope.fill(0.0);
+            return;
+        }
+
+        // Keep only the low quefrencies (both halves of the symmetric cepstrum)
+        let lifter = self.lifter.min(frame_size / 2);
+        self.cepstrum[lifter..frame_size - lifter + 1].fill(0.0);
+
+        if self
+            .forward
+            .process_with_scratch(&mut self.cepstrum, &mut self.log_spectrum, &mut self.scratch)
+            .is_err()
+        {
+            envelope.fill(0.0);
+            return;
+        }
+
+        let norm = 1.0 / frame_size as f32;
+        for (e, bin) in envelope.iter_mut().zip(&self.log_spectrum) {
+            *e = bin.re * norm;
+        }
+    }
+}
+
+/// Read a log envelope at a fractional bin position with linear interpolation.
+pub fn envelope_at(envelope: &[f32], position: f32) -> f32 {
+    let last = envelope.len() - 1;
+    let position = position.clamp(0.0, last as f32);
+    let index = position.floor() as usize;
+    if index >= last {
+        return envelope[last];
+    }
+    let frac = position - index as f32;
+    envelope[index] * (1.0 - frac) + envelope[index + 1] * frac
+}
+
+/// Shifts the spectral envelope (formants) independently of the pitch.
+///
+/// Every frame the envelope is estimated by cepstral smoothing and each bin is
+/// rescaled by `envelope(k / ratio) / envelope(k)`, which moves the formant
+/// peaks while leaving the harmonic fine structure in place.
+pub struct FormantShifter {
+    stft: Stft,
+    state: FormantState,
+}
+
+struct FormantState {
+    envelope: SpectralEnvelope,
+    magnitude: Vec<f32>,
+    log_envelope: Vec<f32>,
+}
+
+impl FormantShifter {
+    pub fn new() -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+
+        Self {
+            stft,
+            state: FormantState {
+                envelope: SpectralEnvelope::new(FRAME_SIZE, LIFTER),
+                magnitude: vec![0.0; bins],
+                log_envelope: vec![0.0; bins],
+            },
+        }
+    }
+}
+
+impl Default for FormantShifter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stage for FormantShifter {
+    type Params = FormantParams;
+
+    fn process(&mut self, block: &mut [f32], params: &FormantParams) {
+        let ratio = semitones_to_ratio(params.semitones.clamp(-24.0, 24.0));
+        let state = &mut self.state;
+        self.stft.process(block, |spectrum| state.warp(spectrum, ratio));
+    }
+
+    fn latency(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+    }
+}
+
+impl FormantState {
+    fn warp(&mut self, spectrum: &mut [Complex<f32>], ratio: f32) {
+        if (ratio - 1.0).abs() < 1e-4 {
+            return;
+        }
+
+        for (m, bin) in self.magnitude.iter_mut().zip(spectrum.iter()) {
+            *m = bin.norm();
+        }
+        self.envelope.estimate(&self.magnitude, &mut self.log_envelope);
+
+        let max_correction = MAX_CORRECTION_DB / 20.0 * std::f32::consts::LN_10;
+        for (k, bin) in spectrum.iter_mut().enumerate() {
+            let target = envelope_at(&self.log_envelope, k as f32 / ratio);
+            let correction = (target - self.log_envelope[k]).clamp(-max_correction, max_correction);
+            *bin *= correction.exp();
+        }
+    }
+}
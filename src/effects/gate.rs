@@ -0,0 +1,262 @@
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// The level histogram covers -120..0 dBFS in 1 dB steps.
+const HISTOGRAM_MIN_DB: f32 = -120.0;
+const HISTOGRAM_BINS: usize = 120;
+
+/// Length of the level measurements fed to the noise floor estimator.
+const FRAME_MS: f32 = 10.0;
+
+/// Time constant over which old level measurements are forgotten.
+const FLOOR_MEMORY_SECONDS: f32 = 5.0;
+
+/// Measurements needed before the floor estimate is trusted (about 300 ms).
+const MIN_FRAMES: f32 = 30.0;
+
+const MAX_LOOKAHEAD_MS: f32 = 20.0;
+
+/// Gain ramp times when opening and closing, short enough to follow word
+/// onsets but long enough not to click.
+const OPEN_MS: f32 = 1.0;
+const CLOSE_MS: f32 = 60.0;
+
+/// Time constant of the RMS detector driving the open/close decision. It
+/// measures the same kind of level as the floor estimator, so the margin is
+/// not eaten up by the crest factor of the noise; a jump of tens of dB at a
+/// word onset still crosses the threshold almost immediately.
+const DETECTOR_MS: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutoGateParams {
+    pub enabled: bool,
+    /// Open threshold above the tracked noise floor, in dB
+    pub margin_db: f32,
+    /// How far below the open threshold the level must fall to close, in dB
+    pub hysteresis_db: f32,
+    /// Time the gate stays open after the level drops below the close threshold
+    pub hold_ms: f32,
+    /// Delay applied to the audio so the gate opens ahead of word onsets
+    pub lookahead_ms: f32,
+    /// Fraction of recent level measurements considered noise (0.01 - 0.5)
+    pub percentile: f32,
+    /// Attenuation while the gate is closed, in dB
+    pub range_db: f32,
+}
+
+impl Default for AutoGateParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            margin_db: 10.0,
+            hysteresis_db: 4.0,
+            hold_ms: 100.0,
+            lookahead_ms: 5.0,
+            percentile: 0.1,
+            range_db: 40.0,
+        }
+    }
+}
+
+/// Noise floor estimate: a low percentile of the short-term level
+/// distribution over the last few seconds.
+///
+/// Levels are collected in a histogram whose counts decay exponentially, so
+/// the estimate follows a changing room without storing any history.
+pub struct NoiseFloor {
+    histogram: Vec<f32>,
+    total: f32,
+    decay: f32,
+    frame_len: usize,
+    frame_energy: f32,
+    frame_fill: usize,
+    floor_db: Option<f32>,
+}
+
+impl NoiseFloor {
+    pub fn new(sample_rate: f32) -> Self {
+        let frame_len = (sample_rate * FRAME_MS / 1000.0).round().max(1.0) as usize;
+        let frame_seconds = frame_len as f32 / sample_rate;
+
+        Self {
+            histogram: vec![0.0; HISTOGRAM_BINS],
+            total: 0.0,
+            decay: (-frame_seconds / FLOOR_MEMORY_SECONDS).exp(),
+            frame_len,
+            frame_energy: 0.0,
+            frame_fill: 0,
+            floor_db: None,
+        }
+    }
+
+    /// Estimated noise floor in dBFS, once enough input has been seen.
+    pub fn floor_db(&self) -> Option<f32> {
+        self.floor_db
+    }
+
+    pub fn reset(&mut self) {
+        self.histogram.fill(0.0);
+        self.total = 0.0;
+        self.frame_energy = 0.0;
+        self.frame_fill = 0;
+        self.floor_db = None;
+    }
+
+    pub fn push(&mut self, sample: f32, percentile: f32) {
+        self.frame_energy += sample * sample;
+        self.frame_fill += 1;
+        if self.frame_fill < self.frame_len {
+            return;
+        }
+
+        let mean_square = self.frame_energy / self.frame_len as f32;
+        self.frame_energy = 0.0;
+        self.frame_fill = 0;
+
+        let level_db = 10.0 * (mean_square + 1e-12).log10();
+        let bin = ((level_db - HISTOGRAM_MIN_DB).max(0.0) as usize).min(HISTOGRAM_BINS - 1);
+        for count in self.histogram.iter_mut() {
+            *count *= self.decay;
+        }
+        self.histogram[bin] += 1.0;
+        self.total = self.total * self.decay + 1.0;
+
+        if self.total < MIN_FRAMES {
+            return;
+        }
+        let target = percentile.clamp(0.01, 0.5) * self.total;
+        let mut cumulative = 0.0;
+        for (index, &count) in self.histogram.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                self.floor_db = Some(HISTOGRAM_MIN_DB + index as f32 + 0.5);
+                break;
+            }
+        }
+    }
+}
+
+/// Noise gate whose thresholds follow the tracked noise floor.
+///
+/// The gate opens when the short-term level rises `margin_db` above the floor and
+/// closes once it has stayed `hysteresis_db` below that for the hold time. The
+/// detector looks at the incoming signal while the gain is applied to a
+/// slightly delayed copy, so the gate is already open when a word starts.
+pub struct AutoGate {
+    sample_rate: f32,
+    floor: NoiseFloor,
+    delay_line: Vec<f32>,
+    write_pos: usize,
+    lookahead: usize,
+    envelope: f32,
+    open: bool,
+    hold_remaining: usize,
+    gain: f32,
+    speech: bool,
+}
+
+impl AutoGate {
+    pub fn new(sample_rate: f32) -> Self {
+        let max_lookahead = (sample_rate * MAX_LOOKAHEAD_MS / 1000.0).ceil() as usize;
+
+        Self {
+            sample_rate,
+            floor: NoiseFloor::new(sample_rate),
+            delay_line: vec![0.0; max_lookahead + 1],
+            write_pos: 0,
+            lookahead: 0,
+            envelope: 0.0,
+            open: true,
+            hold_remaining: 0,
+            gain: 1.0,
+            speech: false,
+        }
+    }
+
+    pub fn noise_floor_db(&self) -> Option<f32> {
+        self.floor.floor_db()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether the voice activity detector hears speech. While it does the
+    /// gate stays open, so quiet words are not cut.
+    pub fn set_speech(&mut self, speech: bool) {
+        self.speech = speech;
+    }
+
+    fn coefficient(&self, ms: f32) -> f32 {
+        (-1000.0 / (ms * self.sample_rate)).exp()
+    }
+}
+
+impl Stage for AutoGate {
+    type Params = AutoGateParams;
+
+    fn process(&mut self, block: &mut [f32], params: &AutoGateParams) {
+        let lookahead = (params.lookahead_ms.clamp(0.0, MAX_LOOKAHEAD_MS) * self.sample_rate / 1000.0)
+            .round() as usize;
+        self.lookahead = lookahead.min(self.delay_line.len() - 1);
+        let hold = (params.hold_ms.max(0.0) * self.sample_rate / 1000.0) as usize;
+        let closed_gain = 10.0f32.powf(-params.range_db.max(0.0) / 20.0);
+        let detector = self.coefficient(DETECTOR_MS);
+        let open_coeff = self.coefficient(OPEN_MS);
+        let close_coeff = self.coefficient(CLOSE_MS);
+        let len = self.delay_line.len();
+
+        for sample in block.iter_mut() {
+            let input = *sample;
+            self.floor.push(input, params.percentile);
+
+            self.envelope = input * input + (self.envelope - input * input) * detector;
+            let level_db = 10.0 * (self.envelope + 1e-12).log10();
+
+            // Stay open until the floor is known rather than guessing
+            match self.floor.floor_db() {
+                None => self.open = true,
+                Some(floor_db) => {
+                    let open_db = floor_db + params.margin_db;
+                    let close_db = open_db - params.hysteresis_db.max(0.0);
+                    if level_db > open_db || self.speech {
+                        self.open = true;
+                        self.hold_remaining = hold;
+                    } else if level_db >= close_db {
+                        self.hold_remaining = hold;
+                    } else if self.hold_remaining > 0 {
+                        self.hold_remaining -= 1;
+                    } else {
+                        self.open = false;
+                    }
+                }
+            }
+
+            let target = if self.open { 1.0 } else { closed_gain };
+            let coeff = if target > self.gain { open_coeff } else { close_coeff };
+            self.gain = target + (self.gain - target) * coeff;
+
+            self.delay_line[self.write_pos] = input;
+            let delayed = self.delay_line[(self.write_pos + len - self.lookahead) % len];
+            self.write_pos = (self.write_pos + 1) % len;
+
+            *sample = delayed * self.gain;
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.lookahead
+    }
+
+    fn reset(&mut self) {
+        self.floor.reset();
+        self.delay_line.fill(0.0);
+        self.write_pos = 0;
+        self.envelope = 0.0;
+        self.open = true;
+        self.hold_remaining = 0;
+        self.gain = 1.0;
+    }
+}
+
@@ -0,0 +1,179 @@
+use super::pitch_shift::{semitones_to_ratio, MAX_SEMITONES};
+use super::psola::Psola;
+use super::scale::{frequency_to_midi_note, Key, Scale};
+use super::{Stage, BLOCK_CAPACITY};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_4;
+
+pub const MAX_HARMONY_VOICES: usize = 4;
+
+/// Samples between updates of the voice intervals.
+const CONTROL_BLOCK: usize = 64;
+
+/// Fade of the harmonies in and out as the voice turns voiced and unvoiced,
+/// so consonants and breaths are not doubled, in ms.
+const VOICING_MS: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalMode {
+    /// Intervals in semitones, the same whatever note is sung
+    Fixed,
+    /// Intervals in steps of the scale, so thirds turn major or minor to
+    /// stay in key
+    #[default]
+    Scale,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HarmonyVoice {
+    pub enabled: bool,
+    /// Distance from the sung note, in semitones or scale steps depending on
+    /// the mode; negative values harmonize below
+    pub interval: i32,
+    pub level: f32,
+    /// Position in the stereo field, -1.0 (left) - 1.0 (right)
+    pub pan: f32,
+}
+
+impl Default for HarmonyVoice {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 0,
+            level: 0.6,
+            pan: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HarmonizerParams {
+    pub enabled: bool,
+    pub mode: IntervalMode,
+    /// Key and scale the scale-step intervals follow
+    pub key: Key,
+    pub scale: Scale,
+    pub voices: [HarmonyVoice; MAX_HARMONY_VOICES],
+    /// Level of the original voice in the mix
+    pub dry_level: f32,
+}
+
+impl Default for HarmonizerParams {
+    fn default() -> Self {
+        let voice = |enabled, interval, pan| HarmonyVoice { enabled, interval, pan, ..HarmonyVoice::default() };
+        Self {
+            enabled: false,
+            mode: IntervalMode::Scale,
+            key: Key::C,
+            scale: Scale::Major,
+            // A third and a fifth above, spread left and right
+            voices: [voice(true, 2, -0.5), voice(true, 4, 0.5), voice(false, -3, 0.0), voice(false, 7, 0.0)],
+            dry_level: 1.0,
+        }
+    }
+}
+
+impl HarmonizerParams {
+    /// Shift of each voice in semitones for a voice singing `note`.
+    fn shifts(&self, note: f32) -> [f32; MAX_HARMONY_VOICES] {
+        let base = self.scale.nearest(self.key, note);
+        self.voices.each_ref().map(|voice| {
+            let semitones = match self.mode {
+                IntervalMode::Fixed => voice.interval,
+                IntervalMode::Scale => self.scale.transpose(self.key, base, voice.interval.clamp(-14, 14)) - base,
+            };
+            (semitones as f32).clamp(-MAX_SEMITONES, MAX_SEMITONES)
+        })
+    }
+}
+
+/// Adds up to four pitch-shifted copies of the voice. All voices are cut
+/// from one PSOLA analysis, so they share a single pitch tracker.
+///
+/// The dry voice is not delayed: the harmonies trail it by the PSOLA delay
+/// (about 16 ms), which is heard as part of the chord rather than as latency.
+/// Like the delay's ping-pong echoes, the panned part of the harmonies is
+/// kept in `side` for stereo outputs.
+pub struct Harmonizer {
+    sample_rate: f32,
+    psola: Psola,
+    shifts: [f32; MAX_HARMONY_VOICES],
+    /// Voicing of past control blocks, delayed to line up with the output
+    voiced: VecDeque<bool>,
+    voicing: f32,
+    side: Vec<f32>,
+}
+
+impl Harmonizer {
+    pub fn new(sample_rate: f32) -> Self {
+        let psola = Psola::with_voices(sample_rate, MAX_HARMONY_VOICES);
+        let voiced = VecDeque::from(vec![false; psola.latency() / CONTROL_BLOCK]);
+        Self {
+            sample_rate,
+            psola,
+            shifts: [0.0; MAX_HARMONY_VOICES],
+            voiced,
+            voicing: 0.0,
+            side: Vec::with_capacity(BLOCK_CAPACITY),
+        }
+    }
+
+    /// Left minus right over two for the last block.
+    pub fn side(&self) -> &[f32] {
+        &self.side
+    }
+}
+
+impl Stage for Harmonizer {
+    type Params = HarmonizerParams;
+
+    fn process(&mut self, block: &mut [f32], params: &HarmonizerParams) {
+        // Constant-power pan split into mid (L + R) / 2 and side (L - R) / 2
+        let gains = params.voices.each_ref().map(|voice| {
+            let level = if voice.enabled { voice.level.max(0.0) } else { 0.0 };
+            let angle = (voice.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+            let (left, right) = (angle.cos(), angle.sin());
+            (0.5 * (left + right) * level, 0.5 * (left - right) * level)
+        });
+        let voicing_coeff = (-1000.0 / (VOICING_MS * self.sample_rate)).exp();
+
+        self.side.clear();
+        for chunk in block.chunks_mut(CONTROL_BLOCK) {
+            // Unvoiced input keeps the last intervals until the next note
+            if let Some(frequency) = self.psola.frequency() {
+                self.shifts = params.shifts(frequency_to_midi_note(frequency));
+            }
+            let ratios = self.shifts.map(semitones_to_ratio);
+            self.voiced.push_back(self.psola.frequency().is_some());
+            let voiced = if self.voiced.pop_front().unwrap_or(false) { 1.0 } else { 0.0 };
+
+            for sample in chunk.iter_mut() {
+                let dry = *sample;
+                let (mut mid, mut side) = (0.0, 0.0);
+                if let Some(emitting) = self.psola.analyze(dry) {
+                    // Every voice keeps running so it can be switched on at any time
+                    for (voice, (&ratio, &(mid_gain, side_gain))) in ratios.iter().zip(&gains).enumerate() {
+                        let harmony = self.psola.synthesize(voice, ratio, emitting);
+                        mid += harmony * mid_gain;
+                        side += harmony * side_gain;
+                    }
+                }
+                self.voicing = voiced + (self.voicing - voiced) * voicing_coeff;
+                *sample = dry * params.dry_level.max(0.0) + mid * self.voicing;
+                self.side.push(side * self.voicing);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.psola.reset();
+        self.shifts = [0.0; MAX_HARMONY_VOICES];
+        self.voiced.iter_mut().for_each(|voiced| *voiced = false);
+        self.voicing = 0.0;
+        self.side.clear();
+    }
+}
@@ -0,0 +1,132 @@
+//! Low-frequency oscillator shared by the modulation stages, the ring
+//! modulator's carrier and the input callback's amplitude wobble.
+
+use super::rng::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Slope of the square wave's edges, relative to a sine of the same
+/// amplitude; softened so a delay or gain stepping with it does not click.
+const SQUARE_STEEPNESS: f32 = 8.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+    /// A new random level every cycle, glided to smoothly
+    Random,
+}
+
+/// Settings of an LFO as exposed by the stages that use one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LfoParams {
+    pub waveform: Waveform,
+    /// Cycles per second, in Hz
+    pub rate_hz: f32,
+    /// Modulation amount, 0.0 - 1.0, scaled by each stage to its own range
+    pub depth: f32,
+    /// Starting point of the cycle when the stage is switched on, in degrees
+    pub phase_deg: f32,
+}
+
+impl Default for LfoParams {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Sine,
+            rate_hz: 1.0,
+            depth: 0.5,
+            phase_deg: 0.0,
+        }
+    }
+}
+
+/// Phase accumulator producing a bipolar (-1.0 - 1.0) control signal.
+pub struct Lfo {
+    sample_rate: f32,
+    /// Position in the cycle, 0.0 - 1.0
+    phase: f32,
+    started: bool,
+    rng: Rng,
+    random_from: f32,
+    random_to: f32,
+}
+
+impl Lfo {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            phase: 0.0,
+            started: false,
+            rng: Rng::new(0x1f0_5eed),
+            random_from: 0.0,
+            random_to: 0.0,
+        }
+    }
+
+    /// Start the cycle at the configured phase, once after each reset.
+    pub fn begin(&mut self, params: &LfoParams) {
+        if !self.started {
+            self.set_phase(params.phase_deg);
+            self.started = true;
+        }
+    }
+
+    /// Forget the position; the next `begin` starts the cycle again.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.started = false;
+        self.random_from = 0.0;
+        self.random_to = 0.0;
+    }
+
+    /// Jump to a point in the cycle, in degrees.
+    pub fn set_phase(&mut self, degrees: f32) {
+        self.phase = (degrees / 360.0).rem_euclid(1.0);
+    }
+
+    /// Position in the cycle, in radians.
+    pub fn phase(&self) -> f32 {
+        2.0 * PI * self.phase
+    }
+
+    /// Move one sample ahead at `rate_hz`.
+    pub fn advance(&mut self, rate_hz: f32) {
+        self.phase += rate_hz.max(0.0) / self.sample_rate;
+        if self.phase >= 1.0 {
+            self.phase -= self.phase.floor();
+            self.random_from = self.random_to;
+            self.random_to = self.rng.next_f32() * 2.0 - 1.0;
+        }
+    }
+
+    /// Output at the current position, offset by `offset` of a cycle (so
+    /// several voices can share one LFO spread around the cycle). The random
+    /// waveform ignores the offset.
+    pub fn value(&self, waveform: Waveform, offset: f32) -> f32 {
+        let phase = (self.phase + offset).rem_euclid(1.0);
+        match waveform {
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Triangle => {
+                // Peaks a quarter of the way in, like the sine
+                let from_peak = (phase - 0.25).rem_euclid(1.0);
+                1.0 - 4.0 * from_peak.min(1.0 - from_peak)
+            }
+            Waveform::Square => (SQUARE_STEEPNESS * (2.0 * PI * phase).sin()).clamp(-1.0, 1.0),
+            Waveform::Random => {
+                let blend = 0.5 - 0.5 * (PI * self.phase).cos();
+                self.random_from + (self.random_to - self.random_from) * blend
+            }
+        }
+    }
+
+    /// Output at the current position, then move one sample ahead.
+    pub fn next(&mut self, waveform: Waveform, rate_hz: f32) -> f32 {
+        let value = self.value(waveform, 0.0);
+        self.advance(rate_hz);
+        value
+    }
+}
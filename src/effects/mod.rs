@@ -0,0 +1,92 @@
+//! Audio processing stages that run after the noise gate in the input callback.
+
+pub mod agc;
+pub mod anonymizer;
+pub mod autotune;
+pub mod band_limit;
+pub mod biquad;
+pub mod chain;
+pub mod conversion;
+pub mod convolution;
+pub mod deess;
+pub mod delay;
+pub mod denoise;
+pub mod distortion;
+pub mod dynamics;
+pub mod echo;
+pub mod eq;
+pub mod feedback;
+pub mod formant;
+pub mod gate;
+pub mod harmonizer;
+pub mod lfo;
+pub mod modulation;
+pub mod morph;
+pub mod pitch_detect;
+pub mod pitch_shift;
+pub mod psola;
+pub mod reverb;
+pub mod ring_mod;
+pub mod rng;
+pub mod scale;
+pub mod stft;
+pub mod vad;
+pub mod vocoder;
+pub mod whisper;
+
+pub use chain::{Chain, ChainSettings, ChainStatus};
+
+/// Block length that per-block buffers are allocated for up front, so the
+/// audio callback does not have to grow them.
+pub const BLOCK_CAPACITY: usize = 4096;
+
+/// A processing stage operating in place on a block of mono samples.
+pub trait Stage: Send {
+    type Params;
+
+    /// Process one block of audio using the current parameters.
+    fn process(&mut self, block: &mut [f32], params: &Self::Params);
+
+    /// Delay introduced by this stage, in samples.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Clear any internal state (buffers, filters, phase accumulators).
+    fn reset(&mut self) {}
+}
+
+/// A stage together with its activation state, so that it starts from a clean
+/// state each time it is switched on.
+pub struct Slot<S: Stage> {
+    pub stage: S,
+    active: bool,
+}
+
+impl<S: Stage> Slot<S> {
+    pub fn new(stage: S) -> Self {
+        Self {
+            stage,
+            active: false,
+        }
+    }
+
+    pub fn run(&mut self, enabled: bool, block: &mut [f32], params: &S::Params) {
+        if enabled && !self.active {
+            self.stage.reset();
+        }
+        self.active = enabled;
+        if enabled {
+            self.stage.process(block, params);
+        }
+    }
+
+    /// Latency of the stage when enabled, zero otherwise.
+    pub fn latency(&self, enabled: bool) -> usize {
+        if enabled {
+            self.stage.latency()
+        } else {
+            0
+        }
+    }
+}
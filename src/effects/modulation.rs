@@ -0,0 +1,339 @@
+use super::lfo::{Lfo, LfoParams, Waveform};
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Largest delay swing of the chorus, flanger and vibrato at full depth, in ms.
+const CHORUS_SWING_MS: f32 = 6.0;
+const FLANGER_SWING_MS: f32 = 5.0;
+const VIBRATO_SWING_MS: f32 = 4.0;
+
+/// Longest centre delay the chorus and flanger accept, in ms.
+const MAX_BASE_DELAY_MS: f32 = 30.0;
+
+pub const MAX_CHORUS_VOICES: usize = 4;
+
+/// Time constant of the tremolo gain, rounding the edges of a square LFO.
+const TREMOLO_SMOOTHING_MS: f32 = 1.0;
+
+/// Ring buffer read between samples with four-point Hermite interpolation,
+/// so a moving read position stays smooth.
+pub struct FractionalDelay {
+    buffer: Vec<f32>,
+    write_pos: usize,
+}
+
+impl FractionalDelay {
+    /// A line able to delay by up to `max_delay` samples.
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_delay + 4],
+            write_pos: 0,
+        }
+    }
+
+    /// Sample written `delay` samples ago (at least 2); call before `write`.
+    pub fn read(&self, delay: f32) -> f32 {
+        let len = self.buffer.len();
+        let delay = delay.clamp(2.0, (len - 3) as f32);
+        let whole = delay as usize;
+        let t = delay - whole as f32;
+        let at = |offset: usize| self.buffer[(self.write_pos + 2 * len - offset) % len];
+        let (y0, y1, y2, y3) = (at(whole - 1), at(whole), at(whole + 1), at(whole + 2));
+        let c1 = 0.5 * (y2 - y0);
+        let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+        let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+        ((c3 * t + c2) * t + c1) * t + y1
+    }
+
+    pub fn write(&mut self, value: f32) {
+        self.buffer[self.write_pos] = value;
+        self.write_pos = (self.write_pos + 1) % self.buffer.len();
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> f32 {
+    ms * sample_rate / 1000.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChorusParams {
+    pub enabled: bool,
+    pub lfo: LfoParams,
+    /// Centre delay of the voices, in ms
+    pub delay_ms: f32,
+    /// Number of delayed voices, spread evenly around the LFO cycle
+    pub voices: usize,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (voices only)
+    pub mix: f32,
+}
+
+impl Default for ChorusParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lfo: LfoParams {
+                rate_hz: 0.8,
+                depth: 0.4,
+                ..LfoParams::default()
+            },
+            delay_ms: 15.0,
+            voices: 3,
+            mix: 0.5,
+        }
+    }
+}
+
+/// Several copies of the voice, each delayed by a slowly wandering amount,
+/// so they drift in pitch and time against the original like an ensemble.
+pub struct Chorus {
+    sample_rate: f32,
+    lfo: Lfo,
+    line: FractionalDelay,
+}
+
+impl Chorus {
+    pub fn new(sample_rate: f32) -> Self {
+        let longest = ms_to_samples(MAX_BASE_DELAY_MS + CHORUS_SWING_MS, sample_rate);
+        Self {
+            sample_rate,
+            lfo: Lfo::new(sample_rate),
+            line: FractionalDelay::new(longest as usize + 1),
+        }
+    }
+}
+
+impl Stage for Chorus {
+    type Params = ChorusParams;
+
+    fn process(&mut self, block: &mut [f32], params: &ChorusParams) {
+        self.lfo.begin(&params.lfo);
+        let voices = params.voices.clamp(1, MAX_CHORUS_VOICES);
+        let swing = ms_to_samples(CHORUS_SWING_MS * params.lfo.depth.clamp(0.0, 1.0), self.sample_rate);
+        let base = ms_to_samples(params.delay_ms.clamp(1.0, MAX_BASE_DELAY_MS), self.sample_rate).max(swing + 2.0);
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        for sample in block.iter_mut() {
+            let dry = *sample;
+            let mut wet = 0.0;
+            for voice in 0..voices {
+                let modulation = self.lfo.value(params.lfo.waveform, voice as f32 / voices as f32);
+                wet += self.line.read(base + swing * modulation);
+            }
+            self.line.write(dry);
+            self.lfo.advance(params.lfo.rate_hz);
+            *sample = dry * (1.0 - mix) + wet / voices as f32 * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.line.reset();
+        self.lfo.reset();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FlangerParams {
+    pub enabled: bool,
+    pub lfo: LfoParams,
+    /// Shortest delay of the sweep, in ms
+    pub delay_ms: f32,
+    /// Share of the output fed back into the delay, -0.95 - 0.95; negative
+    /// values give a hollower sound
+    pub feedback: f32,
+    /// Blend between the dry and the delayed signal, 0.5 for the deepest notches
+    pub mix: f32,
+}
+
+impl Default for FlangerParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lfo: LfoParams {
+                waveform: Waveform::Triangle,
+                rate_hz: 0.25,
+                depth: 0.7,
+                ..LfoParams::default()
+            },
+            delay_ms: 1.0,
+            feedback: 0.5,
+            mix: 0.5,
+        }
+    }
+}
+
+/// A very short, swept delay mixed with the original: the comb filter notches
+/// move up and down the spectrum, the "jet plane" sound.
+pub struct Flanger {
+    sample_rate: f32,
+    lfo: Lfo,
+    line: FractionalDelay,
+}
+
+impl Flanger {
+    pub fn new(sample_rate: f32) -> Self {
+        let longest = ms_to_samples(MAX_BASE_DELAY_MS + FLANGER_SWING_MS, sample_rate);
+        Self {
+            sample_rate,
+            lfo: Lfo::new(sample_rate),
+            line: FractionalDelay::new(longest as usize + 1),
+        }
+    }
+}
+
+impl Stage for Flanger {
+    type Params = FlangerParams;
+
+    fn process(&mut self, block: &mut [f32], params: &FlangerParams) {
+        self.lfo.begin(&params.lfo);
+        let swing = ms_to_samples(FLANGER_SWING_MS * params.lfo.depth.clamp(0.0, 1.0), self.sample_rate);
+        let base = ms_to_samples(params.delay_ms.clamp(0.1, MAX_BASE_DELAY_MS), self.sample_rate);
+        let feedback = params.feedback.clamp(-0.95, 0.95);
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        for sample in block.iter_mut() {
+            let dry = *sample;
+            // The sweep runs from the base delay up to base + swing
+            let position = 0.5 + 0.5 * self.lfo.next(params.lfo.waveform, params.lfo.rate_hz);
+            let delayed = self.line.read(base + swing * position);
+            self.line.write(dry + feedback * delayed);
+            *sample = dry * (1.0 - mix) + delayed * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.line.reset();
+        self.lfo.reset();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct VibratoParams {
+    pub enabled: bool,
+    pub lfo: LfoParams,
+}
+
+impl Default for VibratoParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lfo: LfoParams {
+                rate_hz: 5.5,
+                depth: 0.3,
+                ..LfoParams::default()
+            },
+        }
+    }
+}
+
+/// Pitch wobble from a modulated delay with no dry signal: reading faster and
+/// slower than the input is written raises and lowers the pitch.
+pub struct Vibrato {
+    sample_rate: f32,
+    lfo: Lfo,
+    line: FractionalDelay,
+}
+
+impl Vibrato {
+    pub fn new(sample_rate: f32) -> Self {
+        let longest = ms_to_samples(2.0 * VIBRATO_SWING_MS, sample_rate);
+        Self {
+            sample_rate,
+            lfo: Lfo::new(sample_rate),
+            line: FractionalDelay::new(longest as usize + 4),
+        }
+    }
+}
+
+impl Stage for Vibrato {
+    type Params = VibratoParams;
+
+    fn process(&mut self, block: &mut [f32], params: &VibratoParams) {
+        self.lfo.begin(&params.lfo);
+        let swing = ms_to_samples(VIBRATO_SWING_MS * params.lfo.depth.clamp(0.0, 1.0), self.sample_rate);
+        // Centred on the largest swing so the latency does not follow the depth
+        let centre = ms_to_samples(VIBRATO_SWING_MS, self.sample_rate) + 2.0;
+
+        for sample in block.iter_mut() {
+            let modulation = self.lfo.next(params.lfo.waveform, params.lfo.rate_hz);
+            let delayed = self.line.read(centre + swing * modulation);
+            self.line.write(*sample);
+            *sample = delayed;
+        }
+    }
+
+    fn latency(&self) -> usize {
+        ms_to_samples(VIBRATO_SWING_MS, self.sample_rate) as usize + 2
+    }
+
+    fn reset(&mut self) {
+        self.line.reset();
+        self.lfo.reset();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TremoloParams {
+    pub enabled: bool,
+    pub lfo: LfoParams,
+}
+
+impl Default for TremoloParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lfo: LfoParams {
+                rate_hz: 6.0,
+                depth: 0.5,
+                ..LfoParams::default()
+            },
+        }
+    }
+}
+
+/// Periodic volume changes; at full depth the gain dips to silence.
+pub struct Tremolo {
+    sample_rate: f32,
+    lfo: Lfo,
+    gain: f32,
+}
+
+impl Tremolo {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            lfo: Lfo::new(sample_rate),
+            gain: 1.0,
+        }
+    }
+}
+
+impl Stage for Tremolo {
+    type Params = TremoloParams;
+
+    fn process(&mut self, block: &mut [f32], params: &TremoloParams) {
+        self.lfo.begin(&params.lfo);
+        let depth = params.lfo.depth.clamp(0.0, 1.0);
+        let smoothing = (-1000.0 / (TREMOLO_SMOOTHING_MS * self.sample_rate)).exp();
+
+        for sample in block.iter_mut() {
+            let modulation = self.lfo.next(params.lfo.waveform, params.lfo.rate_hz);
+            let target = 1.0 - depth * (0.5 - 0.5 * modulation);
+            self.gain = target + (self.gain - target) * smoothing;
+            *sample *= self.gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lfo.reset();
+        self.gain = 1.0;
+    }
+}
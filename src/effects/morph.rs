@@ -0,0 +1,208 @@
+//! Morphing between two complete presets: continuous parameters are blended,
+//! everything else (switches, algorithms, seeds, counts, file names) is taken
+//! from one side.
+
+use super::chain::ChainSettings;
+use serde_json::{Number, Value};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Two presets to morph between, kept as JSON trees so that moving the morph
+/// only walks the trees. Blending is done by whoever moves the morph, away
+/// from the audio thread; the chain just takes the finished settings.
+pub struct MorphPresets {
+    a: Value,
+    b: Value,
+    interpolable: bool,
+}
+
+/// Settings at one position of a morph, ready to be handed to the chain.
+pub struct MorphPosition {
+    pub presets: Arc<MorphPresets>,
+    pub amount: f32,
+    /// Blend with A's switches and algorithms, for the chain itself
+    pub own: ChainSettings,
+    /// Blend with B's switches and algorithms, for the second chain
+    pub partner: Option<ChainSettings>,
+}
+
+impl MorphPresets {
+    pub fn new(a: &ChainSettings, b: &ChainSettings) -> Self {
+        let (a, b) = (tree(a), tree(b));
+        let interpolable = same_structure(&a, &b);
+        Self { a, b, interpolable }
+    }
+
+    /// Whether the two presets differ only in continuous numbers, so that a
+    /// single chain running the blend covers the whole morph.
+    pub fn interpolable(&self) -> bool {
+        self.interpolable
+    }
+
+    /// Settings between A (amount 0.0) and B (amount 1.0). Continuous numbers
+    /// are interpolated; whole numbers (seeds, band and voice counts,
+    /// intervals) and everything else come from B when `discrete_from_b` is
+    /// set, from A otherwise.
+    pub fn blend(&self, amount: f32, discrete_from_b: bool) -> ChainSettings {
+        let blended = blend_value(&self.a, &self.b, amount.clamp(0.0, 1.0) as f64, discrete_from_b);
+        let side = if discrete_from_b { &self.b } else { &self.a };
+        serde_json::from_value(blended)
+            .or_else(|_| serde_json::from_value(side.clone()))
+            .unwrap_or_default()
+    }
+
+    /// Both blends at `amount`, the second only when the presets need a
+    /// second chain.
+    pub fn position(self: &Arc<Self>, amount: f32) -> MorphPosition {
+        let amount = amount.clamp(0.0, 1.0);
+        MorphPosition {
+            presets: Arc::clone(self),
+            amount,
+            own: self.blend(amount, false),
+            partner: (!self.interpolable).then(|| self.blend(amount, true)),
+        }
+    }
+}
+
+fn tree(settings: &ChainSettings) -> Value {
+    serde_json::to_value(settings).unwrap_or(Value::Null)
+}
+
+fn blend_value(a: &Value, b: &Value, amount: f64, discrete_from_b: bool) -> Value {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) if continuous(x) && continuous(y) => blend_number(x, y, amount),
+        (Value::Object(x), Value::Object(y)) => Value::Object(
+            x.iter()
+                .map(|(key, value)| {
+                    let blended = match y.get(key) {
+                        Some(other) => blend_value(value, other, amount, discrete_from_b),
+                        None => value.clone(),
+                    };
+                    (key.clone(), blended)
+                })
+                .collect(),
+        ),
+        (Value::Array(x), Value::Array(y)) if x.len() == y.len() => Value::Array(
+            x.iter()
+                .zip(y)
+                .map(|(value, other)| blend_value(value, other, amount, discrete_from_b))
+                .collect(),
+        ),
+        _ if discrete_from_b => b.clone(),
+        _ => a.clone(),
+    }
+}
+
+fn blend_number(x: &Number, y: &Number, amount: f64) -> Value {
+    let (from, to) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+    Number::from_f64(from + (to - from) * amount).map_or(Value::Null, Value::Number)
+}
+
+/// Whether a number can take values in between. Integer settings are seeds,
+/// counts and intervals, where a value in between is a different thing
+/// rather than a mix of the two.
+fn continuous(number: &Number) -> bool {
+    number.is_f64()
+}
+
+fn same_structure(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => (continuous(x) && continuous(y)) || x == y,
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, value)| y.get(key).is_some_and(|other| same_structure(value, other)))
+        }
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(v, w)| same_structure(v, w)),
+        _ => a == b,
+    }
+}
+
+/// Fixed delay of a block and its stereo side, to line up two chains with
+/// different latencies before they are crossfaded.
+pub struct Alignment {
+    mid: VecDeque<f32>,
+    side: VecDeque<f32>,
+}
+
+impl Alignment {
+    pub fn new() -> Self {
+        Self {
+            mid: VecDeque::new(),
+            side: VecDeque::new(),
+        }
+    }
+
+    /// Delay `block` and `side` by `delay` samples in place. An empty side
+    /// counts as silence and comes back filled in.
+    pub fn process(&mut self, delay: usize, block: &mut [f32], side: &mut Vec<f32>) {
+        let len = block.len();
+        side.resize(len, 0.0);
+        for (queue, samples) in [(&mut self.mid, &mut block[..]), (&mut self.side, &mut side[..])] {
+            queue.extend(samples.iter().copied());
+            while queue.len() < delay + len {
+                queue.push_front(0.0);
+            }
+            while queue.len() > delay + len {
+                queue.pop_front();
+            }
+            for (sample, delayed) in samples.iter_mut().zip(queue.drain(..len)) {
+                *sample = delayed;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_interpolates_continuous_numbers() {
+        let mut a = ChainSettings::default();
+        let mut b = ChainSettings::default();
+        a.pitch.semitones = -4.0;
+        b.pitch.semitones = 8.0;
+        let presets = MorphPresets::new(&a, &b);
+        assert!(presets.interpolable());
+        assert_eq!(presets.blend(0.0, false).pitch.semitones, -4.0);
+        assert_eq!(presets.blend(0.25, false).pitch.semitones, -1.0);
+        assert_eq!(presets.blend(1.0, false).pitch.semitones, 8.0);
+    }
+
+    #[test]
+    fn switches_come_from_the_chosen_side() {
+        let a = ChainSettings::default();
+        let mut b = ChainSettings::default();
+        b.reverb.enabled = true;
+        let presets = MorphPresets::new(&a, &b);
+        assert!(!presets.interpolable());
+        assert!(!presets.blend(0.9, false).reverb.enabled);
+        assert!(presets.blend(0.1, true).reverb.enabled);
+    }
+
+    #[test]
+    fn differing_counts_are_not_interpolable() {
+        let a = ChainSettings::default();
+        let mut b = ChainSettings::default();
+        b.vocoder.bands = a.vocoder.bands + 8;
+        let presets = MorphPresets::new(&a, &b);
+        assert!(!presets.interpolable());
+        assert_eq!(presets.blend(0.5, false).vocoder.bands, a.vocoder.bands);
+        assert_eq!(presets.blend(0.5, true).vocoder.bands, b.vocoder.bands);
+    }
+
+    #[test]
+    fn blend_takes_seeds_from_one_side() {
+        let mut a = ChainSettings::default();
+        let mut b = ChainSettings::default();
+        a.anonymizer.seed = Some(10);
+        b.anonymizer.seed = Some(4_000_000_000);
+        let presets = MorphPresets::new(&a, &b);
+        assert!(!presets.interpolable());
+        for amount in [0.0, 0.3, 0.5, 0.7, 1.0] {
+            for discrete_from_b in [false, true] {
+                let seed = presets.blend(amount, discrete_from_b).anonymizer.seed;
+                assert!(seed == a.anonymizer.seed || seed == b.anonymizer.seed, "{:?} at {}", seed, amount);
+            }
+        }
+    }
+}
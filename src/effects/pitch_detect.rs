@@ -0,0 +1,172 @@
+/// Streaming YIN pitch tracker.
+///
+/// Samples are pushed one at a time; every `hop` samples the cumulative mean
+/// normalized difference function is evaluated over the most recent window and
+/// the voicing decision and period estimate are updated.
+pub struct PitchTracker {
+    sample_rate: f32,
+    min_period: usize,
+    max_period: usize,
+    hop: usize,
+    history: Vec<f32>,
+    write_pos: usize,
+    since_update: usize,
+    difference: Vec<f32>,
+    window: Vec<f32>,
+    period: Option<f32>,
+}
+
+/// Normalized difference below which a lag is accepted as the period.
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// RMS level below which the input is treated as silence (and unvoiced).
+const SILENCE_RMS: f32 = 0.002;
+
+impl PitchTracker {
+    pub fn new(sample_rate: f32, min_frequency: f32, max_frequency: f32) -> Self {
+        let min_period = (sample_rate / max_frequency).floor().max(2.0) as usize;
+        let max_period = (sample_rate / min_frequency).ceil() as usize;
+
+        Self {
+            sample_rate,
+            min_period,
+            max_period,
+            hop: 512,
+            history: vec![0.0; max_period * 2],
+            write_pos: 0,
+            since_update: 0,
+            difference: vec![0.0; max_period + 1],
+            window: vec![0.0; max_period * 2],
+            period: None,
+        }
+    }
+
+    /// Longest period the tracker can report, in samples.
+    pub fn max_period(&self) -> usize {
+        self.max_period
+    }
+
+    /// Estimated period in samples, or `None` when the input is unvoiced.
+    pub fn period(&self) -> Option<f32> {
+        self.period
+    }
+
+    /// Estimated fundamental frequency in Hz, or `None` when unvoiced.
+    pub fn frequency(&self) -> Option<f32> {
+        self.period.map(|period| self.sample_rate / period)
+    }
+
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write_pos = 0;
+        self.since_update = 0;
+        self.period = None;
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        self.history[self.write_pos] = sample;
+        self.write_pos = (self.write_pos + 1) % self.history.len();
+        self.since_update += 1;
+
+        if self.since_update >= self.hop {
+            self.since_update = 0;
+            self.analyze();
+        }
+    }
+
+    fn analyze(&mut self) {
+        // Unroll the ring buffer so the oldest sample comes first
+        let len = self.history.len();
+        for i in 0..len {
+            self.window[i] = self.history[(self.write_pos + i) % len];
+        }
+
+        let integration = self.max_period;
+        let rms = (self.window[len - integration..].iter().map(|x| x * x).sum::<f32>()
+            / integration as f32)
+            .sqrt();
+        if rms < SILENCE_RMS {
+            self.period = None;
+            return;
+        }
+
+        // Difference function d(tau) and its cumulative mean normalization
+        self.difference[0] = 1.0;
+        let mut running_sum = 0.0;
+        for tau in 1..=self.max_period {
+            let mut sum = 0.0;
+            for j in 0..integration {
+                let delta = self.window[j] - self.window[j + tau];
+                sum += delta * delta;
+            }
+            running_sum += sum;
+            self.difference[tau] = if running_sum > 0.0 {
+                sum * tau as f32 / running_sum
+            } else {
+                1.0
+            };
+        }
+
+        // First dip below the threshold, followed down to its local minimum
+        let mut found = None;
+        let mut tau = self.min_period;
+        while tau < self.max_period {
+            if self.difference[tau] < YIN_THRESHOLD {
+                while tau + 1 < self.max_period && self.difference[tau + 1] < self.difference[tau] {
+                    tau += 1;
+                }
+                found = Some(tau);
+                break;
+            }
+            tau += 1;
+        }
+
+        self.period = found.map(|tau| self.refine(tau));
+    }
+
+    /// Parabolic interpolation around the selected lag.
+    fn refine(&self, tau: usize) -> f32 {
+        if tau < 1 || tau + 1 > self.max_period {
+            return tau as f32;
+        }
+        let (a, b, c) = (
+            self.difference[tau - 1],
+            self.difference[tau],
+            self.difference[tau + 1],
+        );
+        let denominator = a - 2.0 * b + c;
+        if denominator.abs() < f32::EPSILON {
+            tau as f32
+        } else {
+            tau as f32 + 0.5 * (a - c) / denominator
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn tracks_a_sine() {
+        let sample_rate = 48000.0;
+        for frequency in [110.0, 220.0, 440.0] {
+            let mut tracker = PitchTracker::new(sample_rate, 70.0, 1000.0);
+            for i in 0..sample_rate as usize / 4 {
+                tracker.push(0.5 * (2.0 * PI * frequency * i as f32 / sample_rate).sin());
+            }
+            let estimate = tracker.frequency().unwrap();
+            assert!((estimate / frequency - 1.0).abs() < 0.01, "{} Hz tracked as {} Hz", frequency, estimate);
+        }
+    }
+
+    #[test]
+    fn silence_has_no_pitch() {
+        let mut tracker = PitchTracker::new(48000.0, 70.0, 1000.0);
+        for _ in 0..12000 {
+            tracker.push(0.0);
+        }
+        assert_eq!(tracker.frequency(), None);
+    }
+}
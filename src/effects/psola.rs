@@ -0,0 +1,216 @@
+use super::pitch_detect::PitchTracker;
+use super::pitch_shift::PitchShiftParams;
+use super::Stage;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Lowest and highest fundamental tracked, covering adult and child speech.
+const MIN_FREQUENCY: f32 = 70.0;
+const MAX_FREQUENCY: f32 = 500.0;
+
+/// Half-length of the fixed grains used to pass unvoiced audio through unchanged.
+const UNVOICED_HALF: usize = 256;
+
+const RING_SIZE: usize = 8192;
+
+/// Pitch-synchronous overlap-add pitch shifter for speech.
+///
+/// Analysis marks are placed one period apart on the signal peaks while the
+/// tracker reports voiced input. Two-period Hann grains around those marks are
+/// re-spaced at `period / ratio` on the output, which changes pitch while
+/// keeping the formants in place. Unvoiced input (fricatives, silence) is
+/// resynthesized with fixed, aligned grains and passes through unchanged.
+///
+/// The output lags the input by a little more than the longest tracked period,
+/// well below the delay of the phase vocoder.
+///
+/// Several output voices, each with its own ratio, can share one analysis;
+/// the harmonizer uses this to build its voices from a single pitch tracker.
+pub struct Psola {
+    tracker: PitchTracker,
+    delay: usize,
+    input: Vec<f32>,
+    written: usize,
+    marks: VecDeque<usize>,
+    voices: Vec<Voice>,
+}
+
+/// Output ring and next grain position of one output voice.
+struct Voice {
+    output: Vec<f32>,
+    next_synthesis: f64,
+}
+
+impl Psola {
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_voices(sample_rate, 1)
+    }
+
+    pub fn with_voices(sample_rate: f32, count: usize) -> Self {
+        let tracker = PitchTracker::new(sample_rate, MIN_FREQUENCY, MAX_FREQUENCY);
+        let delay = (tracker.max_period() + 64).max(2 * UNVOICED_HALF + 2);
+
+        Self {
+            tracker,
+            delay,
+            input: vec![0.0; RING_SIZE],
+            written: 0,
+            marks: VecDeque::new(),
+            voices: (0..count)
+                .map(|_| Voice { output: vec![0.0; RING_SIZE], next_synthesis: 0.0 })
+                .collect(),
+        }
+    }
+
+    /// Detected fundamental in Hz, or `None` while the input is unvoiced.
+    pub fn frequency(&self) -> Option<f32> {
+        self.tracker.frequency()
+    }
+
+    /// Place analysis marks one period apart on the local signal maxima, as far
+    /// as enough input is available to extract a full grain around them.
+    fn update_marks(&mut self, period: usize) {
+        let available = self.written;
+
+        let stale = match self.marks.back() {
+            Some(&last) => last + 3 * period < available,
+            None => true,
+        };
+        if stale {
+            self.marks.clear();
+            if available < 2 * period {
+                return;
+            }
+            let mark = self.peak_between(available - 2 * period, available - period);
+            self.marks.push_back(mark);
+        }
+
+        while let Some(&last) = self.marks.back() {
+            let candidate = last + period;
+            if candidate + period > available {
+                break;
+            }
+            let search = period / 4;
+            let end = (candidate + search).min(available - period);
+            let mark = self.peak_between(candidate - search, end + 1);
+            self.marks.push_back(mark.max(last + period / 2));
+        }
+
+        while self.marks.len() > 16 {
+            self.marks.pop_front();
+        }
+    }
+
+    fn peak_between(&self, start: usize, end: usize) -> usize {
+        (start..end.max(start + 1))
+            .max_by(|&a, &b| {
+                self.input[a % RING_SIZE].total_cmp(&self.input[b % RING_SIZE])
+            })
+            .unwrap_or(start)
+    }
+
+    fn nearest_mark(&self, position: usize) -> Option<usize> {
+        self.marks
+            .iter()
+            .copied()
+            .min_by_key(|&mark| mark.abs_diff(position))
+    }
+
+    /// Overlap-add a Hann grain of `2 * half` samples taken around `source` into
+    /// the voice's output, centred on `target`.
+    fn add_grain(&mut self, voice: usize, source: usize, target: usize, half: usize, gain: f32, emitted: usize) {
+        if source < half || target < half {
+            return;
+        }
+        let length = 2 * half;
+        for i in 0..length {
+            let destination = target - half + i;
+            if destination < emitted {
+                continue;
+            }
+            let window = 0.5 - 0.5 * (PI * i as f32 / half as f32).cos();
+            let sample = self.input[(source - half + i) % RING_SIZE];
+            self.voices[voice].output[destination % RING_SIZE] += sample * window * gain;
+        }
+    }
+
+    /// Take in one input sample and update the analysis marks. Returns the
+    /// position of the output sample now due, once the delay has filled.
+    pub fn analyze(&mut self, sample: f32) -> Option<usize> {
+        self.input[self.written % RING_SIZE] = sample;
+        self.written += 1;
+        self.tracker.push(sample);
+
+        match self.tracker.period() {
+            Some(period) => self.update_marks(period.round() as usize),
+            None => self.marks.clear(),
+        }
+
+        (self.written > self.delay).then(|| self.written - 1 - self.delay)
+    }
+
+    /// Output sample of `voice` at position `emitting`, shifted by `ratio`.
+    pub fn synthesize(&mut self, voice: usize, ratio: f32, emitting: usize) -> f32 {
+        let period = self.tracker.period().map(|p| p.round() as usize);
+
+        // Add every grain that starts at or before the sample being emitted
+        loop {
+            let next_synthesis = self.voices[voice].next_synthesis;
+            let voiced = period.zip(self.nearest_mark(next_synthesis as usize));
+            let half = voiced.map_or(UNVOICED_HALF, |(period, _)| period);
+            let target = next_synthesis.round() as usize;
+            if target > emitting + half {
+                break;
+            }
+
+            match voiced {
+                Some((period, _)) => {
+                    let source = self.nearest_mark(target).unwrap_or(target);
+                    let gain = if ratio > 1.0 { 1.0 / ratio } else { 1.0 };
+                    self.add_grain(voice, source, target, period, gain, emitting);
+                    self.voices[voice].next_synthesis += period as f64 / ratio as f64;
+                }
+                None => {
+                    self.add_grain(voice, target, target, UNVOICED_HALF, 1.0, emitting);
+                    self.voices[voice].next_synthesis += UNVOICED_HALF as f64;
+                }
+            }
+        }
+
+        let slot = emitting % RING_SIZE;
+        let output = &mut self.voices[voice].output;
+        let sample = output[slot];
+        output[slot] = 0.0;
+        sample
+    }
+}
+
+impl Stage for Psola {
+    type Params = PitchShiftParams;
+
+    fn process(&mut self, block: &mut [f32], params: &PitchShiftParams) {
+        let ratio = params.ratio();
+
+        for sample in block.iter_mut() {
+            *sample = match self.analyze(*sample) {
+                Some(emitting) => self.synthesize(0, ratio, emitting),
+                None => 0.0,
+            };
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.delay
+    }
+
+    fn reset(&mut self) {
+        self.tracker.reset();
+        self.input.fill(0.0);
+        self.written = 0;
+        self.marks.clear();
+        for voice in self.voices.iter_mut() {
+            voice.output.fill(0.0);
+            voice.next_synthesis = 0.0;
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Delay lines in the feedback network.
+const LINES: usize = 8;
+
+/// Longest pre-delay, in ms.
+const MAX_PRE_DELAY_MS: f32 = 250.0;
+
+/// Range of the size control: delay lengths are scaled from half to twice
+/// the style's base lengths.
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 2.0;
+
+/// Fastest change of a delay length when the size is moved, in samples per
+/// sample; gliding the read position avoids clicks.
+const LENGTH_GLIDE: f32 = 0.05;
+
+/// Input diffusers: Schroeder all-passes smearing the input before it enters
+/// the network, so the first reflections are not heard as discrete echoes.
+const DIFFUSER_MS: [f32; 4] = [4.77, 3.59, 12.73, 9.31];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReverbStyle {
+    /// Short, mutually prime delays: a small to medium room
+    Room,
+    /// Very short lines behind strong diffusion: a dense, bright metal plate
+    Plate,
+    /// Long delays and gentle diffusion: a concert hall or church
+    #[default]
+    Hall,
+}
+
+impl ReverbStyle {
+    /// Delay line lengths at size 0.5, in ms.
+    fn base_delays_ms(self) -> [f32; LINES] {
+        match self {
+            ReverbStyle::Room => [11.3, 13.1, 15.7, 17.9, 19.7, 22.3, 25.1, 28.7],
+            ReverbStyle::Plate => [7.1, 8.9, 10.3, 12.1, 13.7, 15.1, 17.3, 19.9],
+            ReverbStyle::Hall => [29.3, 34.1, 38.9, 43.7, 49.1, 55.3, 61.7, 68.9],
+        }
+    }
+
+    /// All-pass coefficient of the input diffusers.
+    fn diffusion(self) -> f32 {
+        match self {
+            ReverbStyle::Room => 0.6,
+            ReverbStyle::Plate => 0.75,
+            ReverbStyle::Hall => 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReverbParams {
+    pub enabled: bool,
+    pub style: ReverbStyle,
+    /// Room size, 0.0 - 1.0; scales the delay lengths
+    pub size: f32,
+    /// Time for the tail to fall by 60 dB (RT60), in seconds
+    pub decay_s: f32,
+    /// High-frequency absorption, 0.0 (bright) - 1.0 (dark)
+    pub damping: f32,
+    /// Gap between the dry voice and the onset of the reverb, in ms
+    pub pre_delay_ms: f32,
+    /// Wet share of the output, 0.0 (dry) - 1.0 (reverb only)
+    pub mix: f32,
+}
+
+impl Default for ReverbParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            style: ReverbStyle::Hall,
+            size: 0.5,
+            decay_s: 1.8,
+            damping: 0.4,
+            pre_delay_ms: 20.0,
+            mix: 0.25,
+        }
+    }
+}
+
+/// Ring buffer read at a fractional distance behind the write position.
+struct DelayLine {
+    buffer: Vec<f32>,
+    write_pos: usize,
+}
+
+impl DelayLine {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buffer: vec![0.0; capacity.max(2)],
+            write_pos: 0,
+        }
+    }
+
+    fn read(&self, delay: f32) -> f32 {
+        let len = self.buffer.len();
+        let delay = delay.clamp(1.0, (len - 1) as f32);
+        let whole = delay as usize;
+        let frac = delay - whole as f32;
+        let a = self.buffer[(self.write_pos + len - whole) % len];
+        let b = self.buffer[(self.write_pos + len - whole - 1) % len];
+        a + (b - a) * frac
+    }
+
+    fn write(&mut self, value: f32) {
+        self.buffer[self.write_pos] = value;
+        self.write_pos = (self.write_pos + 1) % self.buffer.len();
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
+}
+
+struct Diffuser {
+    line: DelayLine,
+    delay: f32,
+}
+
+impl Diffuser {
+    fn process(&mut self, input: f32, g: f32) -> f32 {
+        let delayed = self.line.read(self.delay);
+        let feed = input + g * delayed;
+        self.line.write(feed);
+        delayed - g * feed
+    }
+}
+
+/// Feedback delay network reverb.
+///
+/// The pre-delayed, diffused input feeds eight delay lines whose outputs are
+/// mixed by a Householder matrix and fed back. Each line loses just enough
+/// per pass to fall by 60 dB in the decay time, and a one-pole low-pass in
+/// the loop makes high frequencies die away faster, as in a real room.
+pub struct Reverb {
+    sample_rate: f32,
+    pre_delay: DelayLine,
+    diffusers: Vec<Diffuser>,
+    lines: Vec<DelayLine>,
+    lengths: [f32; LINES],
+    damping_state: [f32; LINES],
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32) -> Self {
+        let longest_ms = ReverbStyle::Hall.base_delays_ms()[LINES - 1] * MAX_SCALE;
+        let line_capacity = (longest_ms * sample_rate / 1000.0) as usize + 2;
+        Self {
+            sample_rate,
+            pre_delay: DelayLine::new((MAX_PRE_DELAY_MS * sample_rate / 1000.0) as usize + 2),
+            diffusers: DIFFUSER_MS
+                .iter()
+                .map(|ms| {
+                    let delay = ms * sample_rate / 1000.0;
+                    Diffuser {
+                        line: DelayLine::new(delay as usize + 2),
+                        delay,
+                    }
+                })
+                .collect(),
+            lines: (0..LINES).map(|_| DelayLine::new(line_capacity)).collect(),
+            lengths: [0.0; LINES],
+            damping_state: [0.0; LINES],
+        }
+    }
+
+    fn target_lengths(&self, params: &ReverbParams) -> [f32; LINES] {
+        let scale = MIN_SCALE * (MAX_SCALE / MIN_SCALE).powf(params.size.clamp(0.0, 1.0));
+        let mut lengths = params.style.base_delays_ms();
+        for length in lengths.iter_mut() {
+            *length *= scale * self.sample_rate / 1000.0;
+        }
+        lengths
+    }
+}
+
+impl Stage for Reverb {
+    type Params = ReverbParams;
+
+    fn process(&mut self, block: &mut [f32], params: &ReverbParams) {
+        let targets = self.target_lengths(params);
+        if self.lengths[0] == 0.0 {
+            self.lengths = targets;
+        }
+        let decay_samples = params.decay_s.clamp(0.1, 30.0) * self.sample_rate;
+        let damping = params.damping.clamp(0.0, 1.0) * 0.7;
+        let diffusion = params.style.diffusion();
+        let pre_delay = (params.pre_delay_ms.clamp(0.0, MAX_PRE_DELAY_MS) * self.sample_rate / 1000.0).max(1.0);
+        let mix = params.mix.clamp(0.0, 1.0);
+        // Scales the tail to about the level of the dry voice
+        let wet_gain = (1.0 - diffusion * 0.5) / (LINES as f32).sqrt();
+
+        // Loss for one pass through each line, -60 dB over the decay time;
+        // the lengths glide slowly enough to update this once per block
+        let mut gains = [0.0f32; LINES];
+        for (gain, length) in gains.iter_mut().zip(&self.lengths) {
+            *gain = 10.0f32.powf(-3.0 * length / decay_samples);
+        }
+
+        let mut outputs = [0.0f32; LINES];
+        for sample in block.iter_mut() {
+            let dry = *sample;
+            self.pre_delay.write(dry);
+            let mut input = self.pre_delay.read(pre_delay);
+            for diffuser in self.diffusers.iter_mut() {
+                input = diffuser.process(input, diffusion);
+            }
+
+            let mut wet = 0.0;
+            for (i, line) in self.lines.iter().enumerate() {
+                self.lengths[i] += (targets[i] - self.lengths[i]).clamp(-LENGTH_GLIDE, LENGTH_GLIDE);
+                let state = &mut self.damping_state[i];
+                *state = line.read(self.lengths[i]) * gains[i] * (1.0 - damping) + *state * damping;
+                if state.abs() < 1e-20 {
+                    *state = 0.0;
+                }
+                outputs[i] = *state;
+                wet += if i % 2 == 0 { *state } else { -*state };
+            }
+
+            // Householder feedback matrix: lossless and maximally mixing
+            let mean = outputs.iter().sum::<f32>() * 2.0 / LINES as f32;
+            for (line, output) in self.lines.iter_mut().zip(&outputs) {
+                line.write(output - mean + input);
+            }
+
+            *sample = dry * (1.0 - mix) + wet * wet_gain * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pre_delay.clear();
+        for diffuser in self.diffusers.iter_mut() {
+            diffuser.line.clear();
+        }
+        for line in self.lines.iter_mut() {
+            line.clear();
+        }
+        self.lengths = [0.0; LINES];
+        self.damping_state = [0.0; LINES];
+    }
+}
@@ -0,0 +1,53 @@
+use super::lfo::{Lfo, Waveform};
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RingModParams {
+    pub enabled: bool,
+    /// Carrier frequency in Hz
+    pub frequency: f32,
+    /// Blend between the dry (0.0) and ring-modulated (1.0) signal
+    pub mix: f32,
+}
+
+impl Default for RingModParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: 80.0,
+            mix: 1.0,
+        }
+    }
+}
+
+/// Multiplies the voice with a sine carrier, the classic "robot" sound.
+pub struct RingModulator {
+    carrier: Lfo,
+}
+
+impl RingModulator {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            carrier: Lfo::new(sample_rate),
+        }
+    }
+}
+
+impl Stage for RingModulator {
+    type Params = RingModParams;
+
+    fn process(&mut self, block: &mut [f32], params: &RingModParams) {
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        for sample in block.iter_mut() {
+            let modulated = *sample * self.carrier.next(Waveform::Sine, params.frequency);
+            *sample = *sample * (1.0 - mix) + modulated * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.carrier.reset();
+    }
+}
@@ -0,0 +1,30 @@
+/// Small, fast pseudo-random generator (xorshift32) for noise sources.
+///
+/// Not suitable for anything security related; it only needs to be cheap and
+/// allocation free on the audio thread.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            state: seed.max(1),
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Uniform value in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
@@ -0,0 +1,122 @@
+//! Keys, scales and note names for the pitch correction and harmonizer stages.
+//! Pitches are MIDI note numbers (69 = A4 = 440 Hz), fractional in between.
+
+use serde::{Deserialize, Serialize};
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    #[default]
+    C,
+    CSharp,
+    D,
+    DSharp,
+    E,
+    F,
+    FSharp,
+    G,
+    GSharp,
+    A,
+    ASharp,
+    B,
+}
+
+impl Key {
+    /// Pitch class of the tonic, 0 (C) - 11 (B).
+    pub fn pitch_class(self) -> i32 {
+        self as i32
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    /// All twelve notes
+    #[default]
+    Chromatic,
+    Major,
+    /// Natural minor
+    Minor,
+    HarmonicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+}
+
+impl Scale {
+    /// Notes of the scale in semitones above the tonic.
+    pub fn steps(self) -> &'static [i32] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+
+    /// Whether the whole-numbered `note` belongs to the scale in `key`.
+    pub fn contains(self, key: Key, note: i32) -> bool {
+        self.steps().contains(&(note - key.pitch_class()).rem_euclid(12))
+    }
+
+    /// Scale note closest to `note`; ties go to the lower note.
+    pub fn nearest(self, key: Key, note: f32) -> i32 {
+        let centre = note.round() as i32;
+        (centre - 6..=centre + 6)
+            .filter(|&candidate| self.contains(key, candidate))
+            .min_by(|&a, &b| (a as f32 - note).abs().total_cmp(&(b as f32 - note).abs()))
+            .unwrap_or(centre)
+    }
+
+    /// The scale note `degrees` steps above (or below, when negative) the
+    /// scale note `note`.
+    pub fn transpose(self, key: Key, note: i32, degrees: i32) -> i32 {
+        let mut transposed = note;
+        for _ in 0..degrees.abs() {
+            transposed += degrees.signum();
+            while !self.contains(key, transposed) {
+                transposed += degrees.signum();
+            }
+        }
+        transposed
+    }
+}
+
+pub fn frequency_to_midi_note(frequency: f32) -> f32 {
+    69.0 + 12.0 * (frequency / 440.0).log2()
+}
+
+/// Name and octave of the nearest note, e.g. "A4".
+pub fn note_name(note: f32) -> String {
+    let note = note.round() as i32;
+    format!("{}{}", NOTE_NAMES[note.rem_euclid(12) as usize], note.div_euclid(12) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_snaps_to_the_scale() {
+        assert_eq!(Scale::Major.nearest(Key::C, 60.3), 60);
+        assert_eq!(Scale::Major.nearest(Key::C, 63.4), 64);
+        // Halfway between D and E goes down
+        assert_eq!(Scale::Major.nearest(Key::C, 63.0), 62);
+        assert_eq!(Scale::Major.nearest(Key::D, 60.8), 61);
+        assert_eq!(Scale::Chromatic.nearest(Key::C, 61.2), 61);
+    }
+
+    #[test]
+    fn transpose_moves_by_scale_steps() {
+        assert_eq!(Scale::Major.transpose(Key::C, 60, 2), 64);
+        assert_eq!(Scale::Major.transpose(Key::C, 60, -1), 59);
+        assert_eq!(Scale::Major.transpose(Key::C, 60, 7), 72);
+        assert_eq!(Scale::MinorPentatonic.transpose(Key::A, 57, 1), 60);
+        assert_eq!(Scale::Major.transpose(Key::C, 64, 0), 64);
+    }
+}
@@ -0,0 +1,174 @@
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Streaming short-time Fourier transform with weighted overlap-add resynthesis.
+///
+/// Samples are pushed through a FIFO; every `hop` samples a Hann-windowed frame
+/// is transformed, handed to the caller for modification and added back into the
+/// output. The output lags the input by `size` samples: a sample is complete
+/// once the last of the frames overlapping it has been added back.
+pub struct Stft {
+    size: usize,
+    hop: usize,
+    window: Vec<f32>,
+    norm: f32,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    in_fifo: Vec<f32>,
+    out_fifo: Vec<f32>,
+    accumulator: Vec<f32>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    rover: usize,
+}
+
+impl Stft {
+    pub fn new(size: usize, overlap: usize) -> Self {
+        let hop = size / overlap;
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(size);
+        let inverse = planner.plan_fft_inverse(size);
+
+        // Periodic Hann window, applied on both analysis and synthesis
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / size as f32).cos())
+            .collect();
+        let window_energy: f32 = window.iter().map(|w| w * w).sum();
+        let norm = 1.0 / (size as f32 * window_energy / hop as f32);
+
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+        let spectrum = forward.make_output_vec();
+
+        Self {
+            size,
+            hop,
+            window,
+            norm,
+            forward,
+            inverse,
+            in_fifo: vec![0.0; size],
+            out_fifo: vec![0.0; size],
+            accumulator: vec![0.0; size],
+            frame: vec![0.0; size],
+            spectrum,
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            rover: size - hop,
+        }
+    }
+
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Number of spectrum bins handed to the frame callback (`size / 2 + 1`).
+    pub fn bins(&self) -> usize {
+        self.spectrum.len()
+    }
+
+    /// Delay between a sample entering and leaving `process`, in samples.
+    pub fn latency(&self) -> usize {
+        self.size
+    }
+
+    pub fn reset(&mut self) {
+        self.in_fifo.fill(0.0);
+        self.out_fifo.fill(0.0);
+        self.accumulator.fill(0.0);
+        self.rover = self.size - self.hop;
+    }
+
+    /// Run `block` through the analysis/resynthesis loop, calling `on_frame` with
+    /// the complex spectrum of every completed frame.
+    pub fn process<F>(&mut self, block: &mut [f32], mut on_frame: F)
+    where
+        F: FnMut(&mut [Complex<f32>]),
+    {
+        // The input FIFO holds the part of the next frame overlapping the last
+        let start = self.size - self.hop;
+
+        for sample in block.iter_mut() {
+            self.in_fifo[self.rover] = *sample;
+            *sample = self.out_fifo[self.rover - start];
+            self.rover += 1;
+
+            if self.rover >= self.size {
+                self.rover = start;
+                self.process_frame(&mut on_frame);
+            }
+        }
+    }
+
+    fn process_frame<F>(&mut self, on_frame: &mut F)
+    where
+        F: FnMut(&mut [Complex<f32>]),
+    {
+        for ((out, &input), &w) in self.frame.iter_mut().zip(&self.in_fifo).zip(&self.window) {
+            *out = input * w;
+        }
+
+        if self
+            .forward
+            .process_with_scratch(&mut self.frame, &mut self.spectrum, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        on_frame(&mut self.spectrum);
+
+        // The inverse transform requires purely real DC and Nyquist bins
+        let last = self.spectrum.len() - 1;
+        self.spectrum[0].im = 0.0;
+        self.spectrum[last].im = 0.0;
+
+        if self
+            .inverse
+            .process_with_scratch(&mut self.spectrum, &mut self.frame, &mut self.scratch)
+            .is_err()
+        {
+            return;
+        }
+
+        for ((acc, &sample), &w) in self.accumulator.iter_mut().zip(&self.frame).zip(&self.window) {
+            *acc += sample * w * self.norm;
+        }
+
+        self.out_fifo[..self.hop].copy_from_slice(&self.accumulator[..self.hop]);
+        self.accumulator.copy_within(self.hop.., 0);
+        let len = self.accumulator.len();
+        self.accumulator[len - self.hop..].fill(0.0);
+        self.in_fifo.copy_within(self.hop.., 0);
+    }
+}
+
+/// Wrap a phase value into the range [-PI, PI].
+pub fn wrap_phase(phase: f32) -> f32 {
+    (phase + PI).rem_euclid(2.0 * PI) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::rng::Rng;
+
+    #[test]
+    fn unmodified_frames_reconstruct_the_input() {
+        let mut rng = Rng::new(7);
+        let input: Vec<f32> = (0..8192).map(|_| rng.next_f32() * 2.0 - 1.0).collect();
+        let mut stft = Stft::new(1024, 4);
+        let mut output = Vec::new();
+        // Uneven blocks, as the audio callback delivers them
+        for chunk in input.chunks(333) {
+            let mut block = chunk.to_vec();
+            stft.process(&mut block, |_| {});
+            output.extend(block);
+        }
+        let latency = stft.latency();
+        for (i, (&x, &y)) in input.iter().zip(&output[latency..]).enumerate() {
+            assert!((x - y).abs() < 1e-4, "sample {}: {} became {}", i, x, y);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use super::gate::NoiseFloor;
+use super::Stage;
+use realfft::{RealFftPlanner, RealToComplex};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Analysis window and hop: about 12 ms windows every 10 ms at 44.1 kHz.
+const FRAME_SIZE: usize = 512;
+const HOP_MS: f32 = 10.0;
+
+/// Band holding most of the energy of speech; its share of the total and its
+/// spectral flatness separate voices from fans and hiss.
+const SPEECH_LOW: f32 = 300.0;
+const SPEECH_HIGH: f32 = 3400.0;
+const TOTAL_LOW: f32 = 60.0;
+const TOTAL_HIGH: f32 = 8000.0;
+const MIN_SPEECH_RATIO: f32 = 0.6;
+const MAX_FLATNESS: f32 = 0.4;
+
+/// Level above the threshold at which a frame counts as speech whatever its
+/// spectrum, so loud fricatives are not missed.
+const LOUD_DB: f32 = 15.0;
+
+/// Consecutive speech frames needed to start, rejecting clicks.
+const ONSET_FRAMES: usize = 2;
+
+/// Percentile of the level distribution taken as the noise floor.
+const FLOOR_PERCENTILE: f32 = 0.1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct VadParams {
+    pub enabled: bool,
+    /// Level above the tracked noise floor a frame needs to count as speech, in dB
+    pub threshold_db: f32,
+    /// Time speech is still reported after the last speech frame, bridging
+    /// the gaps between words, in ms
+    pub hangover_ms: f32,
+}
+
+impl Default for VadParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: 9.0,
+            hangover_ms: 250.0,
+        }
+    }
+}
+
+/// A change of the detected voice activity.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct VadEvent {
+    pub speaking: bool,
+    /// Stream time of the change, in seconds since processing started
+    pub time_s: f32,
+}
+
+/// Voice activity detector combining energy and spectral shape.
+///
+/// Every 10 ms the level is compared with a tracked noise floor; frames well
+/// above it count as speech when their energy is concentrated in the speech
+/// band or their spectrum is far from flat (harmonic). The detector reports
+/// speech after a short run of such frames and holds it for the hangover
+/// time. The audio passes through unchanged.
+pub struct VoiceActivityDetector {
+    sample_rate: f32,
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    history: Vec<f32>,
+    write_pos: usize,
+    hop: usize,
+    since_analysis: usize,
+    hop_energy: f32,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    floor: NoiseFloor,
+    run: usize,
+    hangover_remaining: usize,
+    speaking: bool,
+    transitions: Vec<(usize, bool)>,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(FRAME_SIZE);
+        let window = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+
+        Self {
+            sample_rate,
+            window,
+            history: vec![0.0; FRAME_SIZE],
+            write_pos: 0,
+            hop: (sample_rate * HOP_MS / 1000.0).round().max(1.0) as usize,
+            since_analysis: 0,
+            hop_energy: 0.0,
+            frame: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            floor: NoiseFloor::new(sample_rate),
+            run: 0,
+            hangover_remaining: 0,
+            speaking: false,
+            transitions: Vec::new(),
+            fft,
+        }
+    }
+
+    /// Changes of the speaking state during the last block, as sample offsets
+    /// into the block and the new state.
+    pub fn transitions(&self) -> &[(usize, bool)] {
+        &self.transitions
+    }
+
+    fn is_speech_frame(&mut self, params: &VadParams) -> bool {
+        let level_db = 10.0 * (self.hop_energy / self.hop as f32 + 1e-12).log10();
+        let above_db = match self.floor.floor_db() {
+            Some(floor_db) => level_db - floor_db - params.threshold_db,
+            // Nothing is speech until the room has been measured
+            None => return false,
+        };
+        if above_db < 0.0 {
+            return false;
+        }
+        if above_db >= LOUD_DB {
+            return true;
+        }
+
+        for (i, (sample, w)) in self.frame.iter_mut().zip(&self.window).enumerate() {
+            *sample = self.history[(self.write_pos + i) % FRAME_SIZE] * w;
+        }
+        if self
+            .fft
+            .process_with_scratch(&mut self.frame, &mut self.spectrum, &mut self.scratch)
+            .is_err()
+        {
+            return false;
+        }
+
+        let bin = |frequency: f32| {
+            ((frequency * FRAME_SIZE as f32 / self.sample_rate) as usize).min(self.spectrum.len() - 1)
+        };
+        let power = |range: std::ops::Range<usize>| -> f32 {
+            self.spectrum[range].iter().map(|c| c.norm_sqr()).sum()
+        };
+        let speech_band = bin(SPEECH_LOW)..bin(SPEECH_HIGH);
+        let speech_ratio = power(speech_band.clone()) / (power(bin(TOTAL_LOW)..bin(TOTAL_HIGH)) + 1e-12);
+
+        // Spectral flatness: geometric over arithmetic mean of the bin powers
+        let count = speech_band.len().max(1) as f32;
+        let (log_sum, sum) = self.spectrum[speech_band]
+            .iter()
+            .map(|c| c.norm_sqr() + 1e-12)
+            .fold((0.0, 0.0), |(log_sum, sum), p| (log_sum + p.ln(), sum + p));
+        let flatness = (log_sum / count).exp() / (sum / count);
+
+        speech_ratio > MIN_SPEECH_RATIO || flatness < MAX_FLATNESS
+    }
+}
+
+impl Stage for VoiceActivityDetector {
+    type Params = VadParams;
+
+    fn process(&mut self, block: &mut [f32], params: &VadParams) {
+        self.transitions.clear();
+        let hangover = (params.hangover_ms.max(0.0) / HOP_MS).round() as usize;
+
+        for (offset, &sample) in block.iter().enumerate() {
+            self.floor.push(sample, FLOOR_PERCENTILE);
+            self.history[self.write_pos] = sample;
+            self.write_pos = (self.write_pos + 1) % FRAME_SIZE;
+            self.hop_energy += sample * sample;
+            self.since_analysis += 1;
+            if self.since_analysis < self.hop {
+                continue;
+            }
+
+            let speech = self.is_speech_frame(params);
+            self.since_analysis = 0;
+            self.hop_energy = 0.0;
+
+            self.run = if speech { self.run + 1 } else { 0 };
+            let was_speaking = self.speaking;
+            if self.run >= ONSET_FRAMES || (self.speaking && speech) {
+                self.speaking = true;
+                self.hangover_remaining = hangover;
+            } else if self.hangover_remaining > 0 {
+                self.hangover_remaining -= 1;
+            } else {
+                self.speaking = false;
+            }
+            if self.speaking != was_speaking {
+                self.transitions.push((offset, self.speaking));
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write_pos = 0;
+        self.since_analysis = 0;
+        self.hop_energy = 0.0;
+        self.floor.reset();
+        self.run = 0;
+        self.hangover_remaining = 0;
+        self.speaking = false;
+        self.transitions.clear();
+    }
+}
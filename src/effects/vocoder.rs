@@ -0,0 +1,217 @@
+use super::biquad::Biquad;
+use super::rng::Rng;
+use super::scale::frequency_to_midi_note;
+use super::Stage;
+use serde::{Deserialize, Serialize};
+
+/// Level correction for the summed bands, so a full-band carrier roughly
+/// matches the input level.
+const OUTPUT_GAIN: f32 = 10.0;
+
+pub const MAX_BANDS: usize = 32;
+
+/// Highest carrier frequency as a share of the sample rate, below Nyquist so
+/// the band-limited waveforms stay valid.
+const MAX_CARRIER_RATIO: f32 = 0.45;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CarrierWaveform {
+    #[default]
+    Saw,
+    Pulse,
+    Noise,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct VocoderParams {
+    pub enabled: bool,
+    /// Number of analysis/synthesis bands (4 - MAX_BANDS)
+    pub bands: usize,
+    /// Lowest and highest band centre frequencies in Hz
+    pub low_freq: f32,
+    pub high_freq: f32,
+    pub carrier: CarrierWaveform,
+    /// Carrier pitch as a MIDI note number (60 = middle C); follows MIDI input
+    /// when a MIDI port is connected
+    pub carrier_note: f32,
+    /// Duty cycle of the pulse carrier (0.05 - 0.95)
+    pub pulse_width: f32,
+    /// Envelope follower attack and release in milliseconds
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Amount of the input above `high_freq` passed straight through, which
+    /// keeps sibilants intelligible
+    pub sibilance: f32,
+    /// Blend between the dry (0.0) and vocoded (1.0) signal
+    pub mix: f32,
+}
+
+impl Default for VocoderParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: 16,
+            low_freq: 120.0,
+            high_freq: 7000.0,
+            carrier: CarrierWaveform::Saw,
+            carrier_note: 48.0,
+            pulse_width: 0.5,
+            attack_ms: 5.0,
+            release_ms: 40.0,
+            sibilance: 0.3,
+            mix: 1.0,
+        }
+    }
+}
+
+pub fn midi_note_to_frequency(note: f32) -> f32 {
+    440.0 * 2.0f32.powf((note - 69.0) / 12.0)
+}
+
+#[derive(Default)]
+struct Band {
+    analysis: [Biquad; 2],
+    synthesis: [Biquad; 2],
+    envelope: f32,
+}
+
+/// Classic channel vocoder.
+///
+/// The voice is split into log-spaced band-pass channels whose envelopes are
+/// followed and imposed on the matching channels of an internal saw, pulse or
+/// noise carrier.
+pub struct ChannelVocoder {
+    sample_rate: f32,
+    tuned: Option<(usize, f32, f32)>,
+    /// All bands, of which the first `active` run
+    bands: Vec<Band>,
+    active: usize,
+    sibilance_filter: Biquad,
+    phase: f32,
+    rng: Rng,
+}
+
+impl ChannelVocoder {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            tuned: None,
+            bands: (0..MAX_BANDS).map(|_| Band::default()).collect(),
+            active: 0,
+            sibilance_filter: Biquad::default(),
+            phase: 0.0,
+            rng: Rng::new(0x0c0d_e500),
+        }
+    }
+
+    fn tune(&mut self, count: usize, low: f32, high: f32) {
+        if self.tuned == Some((count, low, high)) {
+            return;
+        }
+
+        let low = low.max(20.0);
+        let high = high.max(low * 2.0);
+        let spacing = (high / low).powf(1.0 / (count - 1) as f32);
+        // Bandwidth of one band spacing, so neighbouring bands cross near -3 dB
+        let q = spacing.sqrt() / (spacing - 1.0);
+
+        // Retune in place so the filters and envelopes carry on without a
+        // click; bands switched on start from silence
+        for (i, band) in self.bands.iter_mut().enumerate().take(count) {
+            let filter = Biquad::bandpass(self.sample_rate, low * spacing.powi(i as i32), q);
+            for stage in band.analysis.iter_mut().chain(band.synthesis.iter_mut()) {
+                stage.retune(&filter);
+                if i >= self.active {
+                    stage.reset();
+                }
+            }
+            if i >= self.active {
+                band.envelope = 0.0;
+            }
+        }
+        self.active = count;
+        let sibilance_filter = Biquad::highpass(self.sample_rate, high, std::f32::consts::FRAC_1_SQRT_2);
+        self.sibilance_filter.retune(&sibilance_filter);
+        self.tuned = Some((count, low, high));
+    }
+
+    /// Next carrier sample, band-limited with PolyBLEP for the saw and pulse.
+    fn carrier(&mut self, params: &VocoderParams, increment: f32) -> f32 {
+        self.phase = (self.phase + increment).fract();
+        let t = self.phase;
+
+        match params.carrier {
+            CarrierWaveform::Saw => 2.0 * t - 1.0 - poly_blep(t, increment),
+            CarrierWaveform::Pulse => {
+                let width = params.pulse_width.clamp(0.05, 0.95);
+                let naive = if t < width { 1.0 } else { -1.0 };
+                let falling = (t - width).rem_euclid(1.0);
+                naive + poly_blep(t, increment) - poly_blep(falling, increment)
+            }
+            // White noise has far less energy per band than a harmonic carrier
+            CarrierWaveform::Noise => 3.0 * (2.0 * self.rng.next_f32() - 1.0),
+        }
+    }
+}
+
+/// Polynomial band-limited step correction around a discontinuity at phase 0.
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt;
+        x + x - x * x - 1.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        x * x + x + x + 1.0
+    } else {
+        0.0
+    }
+}
+
+impl Stage for ChannelVocoder {
+    type Params = VocoderParams;
+
+    fn process(&mut self, block: &mut [f32], params: &VocoderParams) {
+        self.tune(params.bands.clamp(4, MAX_BANDS), params.low_freq, params.high_freq);
+
+        let highest_note = frequency_to_midi_note(MAX_CARRIER_RATIO * self.sample_rate);
+        let increment = midi_note_to_frequency(params.carrier_note.min(highest_note)) / self.sample_rate;
+        let attack = (-1.0 / (self.sample_rate * params.attack_ms.max(0.1) / 1000.0)).exp();
+        let release = (-1.0 / (self.sample_rate * params.release_ms.max(0.1) / 1000.0)).exp();
+        let sibilance = params.sibilance.clamp(0.0, 1.0);
+        let mix = params.mix.clamp(0.0, 1.0);
+
+        for sample in block.iter_mut() {
+            let input = *sample;
+            let carrier = self.carrier(params, increment);
+
+            let mut output = 0.0;
+            for band in self.bands[..self.active].iter_mut() {
+                let mut analysed = input;
+                for filter in band.analysis.iter_mut() {
+                    analysed = filter.process(analysed);
+                }
+                let level = analysed.abs();
+                let coefficient = if level > band.envelope { attack } else { release };
+                band.envelope = band.envelope * coefficient + level * (1.0 - coefficient);
+
+                let mut excitation = carrier;
+                for filter in band.synthesis.iter_mut() {
+                    excitation = filter.process(excitation);
+                }
+                output += excitation * band.envelope;
+            }
+
+            let vocoded = output * OUTPUT_GAIN + self.sibilance_filter.process(input) * sibilance;
+            *sample = input * (1.0 - mix) + vocoded * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.tuned = None;
+        self.active = 0;
+        self.sibilance_filter.reset();
+        self.phase = 0.0;
+    }
+}
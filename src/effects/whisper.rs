@@ -0,0 +1,111 @@
+use super::formant::SpectralEnvelope;
+use super::rng::Rng;
+use super::stft::Stft;
+use super::Stage;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+const FRAME_SIZE: usize = 1024;
+const OVERLAP: usize = 4;
+const LIFTER: usize = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct WhisperParams {
+    pub enabled: bool,
+    /// Blend between the voiced input (0.0) and pure whisper (1.0)
+    pub amount: f32,
+}
+
+impl Default for WhisperParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            amount: 1.0,
+        }
+    }
+}
+
+/// Replaces the voice's harmonic excitation with noise.
+///
+/// Each frame the spectral envelope is estimated by cepstral smoothing and used
+/// as the magnitude of a random-phase spectrum, so the formants (and therefore
+/// the words) survive while the pitch disappears.
+pub struct Whisper {
+    stft: Stft,
+    state: WhisperState,
+}
+
+struct WhisperState {
+    envelope: SpectralEnvelope,
+    magnitude: Vec<f32>,
+    log_envelope: Vec<f32>,
+    rng: Rng,
+}
+
+impl Whisper {
+    pub fn new() -> Self {
+        let stft = Stft::new(FRAME_SIZE, OVERLAP);
+        let bins = stft.bins();
+
+        Self {
+            stft,
+            state: WhisperState {
+                envelope: SpectralEnvelope::new(FRAME_SIZE, LIFTER),
+                magnitude: vec![0.0; bins],
+                log_envelope: vec![0.0; bins],
+                rng: Rng::new(0x5eed_1234),
+            },
+        }
+    }
+}
+
+impl Default for Whisper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stage for Whisper {
+    type Params = WhisperParams;
+
+    fn process(&mut self, block: &mut [f32], params: &WhisperParams) {
+        let amount = params.amount.clamp(0.0, 1.0);
+        let state = &mut self.state;
+        self.stft.process(block, |spectrum| state.excite(spectrum, amount));
+    }
+
+    fn latency(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+    }
+}
+
+impl WhisperState {
+    fn excite(&mut self, spectrum: &mut [Complex<f32>], amount: f32) {
+        for (m, bin) in self.magnitude.iter_mut().zip(spectrum.iter()) {
+            *m = bin.norm();
+        }
+        self.envelope.estimate(&self.magnitude, &mut self.log_envelope);
+
+        // The smoothed log envelope sits below the harmonic peaks, so match the
+        // noise energy to the frame energy
+        let frame_energy: f32 = self.magnitude.iter().map(|m| m * m).sum();
+        let envelope_energy: f32 = self.log_envelope.iter().map(|e| (2.0 * e).exp()).sum();
+        let gain = if envelope_energy > 0.0 {
+            (frame_energy / envelope_energy).sqrt()
+        } else {
+            0.0
+        };
+
+        for (bin, &log_magnitude) in spectrum.iter_mut().zip(&self.log_envelope) {
+            let phase = 2.0 * PI * self.rng.next_f32();
+            let noise = Complex::from_polar(log_magnitude.exp() * gain, phase);
+            *bin = *bin * (1.0 - amount) + noise * amount;
+        }
+    }
+}
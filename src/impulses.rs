@@ -0,0 +1,109 @@
+//! Impulse responses for the convolution stage, read from WAV files.
+//!
+//! The file is named in the chain settings (`convolution.impulse`), so it can
+//! come from a preset, the web interface or the command line. A background
+//! thread notices a new name, reads and prepares the response away from the
+//! audio callback and hands the finished convolver to the chain.
+
+use crate::effects::convolution::Convolver;
+use crate::effects::Chain;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Longest response used; anything beyond is cut off with a short fade.
+const MAX_SECONDS: f32 = 10.0;
+const FADE_SECONDS: f32 = 0.05;
+
+/// How often the loader looks for a changed file name.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Read an impulse response as mono at `sample_rate`, normalised to unit
+/// energy so switching responses keeps the level roughly the same.
+pub fn load(path: &Path, sample_rate: f32) -> Result<Vec<f32>> {
+    let mut impulse = read_mono(path, sample_rate)
+        .map_err(|e| anyhow::anyhow!("Could not read impulse response {}: {}", path.display(), e))?;
+
+    let max_len = (MAX_SECONDS * sample_rate) as usize;
+    if impulse.len() > max_len {
+        impulse.truncate(max_len);
+        let fade = (FADE_SECONDS * sample_rate) as usize;
+        for (i, sample) in impulse[max_len - fade..].iter_mut().enumerate() {
+            *sample *= 1.0 - i as f32 / fade as f32;
+        }
+    }
+
+    let energy: f32 = impulse.iter().map(|h| h * h).sum();
+    if energy <= f32::EPSILON {
+        return Err(anyhow::anyhow!("Impulse response {} is silent", path.display()));
+    }
+    let norm = 1.0 / energy.sqrt();
+    for h in impulse.iter_mut() {
+        *h *= norm;
+    }
+    Ok(impulse)
+}
+
+/// Read a WAV file of any sample format as mono at `sample_rate`, averaging
+/// the channels.
+pub fn read_mono(path: &Path, sample_rate: f32) -> Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok(resample(&mono, spec.sample_rate as f32, sample_rate))
+}
+
+/// Linear-interpolation sample rate conversion.
+fn resample(samples: &[f32], from: f32, to: f32) -> Vec<f32> {
+    if from == to || samples.len() < 2 {
+        return samples.to_vec();
+    }
+    let step = from / to;
+    let len = ((samples.len() - 1) as f32 / step) as usize + 1;
+    (0..len)
+        .map(|i| {
+            let position = i as f32 * step;
+            let index = position as usize;
+            let frac = position - index as f32;
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            samples[index] + (next - samples[index]) * frac
+        })
+        .collect()
+}
+
+/// Keep the chain's convolver in step with `convolution.impulse`.
+pub fn spawn_loader(chain: Arc<Mutex<Chain>>, sample_rate: f32) {
+    std::thread::spawn(move || loop {
+        let pending = chain.lock().unwrap().pending_impulse();
+        if let Some(file) = pending {
+            let convolver = match load(Path::new(&file), sample_rate) {
+                Ok(impulse) => {
+                    println!("Impulse response loaded: {} ({:.0} ms)", file, impulse.len() as f32 * 1000.0 / sample_rate);
+                    Convolver::new(&impulse)
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    Convolver::new(&[])
+                }
+            };
+            chain.lock().unwrap().install_impulse(file, convolver);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    });
+}
@@ -5,11 +5,22 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::f32::consts::PI;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use rustfft::{FftPlanner, num_complex::Complex};
 
+mod effects;
+mod impulses;
+mod midi;
+mod presets;
+mod targets;
+mod voices;
 mod web_server;
-use web_server::{start_web_server, broadcast_fft_data};
+use effects::Chain;
+use effects::eq::EqBand;
+use effects::lfo::Lfo;
+use voices::{Voice, VoiceMacros, VoiceSelection};
+use web_server::{start_web_server, broadcast_fft_data, broadcast_status, broadcast_vad_events};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -25,6 +36,50 @@ struct Args {
     /// Start web interface (default: disabled)
     #[arg(long)]
     web: bool,
+
+    /// MIDI input port ID; notes played set the vocoder carrier pitch and the
+    /// modulation wheel moves the preset morph
+    #[arg(long)]
+    midi: Option<usize>,
+
+    /// Start with a built-in voice character
+    #[arg(long, value_enum)]
+    voice: Option<Voice>,
+
+    /// Voice character intensity (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.5)]
+    voice_intensity: f32,
+
+    /// Voice character tone, darker to brighter (-1.0 - 1.0)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    voice_tone: f32,
+
+    /// Load the processing chain settings from a preset file
+    #[arg(long)]
+    preset: Option<PathBuf>,
+
+    /// Load two presets to morph between, starting at the first
+    #[arg(long, num_args = 2, value_names = ["A", "B"])]
+    morph: Vec<PathBuf>,
+
+    /// Equalizer band as type:frequency[:gain_db[:q]], e.g. peaking:2500:3:1.4
+    /// (repeat for more bands; types: low_shelf, high_shelf, peaking, low_pass, high_pass, notch)
+    #[arg(long = "eq", value_name = "BAND")]
+    eq_bands: Vec<EqBand>,
+
+    /// Impulse response WAV to convolve the voice with, e.g. a telephone,
+    /// megaphone or room recording
+    #[arg(long, value_name = "WAV")]
+    impulse: Option<PathBuf>,
+
+    /// Recording of a target voice to convert the voice towards
+    #[arg(long, value_name = "WAV")]
+    target: Option<PathBuf>,
+
+    /// Disguise the voice for privacy, from the given seed or a new one
+    /// (printed, so the same disguise can be used again)
+    #[arg(long, value_name = "SEED", num_args = 0..=1)]
+    anonymize: Option<Option<u32>>,
 }
 
 // Cubic interpolation function for smoother audio resampling
@@ -108,8 +163,8 @@ fn display_frequency_spectrum_animated(
         let threshold = (height - row) as f32 / height as f32;
         print!("|");
         
-        for bin in 0..width.min(spectrum.len()) {
-            let normalized_magnitude = spectrum[bin] / max_magnitude;
+        for (bin, &magnitude) in spectrum.iter().enumerate().take(width) {
+            let normalized_magnitude = magnitude / max_magnitude;
             let freq = bin as f32 * freq_resolution;
             
             if normalized_magnitude >= threshold {
@@ -169,6 +224,10 @@ async fn main() -> Result<()> {
         for (idx, device) in host.input_devices()?.enumerate() {
             println!("{}: {}", idx, device.name()?);
         }
+        println!("\nMIDI input ports:");
+        for (idx, name) in midi::list_ports()?.iter().enumerate() {
+            println!("{}: {}", idx, name);
+        }
         return Ok(());
     }
 
@@ -207,6 +266,8 @@ async fn main() -> Result<()> {
 
     // Create a buffer to store audio samples
     let audio_buffer = Arc::new(Mutex::new(Vec::<f32>::new()));
+    // Samples handed to the speakers, the far-end reference for echo cancellation
+    let echo_reference = Arc::new(Mutex::new(Vec::<f32>::new()));
     
     // FFT visualization buffers
     let fft_size = 1024usize;
@@ -225,12 +286,57 @@ async fn main() -> Result<()> {
     let smoothing = Arc::new(Mutex::new(0.7f32));     // Smoothing factor (0-1)
     let freq_shift = Arc::new(Mutex::new(5.0f32));
     let buffer_size_limit = Arc::new(Mutex::new(2400usize)); // Smaller buffer for less latency
-    let phase_accumulator = Arc::new(Mutex::new(0.0f32));
+    let wobble = Arc::new(Mutex::new(Lfo::new(sample_rate)));
     
     // Noise gate state
     let envelope = Arc::new(Mutex::new(0.0f32));
-    let adaptive_threshold = Arc::new(Mutex::new(0.01f32));
     
+    // Processing chain applied after the noise gate (pitch shifting, ...)
+    let chain = Arc::new(Mutex::new(Chain::new(sample_rate)));
+    if let Some(voice) = args.voice {
+        let macros = VoiceMacros { intensity: args.voice_intensity, tone: args.voice_tone };
+        chain.lock().unwrap().apply_voice(Some(VoiceSelection { voice, macros }));
+        println!("Voice character: {} ({})", voice.name(), voice.description());
+    }
+    if let Some(path) = &args.preset {
+        chain.lock().unwrap().apply_preset(presets::load(path)?);
+        println!("Preset loaded: {}", path.display());
+    }
+    if let [a, b] = args.morph.as_slice() {
+        presets::start_morph(&chain, a, b)?;
+        println!("Morphing between {} and {}", a.display(), b.display());
+    }
+    if !args.eq_bands.is_empty() {
+        let mut chain = chain.lock().unwrap();
+        chain.settings.eq.bands = args.eq_bands.clone();
+        chain.settings.eq.enabled = true;
+    }
+    if let Some(path) = &args.impulse {
+        let mut chain = chain.lock().unwrap();
+        chain.settings.convolution.impulse = Some(path.display().to_string());
+        chain.settings.convolution.enabled = true;
+    }
+    if let Some(path) = &args.target {
+        let mut chain = chain.lock().unwrap();
+        chain.settings.conversion.target = Some(path.display().to_string());
+        chain.settings.conversion.enabled = true;
+    }
+    if let Some(seed) = args.anonymize {
+        let seed = seed.unwrap_or_else(effects::anonymizer::fresh_seed);
+        let mut chain = chain.lock().unwrap();
+        chain.settings.anonymizer.seed = Some(seed);
+        chain.settings.anonymizer.enabled = true;
+        println!("Anonymizer seed: {} (start with --anonymize {} to reproduce)", seed, seed);
+    }
+    impulses::spawn_loader(chain.clone(), sample_rate);
+    targets::spawn_loader(chain.clone(), sample_rate);
+
+    // Keep the MIDI connection open for the lifetime of the program
+    let _midi_connection = match args.midi {
+        Some(port) => Some(midi::connect(port, chain.clone())?),
+        None => None,
+    };
+
     // Simple resampling ratio
     let _resample_ratio = 48000.0 / 44100.0;
 
@@ -249,26 +355,30 @@ async fn main() -> Result<()> {
     
     println!("\nUsing input config: {:?}", input_stream_config);
     println!("Using output config: {:?}", output_stream_config);
+    let output_channels = output_stream_config.channels.max(1) as usize;
 
     // Build the input stream
     let input_data = audio_buffer.clone();
     let fft_input_clone = fft_input_buffer.clone();
-    let phase_acc = phase_accumulator.clone();
+    let wobble_clone = wobble.clone();
     let vol_clone = volume.clone();
     let noise_clone = noise_threshold.clone();
     let attack_clone = attack_time.clone();
     let release_clone = release_time.clone();
     let smoothing_clone = smoothing.clone();
     let envelope_clone = envelope.clone();
-    let adaptive_threshold_clone = adaptive_threshold.clone();
     let freq_clone = freq_shift.clone();
     let buffer_limit_clone = buffer_size_limit.clone();
+    let chain_clone = chain.clone();
+    let echo_reference_in = echo_reference.clone();
+    let mut block = Vec::<f32>::new();
+    let mut stereo_side = Vec::<f32>::new();
     
     let input_stream = input_device.build_input_stream(
         &input_stream_config,
         move |data: &[f32], _: &cpal::InputCallbackInfo| {
             let mut buffer = input_data.lock().unwrap();
-            let mut phase = phase_acc.lock().unwrap();
+            let mut wobble = wobble_clone.lock().unwrap();
             
             // Get current parameters
             let volume = *vol_clone.lock().unwrap();
@@ -277,21 +387,23 @@ async fn main() -> Result<()> {
             let release = *release_clone.lock().unwrap();
             let smoothing_factor = *smoothing_clone.lock().unwrap();
             let mut envelope = envelope_clone.lock().unwrap();
-            let mut adaptive_thresh = adaptive_threshold_clone.lock().unwrap();
             let freq_shift = *freq_clone.lock().unwrap();
             let buffer_size_limit = *buffer_limit_clone.lock().unwrap();
-            
-            // Update adaptive threshold based on recent signal levels
-            let signal_level = data.iter().map(|x| x.abs()).sum::<f32>() / data.len() as f32;
-            *adaptive_thresh = *adaptive_thresh * 0.95 + signal_level * 0.05;
+            let mut chain = chain_clone.lock().unwrap();
+            let gate_bypassed = chain.gate_bypassed();
+            {
+                let mut reference = echo_reference_in.lock().unwrap();
+                chain.push_echo_reference(&reference);
+                reference.clear();
+            }
             
             // Process each sample with smoother algorithms
+            block.clear();
+            let mut input_active = false;
             for sample in data {
                 // Improved frequency shifting with smoother modulation
-                *phase += 2.0 * PI * freq_shift / sample_rate;
-                if *phase >= 2.0 * PI {
-                    *phase -= 2.0 * PI;
-                }
+                wobble.advance(freq_shift);
+                let phase = wobble.phase();
                 // Use a blend of sine and cosine for smoother modulation
                 let mod_amount = 0.015; // Reduced modulation depth
                 let shifted_sample = *sample * (1.0 + mod_amount * (0.7 * phase.sin() + 0.3 * phase.cos()));
@@ -316,8 +428,10 @@ async fn main() -> Result<()> {
                 *envelope = *envelope * alpha + target_envelope * (1.0 - alpha);
                 *envelope = *envelope * smoothing_factor + target_envelope * (1.0 - smoothing_factor);
                 
+                input_active |= *envelope > noise_threshold;
+                
                 // Enhanced noise gate with smoother transition curve
-                let gate_multiplier = if *envelope > noise_threshold {
+                let gate_multiplier = if gate_bypassed || *envelope > noise_threshold {
                     1.0
                 } else {
                     let ratio = *envelope / noise_threshold;
@@ -325,9 +439,29 @@ async fn main() -> Result<()> {
                     curve * (0.15 + 0.85 * ratio) // Smoother transition near threshold
                 };
                 
-                let processed_sample = shifted_sample * volume * gate_multiplier;
-                
-                buffer.push(processed_sample);
+                block.push(shifted_sample * volume * gate_multiplier);
+            }
+            
+            // Run the gated block through the processing chain
+            chain.set_input_active(input_active);
+            chain.process(&mut block);
+            stereo_side.clear();
+            stereo_side.extend_from_slice(chain.stereo_side());
+            drop(chain);
+            
+            for (i, &processed_sample) in block.iter().enumerate() {
+                // The buffer holds whole output frames; on stereo outputs the
+                // delay's ping-pong echoes move between left and right
+                let side = stereo_side.get(i).copied().unwrap_or(0.0);
+                let frame_sample = |channel: usize| match (output_channels, channel) {
+                    (1, _) => processed_sample,
+                    (_, 0) => processed_sample + side,
+                    (_, 1) => processed_sample - side,
+                    _ => processed_sample,
+                };
+                for channel in 0..output_channels {
+                    buffer.push(frame_sample(channel));
+                }
                 
                 // Collect data for FFT visualization
                 if let Ok(mut fft_buffer) = fft_input_clone.try_lock() {
@@ -340,18 +474,23 @@ async fn main() -> Result<()> {
                 }
                 
                 // Enhanced sample interpolation
-                if buffer.len() % 441 == 0 && buffer.len() > 2 {
-                    // Cubic interpolation using 4 points
-                    let p0 = buffer[buffer.len() - 2];
-                    let p1 = buffer[buffer.len() - 1];
-                    let p2 = processed_sample;
-                    let p3 = processed_sample; // Future sample (approximated)
-                    let t = 0.5; // Interpolation point
-                    let interpolated = cubic_interpolate(p0, p1, p2, p3, t);
-                    buffer.push(interpolated);
+                let frames = buffer.len() / output_channels;
+                if frames.is_multiple_of(441) && frames > 2 {
+                    let end = buffer.len();
+                    for channel in 0..output_channels {
+                        // Cubic interpolation using 4 points
+                        let p0 = buffer[end - 2 * output_channels + channel];
+                        let p1 = buffer[end - output_channels + channel];
+                        let p2 = frame_sample(channel);
+                        let p3 = frame_sample(channel); // Future sample (approximated)
+                        let t = 0.5; // Interpolation point
+                        let interpolated = cubic_interpolate(p0, p1, p2, p3, t);
+                        buffer.push(interpolated);
+                    }
                 }
             }
             // Keep buffer size manageable
+            let buffer_size_limit = buffer_size_limit * output_channels;
             if buffer.len() > buffer_size_limit {
                 let excess = buffer.len() - buffer_size_limit;
                 buffer.drain(0..excess);
@@ -363,6 +502,7 @@ async fn main() -> Result<()> {
 
     // Build the output stream
     let output_data = audio_buffer.clone();
+    let echo_reference_out = echo_reference.clone();
     let output_stream = output_device.build_output_stream(
         &output_stream_config,
         move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
@@ -379,6 +519,20 @@ async fn main() -> Result<()> {
                     *output_sample = 0.0;
                 }
             }
+            drop(buffer);
+            
+            // Record what was played, mixed to mono, for the echo canceller;
+            // the input callback drains this, the cap only matters if it stops
+            let mut reference = echo_reference_out.lock().unwrap();
+            reference.extend(
+                data.chunks(output_channels)
+                    .map(|frame| frame.iter().sum::<f32>() / output_channels as f32),
+            );
+            let cap = sample_rate as usize;
+            if reference.len() > cap {
+                let excess = reference.len() - cap;
+                reference.drain(0..excess);
+            }
         },
         move |err| eprintln!("An error occurred on the output stream: {}", err),
         None,
@@ -393,14 +547,21 @@ async fn main() -> Result<()> {
         println!("\n🌐 Starting web interface on http://localhost:3030");
         println!("Open your browser and navigate to: http://localhost:3030");
         
-        // Create WebSocket sender
+        // Create WebSocket sender and run the server alongside the terminal menu
         let ws_sender = Arc::new(tokio::sync::Mutex::new(None));
-        start_web_server(ws_sender.clone()).await.map_err(|e| anyhow::anyhow!(e))?;
+        let server_sender = ws_sender.clone();
+        let server_chain = chain.clone();
+        tokio::spawn(async move {
+            if let Err(e) = start_web_server(server_sender, server_chain).await {
+                eprintln!("Web server error: {}", e);
+            }
+        });
         
         // Spawn FFT data broadcasting task
         let fft_input_web = fft_input_buffer.clone();
         let fft_output_web = fft_output_buffer.clone();
         let ws_sender_clone = ws_sender.clone();
+        let chain_web = chain.clone();
         
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_millis(33)); // ~30 FPS
@@ -428,6 +589,13 @@ async fn main() -> Result<()> {
                 };
                 
                 broadcast_fft_data(&ws_sender_clone, input_data, output_data, sample_rate, fft_size).await;
+                
+                let (status, vad_events) = {
+                    let mut chain = chain_web.lock().unwrap();
+                    (chain.status(), chain.take_vad_events())
+                };
+                broadcast_vad_events(&ws_sender_clone, vad_events).await;
+                broadcast_status(&ws_sender_clone, status).await;
             }
         });
     }
@@ -440,6 +608,7 @@ async fn main() -> Result<()> {
     let smooth = smoothing.clone();
     let freq = freq_shift.clone();
     let buffer_limit = buffer_size_limit.clone();
+    let chain_ui = chain.clone();
 
     println!("\nVoice transformer started! Audio is processing...");
     
@@ -467,15 +636,189 @@ async fn main() -> Result<()> {
         let smooth_val = *smooth.lock().unwrap();
         let freq_val = *freq.lock().unwrap();
         let buf_val = *buffer_limit.lock().unwrap();
+        let status = chain_ui.lock().unwrap().status();
+        let pitch = &status.settings.pitch;
+        
+        match status.voice {
+            Some(selection) => println!("Voice Character.........: {} (intensity {:.2}, tone {:+.2})", selection.voice.name(), selection.macros.intensity, selection.macros.tone),
+            None => println!("Voice Character.........: none"),
+        }
+        if let Some(amount) = status.morph_amount {
+            println!("Preset Morph............: A {:3.0}% | B {:3.0}% {}", (1.0 - amount) * 100.0, amount * 100.0, create_bar(amount, 0.0, 1.0, 20));
+        }
         
         println!("\n================== Current Settings ==================");
         println!("Volume (0.0 - 1.0)......: {:.2}..{}", vol_val, create_bar(vol_val, 0.0, 1.0, 20));
-        println!("Noise Gate (0.0 - 0.1)..: {:.3}.{}", noise_val, create_bar(noise_val, 0.0, 0.1, 20));
+        let auto_gate = &status.settings.auto_gate;
+        if auto_gate.enabled {
+            let floor = status.noise_floor_db.map_or("--".to_string(), |floor| format!("{:.0} dBFS", floor));
+            println!("Noise Gate..............: auto, +{:.0} dB over floor {} ({})", auto_gate.margin_db, floor, if status.gate_open { "open" } else { "closed" });
+        } else {
+            println!("Noise Gate (0.0 - 0.1)..: {:.3}.{}", noise_val, create_bar(noise_val, 0.0, 0.1, 20));
+        }
         println!("Attack Time (0.0 - 0.1).: {:.3}.{}", attack_val, create_bar(attack_val, 0.0, 0.1, 20));
         println!("Release Time (0.0 - 0.5): {:.3}.{}", release_val, create_bar(release_val, 0.0, 0.5, 20));
         println!("Smoothing (0.0 - 1.0)...: {:.2}..{}", smooth_val, create_bar(smooth_val, 0.0, 1.0, 20));
         println!("Freq Shift (0 - 20 Hz)..: {:.1}...{}", freq_val, create_bar(freq_val, 0.0, 20.0, 20));
         println!("Buffer (0 - 10000)......: {}..{}", buf_val, create_bar_usize(buf_val, 0, 10000, 20));
+        if pitch.enabled {
+            println!("Pitch (-12 - +12 st)....: {:+.1}..{} {:?}", pitch.semitones, create_bar(pitch.semitones, -12.0, 12.0, 20), pitch.algorithm);
+        } else {
+            println!("Pitch (-12 - +12 st)....: off");
+        }
+        let formant = &status.settings.formant;
+        if formant.enabled {
+            println!("Formant (-12 - +12 st)..: {:+.1}..{}{}", formant.semitones, create_bar(formant.semitones, -12.0, 12.0, 20), if formant.preserve { " preserve" } else { "" });
+        } else {
+            println!("Formant (-12 - +12 st)..: off");
+        }
+        let conversion = &status.settings.conversion;
+        if conversion.enabled {
+            let target = match (&conversion.target, status.target_pitch) {
+                (Some(file), Some(pitch)) => format!("{} ({:.0} Hz)", file, pitch),
+                (Some(file), None) => format!("{} (not loaded)", file),
+                (None, _) => "no target voice".to_string(),
+            };
+            let speaker = status.speaker_pitch.map_or("listening".to_string(), |pitch| format!("{:.0} Hz", pitch));
+            println!("Voice Conversion........: {}, speaker {}, envelope {:.0}%, pitch {:.0}%, range {:.0}%", target, speaker, conversion.envelope * 100.0, conversion.pitch * 100.0, conversion.range * 100.0);
+        }
+        let anonymizer = &status.settings.anonymizer;
+        if anonymizer.enabled {
+            let seed = anonymizer.seed.map_or("picking".to_string(), |seed| seed.to_string());
+            let variation = if anonymizer.variation > 0.0 {
+                format!(", varying {:.0}% every {:.0} s", anonymizer.variation * 100.0, anonymizer.variation_s)
+            } else {
+                String::new()
+            };
+            println!("Anonymizer..............: seed {}, strength {:.0}%{}", seed, anonymizer.strength * 100.0, variation);
+            if let Some(disguise) = status.disguise {
+                println!("  pitch {:+.1} st, formants {:+.1} st, warp {:+.3}", disguise.pitch_semitones, disguise.formant_semitones, disguise.warp);
+            }
+        }
+        let echo = &status.settings.echo;
+        if echo.enabled {
+            let delay = status.echo_delay_ms.map_or("measuring".to_string(), |ms| format!("{:.0} ms", ms));
+            let erle = status.echo_erle_db.map_or("adapting".to_string(), |db| format!("{:.0} dB reduction", db));
+            println!("Echo Cancellation.......: delay {}, {}{}", delay, erle, if status.double_talk { ", double-talk" } else { "" });
+        }
+        if let Some(speaking) = status.voice_active {
+            println!("Voice Activity..........: {}", if speaking { "speaking" } else { "silent" });
+        }
+        let denoise = &status.settings.denoise;
+        if denoise.enabled {
+            println!("Noise Suppression.......: {:.0} dB{}{}", denoise.reduction_db, if denoise.bypass_gate { " (replacing gate)" } else { "" }, if status.noise_profile { ", learned profile" } else { "" });
+        }
+        let distortion = &status.settings.distortion;
+        if distortion.enabled {
+            println!("Distortion..............: {:?}, drive {:.0} dB, output {:+.0} dB, mix {:.0}%, oversampling {:?}", distortion.shape, distortion.drive_db, distortion.output_db, distortion.mix * 100.0, distortion.oversampling);
+        }
+        let bitcrusher = &status.settings.bitcrusher;
+        if bitcrusher.enabled {
+            println!("Bitcrusher..............: {:.1} bits, {:.0} Hz, mix {:.0}%", bitcrusher.bits, bitcrusher.sample_rate, bitcrusher.mix * 100.0);
+        }
+        let eq = &status.settings.eq;
+        if eq.enabled {
+            println!("Equalizer...............: {} bands", eq.bands.len());
+            for (i, band) in eq.bands.iter().enumerate() {
+                println!("  {}: {:<10} {:7.0} Hz {:+5.1} dB  Q {:.2}", i + 1, band.band_type.name(), band.frequency, band.gain_db, band.q);
+            }
+        }
+        let agc = &status.settings.agc;
+        if agc.enabled {
+            let loudness = status.loudness_lufs.map_or("--".to_string(), |lufs| format!("{:.1} LUFS", lufs));
+            println!("AGC.....................: {:.0} LUFS target ({:?}), input {}, gain {:+.1} dB", agc.target_lufs, agc.mode, loudness, status.agc_gain_db.unwrap_or(0.0));
+        }
+        if status.settings.feedback.enabled {
+            let notches: Vec<String> = status
+                .feedback_notches
+                .iter()
+                .map(|notch| format!("{:.0} Hz -{:.0} dB", notch.frequency, notch.depth_db))
+                .collect();
+            println!("Feedback Suppression....: {}", if notches.is_empty() { "no notches".to_string() } else { notches.join(", ") });
+        }
+        let plosive = &status.settings.plosive;
+        if plosive.enabled {
+            println!("Plosive Suppression.....: below {:.0} Hz, {:.0} dB, GR {:4.1} dB {}", plosive.cutoff, plosive.threshold_db, status.plosive_reduction_db, create_bar(status.plosive_reduction_db, 0.0, 20.0, 20));
+        }
+        let deesser = &status.settings.deesser;
+        if deesser.enabled {
+            println!("De-esser................: above {:.0} Hz, {:.0} dB, GR {:4.1} dB {}", deesser.frequency, deesser.threshold_db, status.deesser_reduction_db, create_bar(status.deesser_reduction_db, 0.0, 20.0, 20));
+        }
+        let compressor = &status.settings.compressor;
+        if compressor.enabled {
+            println!("Compressor..............: {:.0} dB, {:.1}:1, makeup {:+.0} dB, GR {:4.1} dB {}", compressor.threshold_db, compressor.ratio, compressor.makeup_db, status.compressor_reduction_db, create_bar(status.compressor_reduction_db, 0.0, 20.0, 20));
+        }
+        let lfo = |lfo: &effects::lfo::LfoParams| format!("{:?} LFO at {:.2} Hz, depth {:.0}%", lfo.waveform, lfo.rate_hz, lfo.depth * 100.0);
+        let vibrato = &status.settings.vibrato;
+        if vibrato.enabled {
+            println!("Vibrato.................: {}", lfo(&vibrato.lfo));
+        }
+        let chorus = &status.settings.chorus;
+        if chorus.enabled {
+            println!("Chorus..................: {} voices, {:.0} ms, {}, mix {:.0}%", chorus.voices, chorus.delay_ms, lfo(&chorus.lfo), chorus.mix * 100.0);
+        }
+        let flanger = &status.settings.flanger;
+        if flanger.enabled {
+            println!("Flanger.................: {:.1} ms, feedback {:+.0}%, {}, mix {:.0}%", flanger.delay_ms, flanger.feedback * 100.0, lfo(&flanger.lfo), flanger.mix * 100.0);
+        }
+        let tremolo = &status.settings.tremolo;
+        if tremolo.enabled {
+            println!("Tremolo.................: {}", lfo(&tremolo.lfo));
+        }
+        let convolution = &status.settings.convolution;
+        if convolution.enabled {
+            let impulse = match (&convolution.impulse, status.impulse_ms) {
+                (Some(file), Some(ms)) => format!("{} ({:.0} ms)", file, ms),
+                (Some(file), None) => format!("{} (not loaded)", file),
+                (None, _) => "no impulse response".to_string(),
+            };
+            println!("Convolution.............: {}, mix {:.0}%, gain {:+.1} dB", impulse, convolution.mix * 100.0, convolution.gain_db);
+        }
+        let harmonizer = &status.settings.harmonizer;
+        if harmonizer.enabled {
+            let mode = match harmonizer.mode {
+                effects::harmonizer::IntervalMode::Fixed => "semitones".to_string(),
+                effects::harmonizer::IntervalMode::Scale => format!("steps of {:?} {:?}", harmonizer.key, harmonizer.scale),
+            };
+            println!("Harmonizer..............: intervals in {}, dry {:.0}%", mode, harmonizer.dry_level * 100.0);
+            for (i, voice) in harmonizer.voices.iter().enumerate().filter(|(_, voice)| voice.enabled) {
+                println!("  {}: {:+3}, level {:3.0}%, pan {:+.2}", i + 1, voice.interval, voice.level * 100.0, voice.pan);
+            }
+        }
+        let reverb = &status.settings.reverb;
+        if reverb.enabled {
+            println!("Reverb..................: {:?}, size {:.2}, decay {:.1} s, damping {:.2}, pre-delay {:.0} ms, mix {:.0}%", reverb.style, reverb.size, reverb.decay_s, reverb.damping, reverb.pre_delay_ms, reverb.mix * 100.0);
+        }
+        let delay = &status.settings.delay;
+        if delay.enabled {
+            let time = if delay.sync {
+                format!("{:?} at {:.0} BPM ({:.0} ms)", delay.note, delay.bpm, delay.delay_ms())
+            } else {
+                format!("{:.0} ms", delay.delay_ms())
+            };
+            println!("Delay...................: {}, feedback {:.0}%, high-cut {:.0} Hz, mix {:.0}%{}", time, delay.feedback * 100.0, delay.high_cut, delay.mix * 100.0, if delay.ping_pong { ", ping-pong" } else { "" });
+        }
+        let limiter = &status.settings.limiter;
+        if limiter.enabled {
+            println!("Limiter.................: {:.1} dBTP ceiling, GR {:4.1} dB {}", limiter.ceiling_db, status.limiter_reduction_db, create_bar(status.limiter_reduction_db, 0.0, 20.0, 20));
+        }
+        let vocoder = &status.settings.vocoder;
+        if vocoder.enabled {
+            println!("Vocoder.................: {} bands, {:?} carrier at note {:.0} ({:.1} Hz)", vocoder.bands, vocoder.carrier, vocoder.carrier_note, effects::vocoder::midi_note_to_frequency(vocoder.carrier_note));
+        }
+        let autotune = &status.settings.autotune;
+        if autotune.enabled {
+            let speed = if autotune.robotic { "robotic".to_string() } else { format!("retune {:.0} ms, humanize {:.0}%", autotune.retune_ms, autotune.humanize * 100.0) };
+            println!("Pitch Correction........: {:?} {:?}, {}", autotune.key, autotune.scale, speed);
+        }
+        if let Some(detected) = status.detected_pitch {
+            let note = |frequency: f32| effects::scale::note_name(effects::scale::frequency_to_midi_note(frequency));
+            match status.corrected_pitch {
+                Some(corrected) => println!("Detected Pitch..........: {:.1} Hz ({}) -> {:.1} Hz ({})", detected, note(detected), corrected, note(corrected)),
+                None => println!("Detected Pitch..........: {:.1} Hz ({})", detected, note(detected)),
+            }
+        }
+        println!("Chain Latency...........: {:.1} ms", status.latency_ms);
         println!("======================================================");
     };
     
@@ -488,7 +831,7 @@ async fn main() -> Result<()> {
     let _last_vis_time = last_visualization_time.clone();
     
     loop {
-        print!("\nCommands: (v)olume, (n)oise, (a)ttack, (r)elease, (s)moothing, (f)req shift, (b)uffer, (w)aveform viz, (d)efault, (i)nfo, (q)uit: ");
+        print!("\nCommands: (v)olume, (n)oise, (a)ttack, (r)elease, (s)moothing, (f)req shift, (b)uffer, (p)itch, f(o)rmant, voic(e), c(h)annel vocoder, noise s(u)ppression, (l)earn noise, equali(z)er, a(g)c, dyna(m)ics, de-ess/pops (j), feedback (x), echo (k), voice activit(y), preset (t), (c)hain param, (w)aveform viz, (d)efault, (i)nfo, (q)uit: ");
         io::stdout().flush()?;
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
@@ -505,13 +848,24 @@ async fn main() -> Result<()> {
                 println!("Volume set to: {}", new_vol);
             },
             Some('n') => {
-                print!("Enter noise threshold (0.0 to 0.1): ");
+                print!("Enter noise threshold (0.0 to 0.1), or 'auto' optionally followed by the margin in dB above the noise floor: ");
                 io::stdout().flush()?;
                 let mut noise_input = String::new();
                 io::stdin().read_line(&mut noise_input)?;
-                let new_noise: f32 = noise_input.trim().parse().unwrap_or(0.01);
-                *noise.lock().unwrap() = new_noise;
-                println!("Noise threshold set to: {}", new_noise);
+                let mut parts = noise_input.split_whitespace();
+                if parts.next() == Some("auto") {
+                    let mut chain = chain_ui.lock().unwrap();
+                    if let Some(margin) = parts.next().and_then(|p| p.parse::<f32>().ok()) {
+                        chain.settings.auto_gate.margin_db = margin.clamp(0.0, 40.0);
+                    }
+                    chain.settings.auto_gate.enabled = true;
+                    println!("Automatic noise gate on, opening {} dB above the noise floor", chain.settings.auto_gate.margin_db);
+                } else {
+                    let new_noise: f32 = noise_input.trim().parse().unwrap_or(0.01);
+                    *noise.lock().unwrap() = new_noise;
+                    chain_ui.lock().unwrap().settings.auto_gate.enabled = false;
+                    println!("Noise threshold set to: {}", new_noise);
+                }
             },
             Some('f') => {
                 print!("Enter frequency shift (Hz): ");
@@ -531,6 +885,437 @@ async fn main() -> Result<()> {
                 *buffer_limit.lock().unwrap() = new_buf;
                 println!("Buffer size set to: {}", new_buf);
             },
+            Some('p') => {
+                print!("Enter pitch shift in semitones (-12 to +12, 0 = off), optionally followed by 'psola' or 'vocoder': ");
+                io::stdout().flush()?;
+                let mut pitch_input = String::new();
+                io::stdin().read_line(&mut pitch_input)?;
+                let mut parts = pitch_input.split_whitespace();
+                let new_pitch: f32 = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0.0);
+                let new_pitch = new_pitch.clamp(-12.0, 12.0);
+                let algorithm = match parts.next() {
+                    Some("psola") => Some(effects::pitch_shift::PitchAlgorithm::Psola),
+                    Some("vocoder") => Some(effects::pitch_shift::PitchAlgorithm::PhaseVocoder),
+                    _ => None,
+                };
+                let latency_ms = {
+                    let mut chain = chain_ui.lock().unwrap();
+                    chain.settings.pitch.semitones = new_pitch;
+                    chain.settings.pitch.enabled = new_pitch != 0.0;
+                    if let Some(algorithm) = algorithm {
+                        chain.settings.pitch.algorithm = algorithm;
+                    }
+                    chain.latency_ms()
+                };
+                println!("Pitch shift set to: {:+} semitones (chain latency {:.1} ms)", new_pitch, latency_ms);
+            },
+            Some('o') => {
+                print!("Enter formant shift in semitones (-12 to +12) or 'off'; append 'preserve' to keep formants fixed while pitch shifting: ");
+                io::stdout().flush()?;
+                let mut formant_input = String::new();
+                io::stdin().read_line(&mut formant_input)?;
+                let mut parts = formant_input.split_whitespace();
+                let first = parts.next().unwrap_or("off");
+                let preserve = parts.any(|p| p == "preserve") || first == "preserve";
+                let new_formant: Option<f32> = first.parse().ok().or(if preserve { Some(0.0) } else { None });
+                let mut chain = chain_ui.lock().unwrap();
+                match new_formant {
+                    Some(semitones) => {
+                        let semitones = semitones.clamp(-12.0, 12.0);
+                        chain.settings.formant.semitones = semitones;
+                        chain.settings.formant.preserve = preserve;
+                        chain.settings.formant.enabled = true;
+                        println!("Formant shift set to: {:+} semitones{} (chain latency {:.1} ms)", semitones, if preserve { ", preserving formants" } else { "" }, chain.latency_ms());
+                    }
+                    None => {
+                        chain.settings.formant.enabled = false;
+                        println!("Formant shift disabled.");
+                    }
+                }
+            },
+            Some('e') => {
+                println!("Voice characters:");
+                for voice in Voice::ALL {
+                    println!("  {:<10} {}", voice.name(), voice.description());
+                }
+                print!("Enter voice name (or 'none'), optionally followed by intensity (0.0 - 1.0) and tone (-1.0 - 1.0): ");
+                io::stdout().flush()?;
+                let mut voice_input = String::new();
+                io::stdin().read_line(&mut voice_input)?;
+                let mut parts = voice_input.split_whitespace();
+                let name = parts.next().unwrap_or("none");
+                let defaults = VoiceMacros::default();
+                let macros = VoiceMacros {
+                    intensity: parts.next().and_then(|p| p.parse().ok()).unwrap_or(defaults.intensity),
+                    tone: parts.next().and_then(|p| p.parse().ok()).unwrap_or(defaults.tone),
+                };
+                if name == "none" {
+                    chain_ui.lock().unwrap().apply_voice(None);
+                    println!("Voice character cleared.");
+                } else if let Some(voice) = Voice::from_name(name) {
+                    chain_ui.lock().unwrap().apply_voice(Some(VoiceSelection { voice, macros }));
+                    println!("Voice set to: {} (intensity {:.2}, tone {:+.2})", voice.name(), macros.intensity, macros.tone);
+                } else {
+                    println!("Unknown voice: {}", name);
+                }
+            },
+            Some('h') => {
+                print!("Enter vocoder carrier (saw, pulse, noise) and MIDI note (e.g. 'saw 48'), or 'off': ");
+                io::stdout().flush()?;
+                let mut vocoder_input = String::new();
+                io::stdin().read_line(&mut vocoder_input)?;
+                let mut parts = vocoder_input.split_whitespace();
+                let carrier = match parts.next() {
+                    Some("saw") => Some(effects::vocoder::CarrierWaveform::Saw),
+                    Some("pulse") => Some(effects::vocoder::CarrierWaveform::Pulse),
+                    Some("noise") => Some(effects::vocoder::CarrierWaveform::Noise),
+                    _ => None,
+                };
+                let mut chain = chain_ui.lock().unwrap();
+                match carrier {
+                    Some(carrier) => {
+                        chain.settings.vocoder.enabled = true;
+                        chain.settings.vocoder.carrier = carrier;
+                        if let Some(note) = parts.next().and_then(|p| p.parse::<f32>().ok()) {
+                            chain.settings.vocoder.carrier_note = note.clamp(0.0, 127.0);
+                        }
+                        println!("Vocoder enabled: {:?} carrier at MIDI note {:.0}", carrier, chain.settings.vocoder.carrier_note);
+                    }
+                    None => {
+                        chain.settings.vocoder.enabled = false;
+                        println!("Vocoder disabled.");
+                    }
+                }
+            },
+            Some('u') => {
+                print!("Enter noise reduction in dB (0 to 40) or 'off'; append 'replace' to use it instead of the noise gate: ");
+                io::stdout().flush()?;
+                let mut denoise_input = String::new();
+                io::stdin().read_line(&mut denoise_input)?;
+                let mut parts = denoise_input.split_whitespace();
+                let reduction: Option<f32> = parts.next().and_then(|p| p.parse().ok());
+                let replace = parts.any(|p| p == "replace");
+                let mut chain = chain_ui.lock().unwrap();
+                match reduction {
+                    Some(reduction) => {
+                        let reduction = reduction.clamp(0.0, 40.0);
+                        chain.settings.denoise.enabled = true;
+                        chain.settings.denoise.reduction_db = reduction;
+                        chain.settings.denoise.bypass_gate = replace;
+                        println!("Noise suppression set to: {} dB{}", reduction, if replace { " (noise gate bypassed)" } else { "" });
+                    }
+                    None => {
+                        chain.settings.denoise.enabled = false;
+                        println!("Noise suppression disabled.");
+                    }
+                }
+            },
+            Some('l') => {
+                print!("Enter seconds of room tone to record (default 3), or 'clear' to forget the profile: ");
+                io::stdout().flush()?;
+                let mut learn_input = String::new();
+                io::stdin().read_line(&mut learn_input)?;
+                if learn_input.trim() == "clear" {
+                    chain_ui.lock().unwrap().settings.denoise.profile = None;
+                    println!("Noise profile cleared.");
+                } else {
+                    let seconds: f32 = learn_input.trim().parse().unwrap_or(3.0);
+                    let seconds = seconds.clamp(effects::denoise::MIN_LEARN_SECONDS, effects::denoise::MAX_LEARN_SECONDS);
+                    println!("Learning room tone for {} seconds, please stay quiet...", seconds);
+                    chain_ui.lock().unwrap().learn_noise(seconds);
+                    let deadline = Instant::now() + Duration::from_secs_f32(seconds + 2.0);
+                    while chain_ui.lock().unwrap().noise_learning().is_some() && Instant::now() < deadline {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                    if chain_ui.lock().unwrap().noise_learning().is_some() {
+                        println!("Still learning; is the input device delivering audio?");
+                    } else {
+                        println!("Noise profile learned; spectral noise suppression is on.");
+                    }
+                }
+            },
+            Some('z') => {
+                print!("Enter '<band #> <type:freq:gain:q>' to change a band, 'add <type:freq:gain:q>', 'remove <band #>', 'on' or 'off': ");
+                io::stdout().flush()?;
+                let mut eq_input = String::new();
+                io::stdin().read_line(&mut eq_input)?;
+                let mut parts = eq_input.split_whitespace();
+                let mut chain = chain_ui.lock().unwrap();
+                let eq = &mut chain.settings.eq;
+                let result = match (parts.next(), parts.next()) {
+                    (Some("on"), _) => {
+                        eq.enabled = true;
+                        Ok(())
+                    }
+                    (Some("off"), _) => {
+                        eq.enabled = false;
+                        Ok(())
+                    }
+                    (Some("add"), Some(spec)) if eq.bands.len() < effects::eq::MAX_BANDS => spec.parse().map(|band| {
+                        eq.bands.push(band);
+                        eq.enabled = true;
+                    }),
+                    (Some("add"), Some(_)) => Err(anyhow::anyhow!("The equalizer has at most {} bands", effects::eq::MAX_BANDS)),
+                    (Some("remove"), Some(index)) => match index.parse::<usize>() {
+                        Ok(index) if (1..=eq.bands.len()).contains(&index) => {
+                            eq.bands.remove(index - 1);
+                            Ok(())
+                        }
+                        _ => Err(anyhow::anyhow!("No EQ band {}", index)),
+                    },
+                    (Some(index), Some(spec)) => match index.parse::<usize>() {
+                        Ok(index) if (1..=eq.bands.len()).contains(&index) => spec.parse().map(|band| {
+                            eq.bands[index - 1] = band;
+                            eq.enabled = true;
+                        }),
+                        _ => Err(anyhow::anyhow!("No EQ band {}", index)),
+                    },
+                    _ => Err(anyhow::anyhow!("Expected a band number, 'add', 'remove', 'on' or 'off'")),
+                };
+                match result {
+                    Ok(()) => {
+                        println!("Equalizer {}:", if eq.enabled { "on" } else { "off" });
+                        for (i, band) in eq.bands.iter().enumerate() {
+                            println!("  {}: {:<10} {:7.0} Hz {:+5.1} dB  Q {:.2}", i + 1, band.band_type.name(), band.frequency, band.gain_db, band.q);
+                        }
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            },
+            Some('g') => {
+                print!("Enter AGC target loudness in LUFS (e.g. -18), optionally followed by 'slow' or 'fast', or 'off': ");
+                io::stdout().flush()?;
+                let mut agc_input = String::new();
+                io::stdin().read_line(&mut agc_input)?;
+                let mut parts = agc_input.split_whitespace();
+                let mut chain = chain_ui.lock().unwrap();
+                match parts.next().and_then(|t| t.parse::<f32>().ok()) {
+                    Some(target) => {
+                        let agc = &mut chain.settings.agc;
+                        agc.target_lufs = target.clamp(-40.0, -6.0);
+                        match parts.next() {
+                            Some("slow") => agc.mode = effects::agc::AgcMode::Slow,
+                            Some("fast") => agc.mode = effects::agc::AgcMode::Fast,
+                            _ => {}
+                        }
+                        agc.enabled = true;
+                        println!("AGC set to: {} LUFS ({:?})", agc.target_lufs, agc.mode);
+                    }
+                    None => {
+                        chain.settings.agc.enabled = false;
+                        println!("AGC disabled.");
+                    }
+                }
+            },
+            Some('m') => {
+                print!("Enter 'comp <threshold dB> <ratio>', 'limit <ceiling dB>', 'comp off' or 'limit off': ");
+                io::stdout().flush()?;
+                let mut dynamics_input = String::new();
+                io::stdin().read_line(&mut dynamics_input)?;
+                let mut parts = dynamics_input.split_whitespace();
+                let mut chain = chain_ui.lock().unwrap();
+                match (parts.next(), parts.next()) {
+                    (Some("comp"), Some("off")) => {
+                        chain.settings.compressor.enabled = false;
+                        println!("Compressor disabled.");
+                    }
+                    (Some("comp"), threshold) => {
+                        let compressor = &mut chain.settings.compressor;
+                        if let Some(threshold) = threshold.and_then(|t| t.parse::<f32>().ok()) {
+                            compressor.threshold_db = threshold.clamp(-60.0, 0.0);
+                        }
+                        if let Some(ratio) = parts.next().and_then(|r| r.parse::<f32>().ok()) {
+                            compressor.ratio = ratio.clamp(1.0, 20.0);
+                        }
+                        compressor.enabled = true;
+                        println!("Compressor set to: {} dB at {}:1", compressor.threshold_db, compressor.ratio);
+                    }
+                    (Some("limit"), Some("off")) => {
+                        chain.settings.limiter.enabled = false;
+                        println!("Limiter disabled.");
+                    }
+                    (Some("limit"), ceiling) => {
+                        let limiter = &mut chain.settings.limiter;
+                        if let Some(ceiling) = ceiling.and_then(|c| c.parse::<f32>().ok()) {
+                            limiter.ceiling_db = ceiling.clamp(-24.0, 0.0);
+                        }
+                        limiter.enabled = true;
+                        println!("Limiter set to: {} dBTP ceiling", limiter.ceiling_db);
+                    }
+                    _ => println!("Expected 'comp ...' or 'limit ...'"),
+                }
+            },
+            Some('j') => {
+                print!("Enter 'ess <frequency Hz> <threshold dB>', 'pop <threshold dB>', 'ess off' or 'pop off': ");
+                io::stdout().flush()?;
+                let mut sibilance_input = String::new();
+                io::stdin().read_line(&mut sibilance_input)?;
+                let mut parts = sibilance_input.split_whitespace();
+                let mut chain = chain_ui.lock().unwrap();
+                match (parts.next(), parts.next()) {
+                    (Some("ess"), Some("off")) => {
+                        chain.settings.deesser.enabled = false;
+                        println!("De-esser disabled.");
+                    }
+                    (Some("ess"), frequency) => {
+                        let deesser = &mut chain.settings.deesser;
+                        if let Some(frequency) = frequency.and_then(|f| f.parse::<f32>().ok()) {
+                            deesser.frequency = frequency.clamp(2000.0, 16000.0);
+                        }
+                        if let Some(threshold) = parts.next().and_then(|t| t.parse::<f32>().ok()) {
+                            deesser.threshold_db = threshold.clamp(-60.0, 0.0);
+                        }
+                        deesser.enabled = true;
+                        println!("De-esser set to: above {} Hz, {} dB", deesser.frequency, deesser.threshold_db);
+                    }
+                    (Some("pop"), Some("off")) => {
+                        chain.settings.plosive.enabled = false;
+                        println!("Plosive suppression disabled.");
+                    }
+                    (Some("pop"), threshold) => {
+                        let plosive = &mut chain.settings.plosive;
+                        if let Some(threshold) = threshold.and_then(|t| t.parse::<f32>().ok()) {
+                            plosive.threshold_db = threshold.clamp(-60.0, 0.0);
+                        }
+                        plosive.enabled = true;
+                        println!("Plosive suppression set to: below {} Hz, {} dB", plosive.cutoff, plosive.threshold_db);
+                    }
+                    _ => println!("Expected 'ess ...' or 'pop ...'"),
+                }
+            },
+            Some('x') => {
+                print!("Enter feedback sensitivity in dB (e.g. 20), 'reset' to remove the notches, or 'off': ");
+                io::stdout().flush()?;
+                let mut feedback_input = String::new();
+                io::stdin().read_line(&mut feedback_input)?;
+                let mut chain = chain_ui.lock().unwrap();
+                match feedback_input.trim() {
+                    "reset" => {
+                        chain.reset_feedback();
+                        println!("Feedback notches removed.");
+                    }
+                    "off" => {
+                        chain.settings.feedback.enabled = false;
+                        println!("Feedback suppression disabled.");
+                    }
+                    text => {
+                        let feedback = &mut chain.settings.feedback;
+                        if let Ok(sensitivity) = text.parse::<f32>() {
+                            feedback.sensitivity_db = sensitivity.clamp(6.0, 40.0);
+                        }
+                        feedback.enabled = true;
+                        println!("Feedback suppression on, sensitivity {} dB", feedback.sensitivity_db);
+                        for notch in chain.status().feedback_notches {
+                            println!("  notch at {:.0} Hz, -{:.1} dB", notch.frequency, notch.depth_db);
+                        }
+                    }
+                }
+            },
+            Some('k') => {
+                print!("Enter echo tail length in ms (e.g. 80) to cancel speaker echo, or 'off': ");
+                io::stdout().flush()?;
+                let mut echo_input = String::new();
+                io::stdin().read_line(&mut echo_input)?;
+                let mut chain = chain_ui.lock().unwrap();
+                let echo = &mut chain.settings.echo;
+                match echo_input.trim() {
+                    "off" => {
+                        echo.enabled = false;
+                        println!("Echo cancellation disabled.");
+                    }
+                    text => {
+                        if let Ok(tail) = text.parse::<f32>() {
+                            echo.tail_ms = tail.clamp(10.0, 180.0);
+                        }
+                        echo.enabled = true;
+                        println!("Echo cancellation on, {} ms tail", echo.tail_ms);
+                    }
+                }
+            },
+            Some('y') => {
+                print!("Enter voice activity threshold in dB above the noise floor (e.g. 9), optionally followed by a hangover in ms, or 'off': ");
+                io::stdout().flush()?;
+                let mut vad_input = String::new();
+                io::stdin().read_line(&mut vad_input)?;
+                let mut parts = vad_input.split_whitespace();
+                let mut chain = chain_ui.lock().unwrap();
+                let vad = &mut chain.settings.vad;
+                match parts.next() {
+                    Some("off") => {
+                        vad.enabled = false;
+                        println!("Voice activity detection disabled.");
+                    }
+                    threshold => {
+                        if let Some(threshold) = threshold.and_then(|t| t.parse::<f32>().ok()) {
+                            vad.threshold_db = threshold.clamp(3.0, 30.0);
+                        }
+                        if let Some(hangover) = parts.next().and_then(|h| h.parse::<f32>().ok()) {
+                            vad.hangover_ms = hangover.clamp(0.0, 2000.0);
+                        }
+                        vad.enabled = true;
+                        println!("Voice activity detection on: {} dB, {} ms hangover", vad.threshold_db, vad.hangover_ms);
+                    }
+                }
+            },
+            Some('t') => {
+                print!("Enter 'save <file>', 'load <file>', 'morph <a> <b>', 'morph <0-1>' or 'morph off': ");
+                io::stdout().flush()?;
+                let mut preset_input = String::new();
+                io::stdin().read_line(&mut preset_input)?;
+                let (action, file) = preset_input.trim().split_once(' ').unwrap_or((preset_input.trim(), ""));
+                let path = Path::new(file.trim());
+                let result = match action {
+                    "save" if !file.trim().is_empty() => presets::save(path, &chain_ui.lock().unwrap().settings)
+                        .map(|_| println!("Preset saved to {}", path.display())),
+                    "load" if !file.trim().is_empty() => presets::load(path)
+                        .map(|settings| {
+                            chain_ui.lock().unwrap().apply_preset(settings);
+                            println!("Preset loaded from {}", path.display());
+                        }),
+                    "morph" => match file.split_whitespace().collect::<Vec<_>>().as_slice() {
+                        ["off"] => {
+                            presets::stop_morph(&chain_ui);
+                            println!("Morph stopped, keeping the current blend");
+                            Ok(())
+                        }
+                        [amount] => match amount.parse::<f32>() {
+                            Ok(amount) => {
+                                match presets::move_morph(&chain_ui, amount) {
+                                    Some(amount) => println!("Morph set to: {:.0}% B", amount * 100.0),
+                                    None => println!("No morph running; start one with 'morph <a> <b>'"),
+                                }
+                                Ok(())
+                            }
+                            Err(_) => Err(anyhow::anyhow!("Invalid morph amount: {}", amount)),
+                        },
+                        [a, b] => presets::start_morph(&chain_ui, Path::new(a), Path::new(b))
+                            .map(|_| println!("Morphing between {} and {}", a, b)),
+                        _ => Err(anyhow::anyhow!("Expected 'morph <a> <b>', 'morph <0-1>' or 'morph off'")),
+                    },
+                    _ => Err(anyhow::anyhow!("Expected 'save <file>' or 'load <file>'")),
+                };
+                if let Err(e) = result {
+                    println!("{}", e);
+                }
+            },
+            Some('c') => {
+                print!("Enter chain parameter and value (e.g. pitch.phase_lock false): ");
+                io::stdout().flush()?;
+                let mut param_input = String::new();
+                io::stdin().read_line(&mut param_input)?;
+                let mut parts = param_input.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some(path), Some(raw)) => {
+                        let value = serde_json::from_str(raw)
+                            .unwrap_or_else(|_| serde_json::Value::String(raw.to_string()));
+                        match chain_ui.lock().unwrap().settings.set(path, value) {
+                            Ok(()) => println!("{} set to: {}", path, raw),
+                            Err(e) => println!("Could not set {}: {}", path, e),
+                        }
+                    }
+                    _ => println!("Expected a parameter path followed by a value."),
+                }
+            },
             Some('d') => {
                 // Load default settings
                 *vol.lock().unwrap() = 0.8;
@@ -540,6 +1325,7 @@ async fn main() -> Result<()> {
                 *smooth.lock().unwrap() = 0.7;
                 *freq.lock().unwrap() = 5.0;
                 *buffer_limit.lock().unwrap() = 2400;
+                chain_ui.lock().unwrap().apply_voice(None);
                 println!("\nDefault settings loaded!");
                 display_settings();
             },
@@ -628,7 +1414,7 @@ async fn main() -> Result<()> {
                 display_settings();
             },
             Some('q') => break,
-            _ => println!("Invalid option. Use v, n, a, r, s, f, b, w, d, i, or q."),
+            _ => println!("Invalid option. Use v, n, a, r, s, f, b, p, o, e, h, u, l, z, g, m, j, x, k, y, t, c, w, d, i, or q."),
         }
     }
 
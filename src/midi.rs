@@ -0,0 +1,129 @@
+//! MIDI input: notes played on a connected keyboard drive the vocoder carrier,
+//! and the modulation wheel moves the preset morph.
+
+use crate::effects::Chain;
+use crate::presets;
+use anyhow::Result;
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use std::sync::{Arc, Mutex};
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const CONTROL_CHANGE: u8 = 0xB0;
+
+/// Controller moving the preset morph: the modulation wheel.
+const MORPH_CONTROLLER: u8 = 1;
+
+pub fn list_ports() -> Result<Vec<String>> {
+    let input = MidiInput::new("voice_transformer")?;
+    Ok(input
+        .ports()
+        .iter()
+        .map(|port| input.port_name(port).unwrap_or_else(|_| "<unknown>".to_string()))
+        .collect())
+}
+
+/// Connect to a MIDI input port. The returned connection must be kept alive for
+/// as long as MIDI input should be handled.
+pub fn connect(port_index: usize, chain: Arc<Mutex<Chain>>) -> Result<MidiInputConnection<()>> {
+    let mut input = MidiInput::new("voice_transformer")?;
+    input.ignore(Ignore::All);
+
+    let ports = input.ports();
+    let port = ports
+        .get(port_index)
+        .ok_or_else(|| anyhow::anyhow!("Invalid MIDI port ID"))?;
+    println!("Using MIDI input: {}", input.port_name(port)?);
+
+    // Notes currently held, most recent last, so releasing a key falls back to
+    // the previous one (legato)
+    let mut held: Vec<u8> = Vec::new();
+
+    input
+        .connect(
+            port,
+            "voice_transformer-input",
+            move |_, message, _| {
+                if let Some(amount) = morph_amount(message) {
+                    presets::move_morph(&chain, amount);
+                    return;
+                }
+                let note = match handle_note(message, &mut held) {
+                    Some(note) => note,
+                    None => return,
+                };
+                chain.lock().unwrap().settings.vocoder.carrier_note = note as f32;
+            },
+            (),
+        )
+        .map_err(|e| anyhow::anyhow!("Could not connect to MIDI port: {}", e))
+}
+
+/// Update the held notes for a channel message and return the note the carrier
+/// should play, if it changed.
+fn handle_note(message: &[u8], held: &mut Vec<u8>) -> Option<u8> {
+    if message.len() < 3 {
+        return None;
+    }
+    let (status, note, velocity) = (message[0] & 0xF0, message[1], message[2]);
+
+    match status {
+        NOTE_ON if velocity > 0 => {
+            held.retain(|&n| n != note);
+            held.push(note);
+            Some(note)
+        }
+        NOTE_ON | NOTE_OFF => {
+            let was_playing = held.last() == Some(&note);
+            held.retain(|&n| n != note);
+            if was_playing {
+                held.last().copied()
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Morph position (0.0 - 1.0) set by a modulation wheel message, if it is one.
+fn morph_amount(message: &[u8]) -> Option<f32> {
+    match message {
+        [status, MORPH_CONTROLLER, value, ..] if status & 0xF0 == CONTROL_CHANGE => Some(*value as f32 / 127.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releasing_a_note_falls_back_to_the_previous_one() {
+        let mut held = Vec::new();
+        assert_eq!(handle_note(&[NOTE_ON, 60, 100], &mut held), Some(60));
+        assert_eq!(handle_note(&[NOTE_ON, 64, 100], &mut held), Some(64));
+        assert_eq!(handle_note(&[NOTE_ON, 67, 100], &mut held), Some(67));
+        // Releasing a note that is not sounding changes nothing
+        assert_eq!(handle_note(&[NOTE_OFF, 64, 0], &mut held), None);
+        assert_eq!(handle_note(&[NOTE_OFF, 67, 0], &mut held), Some(60));
+        // A note-on with zero velocity is a release
+        assert_eq!(handle_note(&[NOTE_ON, 60, 0], &mut held), None);
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn notes_on_any_channel_count() {
+        let mut held = Vec::new();
+        assert_eq!(handle_note(&[NOTE_ON | 0x0F, 72, 1], &mut held), Some(72));
+        assert_eq!(handle_note(&[CONTROL_CHANGE, 72, 1], &mut held), None);
+        assert_eq!(handle_note(&[NOTE_ON], &mut held), None);
+    }
+
+    #[test]
+    fn modulation_wheel_sets_the_morph() {
+        assert_eq!(morph_amount(&[CONTROL_CHANGE, MORPH_CONTROLLER, 0]), Some(0.0));
+        assert_eq!(morph_amount(&[CONTROL_CHANGE | 3, MORPH_CONTROLLER, 127]), Some(1.0));
+        assert_eq!(morph_amount(&[CONTROL_CHANGE, 7, 127]), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Presets: the complete processing chain settings stored as a JSON file,
+//! and morphs between two of them.
+
+use crate::effects::chain::Morph;
+use crate::effects::morph::MorphPresets;
+use crate::effects::{Chain, ChainSettings};
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub fn load(path: &Path) -> Result<ChainSettings> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read preset {}: {}", path.display(), e))?;
+    let settings = serde_json::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("Invalid preset {}: {}", path.display(), e))?;
+    Ok(settings)
+}
+
+pub fn save(path: &Path, settings: &ChainSettings) -> Result<()> {
+    let text = serde_json::to_string_pretty(settings)?;
+    fs::write(path, text)
+        .map_err(|e| anyhow::anyhow!("Could not write preset {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Start morphing from preset file `a` to `b`, positioned at `a`. The files
+/// are read and the morph built before the chain is locked, and a morph it
+/// replaces is dropped after.
+pub fn start_morph(chain: &Mutex<Chain>, a: &Path, b: &Path) -> Result<()> {
+    let presets = Arc::new(MorphPresets::new(&load(a)?, &load(b)?));
+    let sample_rate = chain.lock().unwrap().sample_rate();
+    let morph = Morph::new(sample_rate, Arc::clone(&presets));
+    let start = presets.position(0.0);
+    let replaced = {
+        let mut chain = chain.lock().unwrap();
+        let replaced = chain.start_morph(morph);
+        chain.set_morph(start);
+        replaced
+    };
+    drop(replaced);
+    Ok(())
+}
+
+/// Move the running morph to `amount`, from 0.0 (preset A) to 1.0 (preset
+/// B), blending outside the lock. Returns the new position, or `None` when
+/// no morph is running.
+pub fn move_morph(chain: &Mutex<Chain>, amount: f32) -> Option<f32> {
+    let presets = chain.lock().unwrap().morph_presets()?;
+    let position = presets.position(amount);
+    let amount = position.amount;
+    chain.lock().unwrap().set_morph(position);
+    Some(amount)
+}
+
+/// End the running morph, keeping the current blend.
+pub fn stop_morph(chain: &Mutex<Chain>) {
+    let stopped = chain.lock().unwrap().stop_morph();
+    drop(stopped);
+}
@@ -0,0 +1,51 @@
+//! Target voice recordings for the voice conversion stage.
+//!
+//! Like impulse responses, the file is named in the chain settings
+//! (`conversion.target`); a background thread analyses a newly named
+//! recording away from the audio callback and hands the finished converter
+//! to the chain.
+
+use crate::effects::conversion::{VoiceConverter, VoiceProfile};
+use crate::effects::Chain;
+use crate::impulses;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Longest stretch of a recording analysed; more adds little to the averages.
+const MAX_SECONDS: f32 = 180.0;
+
+/// How often the loader looks for a changed file name.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Read a recording of the target voice and measure its average spectral
+/// envelope and pitch statistics at `sample_rate`.
+pub fn load(path: &Path, sample_rate: f32) -> Result<VoiceProfile> {
+    let mut samples = impulses::read_mono(path, sample_rate)
+        .map_err(|e| anyhow::anyhow!("Could not read target voice {}: {}", path.display(), e))?;
+    samples.truncate((MAX_SECONDS * sample_rate) as usize);
+    VoiceProfile::analyze(&samples, sample_rate)
+        .ok_or_else(|| anyhow::anyhow!("Target voice {} holds no voiced speech", path.display()))
+}
+
+/// Keep the chain's voice converter in step with `conversion.target`.
+pub fn spawn_loader(chain: Arc<Mutex<Chain>>, sample_rate: f32) {
+    std::thread::spawn(move || loop {
+        let pending = chain.lock().unwrap().pending_target();
+        if let Some(file) = pending {
+            let profile = match load(Path::new(&file), sample_rate) {
+                Ok(profile) => {
+                    println!("Target voice loaded: {} (median pitch {:.0} Hz, range {:.1} semitones)", file, profile.median_pitch(), profile.range());
+                    Some(profile)
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    None
+                }
+            };
+            chain.lock().unwrap().install_target(file, VoiceConverter::new(sample_rate, profile));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    });
+}
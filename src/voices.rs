@@ -0,0 +1,175 @@
+//! Built-in voice characters.
+//!
+//! Each character is a documented combination of processing chain stages. Two
+//! macro controls shape every character: `intensity` (0.0 - 1.0) scales how
+//! strong the effect is, and `tone` (-1.0 - 1.0) moves its colour darker or
+//! brighter. Applying a character replaces the current chain settings; the
+//! individual stage parameters can still be tweaked afterwards.
+
+use crate::effects::ChainSettings;
+use crate::effects::pitch_shift::PitchAlgorithm;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Voice {
+    /// Ring modulation with a low sine carrier
+    Robot,
+    /// Pitch and formants shifted up together
+    Chipmunk,
+    /// Pitch and formants shifted down together
+    Deep,
+    /// Pitch up with formants warped down, plus a faint metallic ring
+    Alien,
+    /// Band-limited, saturated AM radio
+    Radio,
+    /// Narrow 300 - 3400 Hz telephone line
+    Telephone,
+    /// Noise-excited spectral envelope
+    Whisper,
+}
+
+/// Macro controls shared by every voice character.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct VoiceMacros {
+    /// Effect strength, 0.0 - 1.0
+    pub intensity: f32,
+    /// Darker (-1.0) to brighter (+1.0)
+    pub tone: f32,
+}
+
+impl Default for VoiceMacros {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            tone: 0.0,
+        }
+    }
+}
+
+/// A character together with the macro values it was applied with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VoiceSelection {
+    pub voice: Voice,
+    pub macros: VoiceMacros,
+}
+
+impl Voice {
+    pub const ALL: [Voice; 7] = [
+        Voice::Robot,
+        Voice::Chipmunk,
+        Voice::Deep,
+        Voice::Alien,
+        Voice::Radio,
+        Voice::Telephone,
+        Voice::Whisper,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Voice::Robot => "robot",
+            Voice::Chipmunk => "chipmunk",
+            Voice::Deep => "deep",
+            Voice::Alien => "alien",
+            Voice::Radio => "radio",
+            Voice::Telephone => "telephone",
+            Voice::Whisper => "whisper",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Voice> {
+        Voice::ALL.iter().copied().find(|voice| voice.name() == name)
+    }
+
+    /// Stage combination and macro mapping, as shown in the menu.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Voice::Robot => "ring mod 40-160 Hz (tone), mix 40-100% and 12-8 bit crush (intensity), pops reduced",
+            Voice::Chipmunk => "pitch +4..+12 st (intensity), formant offset +-3 st (tone), de-essed",
+            Voice::Deep => "pitch -4..-12 st (intensity), formant offset +-3 st (tone), de-essed",
+            Voice::Alien => "pitch +3..+7 st, formants -4..-8 st (intensity), ring mod 530-1200 Hz (tone), de-essed",
+            Voice::Radio => "band 400-4000 Hz shifted by tone, drive 1.5-6x (intensity)",
+            Voice::Telephone => "band 300-3400 Hz narrowing with intensity, tilted by tone",
+            Voice::Whisper => "noise excitation 50-100% (intensity), formant offset +-4 st (tone), pops reduced",
+        }
+    }
+
+    /// Chain settings that realise this character with the given macros.
+    pub fn settings(&self, macros: &VoiceMacros) -> ChainSettings {
+        let intensity = macros.intensity.clamp(0.0, 1.0);
+        let tone = macros.tone.clamp(-1.0, 1.0);
+        let mut settings = ChainSettings::default();
+
+        match self {
+            Voice::Robot => {
+                settings.ring_mod.enabled = true;
+                settings.ring_mod.frequency = 80.0 * 2.0f32.powf(tone);
+                settings.ring_mod.mix = 0.4 + 0.6 * intensity;
+                settings.bitcrusher.enabled = true;
+                settings.bitcrusher.bits = 12.0 - 4.0 * intensity;
+                settings.bitcrusher.sample_rate = 16000.0;
+                settings.plosive.enabled = true;
+            }
+            Voice::Chipmunk | Voice::Deep => {
+                let direction = if *self == Voice::Chipmunk { 1.0 } else { -1.0 };
+                settings.pitch.enabled = true;
+                settings.pitch.algorithm = PitchAlgorithm::PhaseVocoder;
+                settings.pitch.semitones = direction * (4.0 + 8.0 * intensity);
+                if tone != 0.0 {
+                    settings.formant.enabled = true;
+                    settings.formant.semitones = 3.0 * tone;
+                }
+                tame_shifted_voice(&mut settings);
+            }
+            Voice::Alien => {
+                settings.pitch.enabled = true;
+                settings.pitch.algorithm = PitchAlgorithm::PhaseVocoder;
+                settings.pitch.semitones = 3.0 + 4.0 * intensity;
+                settings.formant.enabled = true;
+                settings.formant.preserve = true;
+                settings.formant.semitones = -(4.0 + 4.0 * intensity);
+                settings.ring_mod.enabled = true;
+                settings.ring_mod.frequency = 800.0 * 1.5f32.powf(tone);
+                settings.ring_mod.mix = 0.2;
+                tame_shifted_voice(&mut settings);
+            }
+            Voice::Radio => {
+                let shift = 2.0f32.powf(tone);
+                settings.band_limit.enabled = true;
+                settings.band_limit.low_cut = 400.0 * shift;
+                settings.band_limit.high_cut = 4000.0 * shift;
+                settings.band_limit.drive = 1.5 + 4.5 * intensity;
+            }
+            Voice::Telephone => {
+                let tilt = 1.5f32.powf(tone);
+                settings.band_limit.enabled = true;
+                settings.band_limit.low_cut = (300.0 + 200.0 * intensity) * tilt;
+                settings.band_limit.high_cut = (3400.0 - 900.0 * intensity) * tilt;
+                settings.band_limit.drive = 1.2;
+            }
+            Voice::Whisper => {
+                settings.whisper.enabled = true;
+                settings.whisper.amount = 0.5 + 0.5 * intensity;
+                settings.plosive.enabled = true;
+                if tone != 0.0 {
+                    settings.formant.enabled = true;
+                    settings.formant.semitones = 4.0 * tone;
+                }
+            }
+        }
+
+        settings
+    }
+}
+
+/// Pitch shifting exaggerates sibilance and mic pops. Pops are reduced before
+/// the shifter; the de-esser runs after it, with its split moved along with
+/// the shifted sibilants.
+fn tame_shifted_voice(settings: &mut ChainSettings) {
+    settings.plosive.enabled = true;
+    settings.deesser.enabled = true;
+    settings.deesser.frequency =
+        (settings.deesser.frequency * 2.0f32.powf(settings.pitch.semitones / 12.0)).clamp(3000.0, 12000.0);
+}
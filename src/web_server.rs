@@ -1,9 +1,14 @@
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use warp::Filter;
 use serde::{Deserialize, Serialize};
 use warp::ws::Message;
 use futures_util::StreamExt;
+use crate::effects::vad::VadEvent;
+use crate::effects::{Chain, ChainStatus};
+use crate::presets;
+use crate::voices::{Voice, VoiceMacros, VoiceSelection};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FFTData {
@@ -14,10 +19,61 @@ pub struct FFTData {
     pub fft_size: usize,
 }
 
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusData {
+    pub r#type: String,
+    #[serde(flatten)]
+    pub status: ChainStatus,
+}
+
+/// Voice activity start/stop event, sent as soon as the status loop sees it.
+#[derive(Serialize, Debug, Clone)]
+pub struct VoiceActivityData {
+    pub r#type: String,
+    /// `start` or `stop`
+    pub event: String,
+    /// Stream time of the change, in seconds since processing started
+    pub time_s: f32,
+}
+
+/// Messages sent from the browser to change processing parameters.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// Set a chain parameter by dotted path, e.g. `pitch.semitones`
+    Set { param: String, value: serde_json::Value },
+    /// Apply a voice character (or clear it with `null`) with its macro controls
+    Voice {
+        voice: Option<Voice>,
+        #[serde(default)]
+        macros: VoiceMacros,
+    },
+    /// Record room tone for the noise suppressor's profile, 0.5 - 10 seconds
+    LearnNoise {
+        #[serde(default = "default_learn_seconds")]
+        seconds: f32,
+    },
+    /// Remove the notches placed by the feedback suppressor
+    ResetFeedback,
+    /// Load two preset files (paths on the server) to morph between
+    LoadMorph { a: String, b: String },
+    /// Move the morph between preset A (0.0) and B (1.0)
+    Morph { amount: f32 },
+    /// End the morph, keeping the current blend
+    StopMorph,
+}
+
+fn default_learn_seconds() -> f32 {
+    3.0
+}
+
+pub type SharedChain = Arc<std::sync::Mutex<Chain>>;
+
 pub type WebSocketSender = Arc<Mutex<Option<tokio::sync::mpsc::UnboundedSender<Message>>>>;
 
 pub async fn start_web_server(
     fft_sender: WebSocketSender,
+    chain: SharedChain,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Serve static files
     let static_files = warp::path::end()
@@ -34,8 +90,9 @@ pub async fn start_web_server(
     let websocket = warp::path("ws")
         .and(warp::ws())
         .and(with_sender(fft_sender))
-        .map(|ws: warp::ws::Ws, sender| {
-            ws.on_upgrade(move |socket| handle_websocket(socket, sender))
+        .and(with_chain(chain))
+        .map(|ws: warp::ws::Ws, sender, chain| {
+            ws.on_upgrade(move |socket| handle_websocket(socket, sender, chain))
         });
 
     let routes = static_files.or(websocket);
@@ -56,9 +113,16 @@ fn with_sender(
     warp::any().map(move || sender.clone())
 }
 
+fn with_chain(
+    chain: SharedChain,
+) -> impl Filter<Extract = (SharedChain,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || chain.clone())
+}
+
 async fn handle_websocket(
     ws: warp::ws::WebSocket,
     global_sender: WebSocketSender,
+    chain: SharedChain,
 ) {
     let (tx, mut rx) = ws.split();
     let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
@@ -77,6 +141,9 @@ async fn handle_websocket(
                     if msg.is_close() {
                         break;
                     }
+                    if let Ok(text) = msg.to_str() {
+                        handle_control_message(text, &chain);
+                    }
                 }
                 Err(_) => break,
             }
@@ -107,6 +174,38 @@ async fn handle_websocket(
     }
 }
 
+fn handle_control_message(text: &str, chain: &SharedChain) {
+    match serde_json::from_str::<ControlMessage>(text) {
+        Ok(ControlMessage::Set { param, value }) => {
+            if let Err(e) = chain.lock().unwrap().settings.set(&param, value) {
+                eprintln!("Rejected web control for {}: {}", param, e);
+            }
+        }
+        Ok(ControlMessage::Voice { voice, macros }) => {
+            let selection = voice.map(|voice| VoiceSelection { voice, macros });
+            chain.lock().unwrap().apply_voice(selection);
+        }
+        Ok(ControlMessage::LearnNoise { seconds }) => {
+            chain.lock().unwrap().learn_noise(seconds);
+        }
+        Ok(ControlMessage::ResetFeedback) => {
+            chain.lock().unwrap().reset_feedback();
+        }
+        Ok(ControlMessage::LoadMorph { a, b }) => {
+            if let Err(e) = presets::start_morph(chain, Path::new(&a), Path::new(&b)) {
+                eprintln!("Could not start morph: {}", e);
+            }
+        }
+        Ok(ControlMessage::Morph { amount }) => {
+            presets::move_morph(chain, amount);
+        }
+        Ok(ControlMessage::StopMorph) => {
+            presets::stop_morph(chain);
+        }
+        Err(e) => eprintln!("Invalid control message: {}", e),
+    }
+}
+
 pub async fn broadcast_fft_data(
     sender: &WebSocketSender,
     input_spectrum: Vec<f32>,
@@ -131,3 +230,34 @@ let message = warp::ws::Message::text(json);
         }
     }
 }
+
+pub async fn broadcast_vad_events(sender: &WebSocketSender, events: Vec<VadEvent>) {
+    let sender_guard = sender.lock().await;
+    let Some(ref tx) = *sender_guard else {
+        return;
+    };
+    for event in events {
+        let data = VoiceActivityData {
+            r#type: "voice_activity".to_string(),
+            event: if event.speaking { "start" } else { "stop" }.to_string(),
+            time_s: event.time_s,
+        };
+        if let Ok(json) = serde_json::to_string(&data) {
+            let _ = tx.send(Message::text(json));
+        }
+    }
+}
+
+pub async fn broadcast_status(sender: &WebSocketSender, status: ChainStatus) {
+    let data = StatusData {
+        r#type: "status".to_string(),
+        status,
+    };
+
+    if let Ok(json) = serde_json::to_string(&data) {
+        let sender_guard = sender.lock().await;
+        if let Some(ref tx) = *sender_guard {
+            let _ = tx.send(Message::text(json));
+        }
+    }
+}
@@ -1,3 +1,165 @@
+// Chain parameters exposed in the control panel, addressed by dotted path
+const CONTROLS = [
+    { param: 'denoise.enabled', label: 'Noise Suppression', type: 'toggle' },
+    { param: 'denoise.reduction_db', label: 'Reduction (dB)', min: 0, max: 40, step: 1 },
+    { param: 'denoise.smoothing', label: 'NR Smoothing', min: 0.9, max: 0.99, step: 0.01 },
+    { param: 'denoise.bypass_gate', label: 'Replace Gate', type: 'toggle' },
+    { param: 'auto_gate.enabled', label: 'Auto Gate', type: 'toggle' },
+    { param: 'auto_gate.margin_db', label: 'Gate Margin (dB)', min: 0, max: 30, step: 1 },
+    { param: 'auto_gate.hysteresis_db', label: 'Hysteresis (dB)', min: 0, max: 12, step: 0.5 },
+    { param: 'auto_gate.hold_ms', label: 'Hold (ms)', min: 0, max: 500, step: 10 },
+    { param: 'auto_gate.lookahead_ms', label: 'Lookahead (ms)', min: 0, max: 20, step: 1 },
+    { param: 'auto_gate.range_db', label: 'Gate Range (dB)', min: 6, max: 80, step: 1 },
+    { param: 'autotune.enabled', label: 'Pitch Correction', type: 'toggle' },
+    { param: 'autotune.key', label: 'Key', type: 'select', options: ['c', 'c_sharp', 'd', 'd_sharp', 'e', 'f', 'f_sharp', 'g', 'g_sharp', 'a', 'a_sharp', 'b'] },
+    { param: 'autotune.scale', label: 'Scale', type: 'select', options: ['chromatic', 'major', 'minor', 'harmonic_minor', 'major_pentatonic', 'minor_pentatonic', 'blues'] },
+    { param: 'autotune.retune_ms', label: 'Retune Speed (ms)', min: 0, max: 500, step: 5 },
+    { param: 'autotune.humanize', label: 'Humanize', min: 0, max: 1, step: 0.05 },
+    { param: 'autotune.robotic', label: 'Robotic', type: 'toggle' },
+    { param: 'pitch.enabled', label: 'Pitch Shift', type: 'toggle' },
+    { param: 'pitch.algorithm', label: 'Algorithm', type: 'select', options: ['phase_vocoder', 'psola'] },
+    { param: 'pitch.semitones', label: 'Semitones', min: -12, max: 12, step: 0.5 },
+    { param: 'pitch.phase_lock', label: 'Phase Lock', type: 'toggle' },
+    { param: 'formant.enabled', label: 'Formant Shift', type: 'toggle' },
+    { param: 'formant.semitones', label: 'Formant', min: -12, max: 12, step: 0.5 },
+    { param: 'formant.preserve', label: 'Preserve Formants', type: 'toggle' },
+    { param: 'conversion.enabled', label: 'Voice Conversion', type: 'toggle' },
+    { param: 'conversion.envelope', label: 'Target Timbre', min: 0, max: 1, step: 0.05 },
+    { param: 'conversion.pitch', label: 'Target Pitch', min: 0, max: 1, step: 0.05 },
+    { param: 'conversion.range', label: 'Target Intonation', min: 0, max: 1, step: 0.05 },
+    { param: 'conversion.adapt_s', label: 'Adaptation (s)', min: 0.5, max: 30, step: 0.5 },
+    { param: 'anonymizer.enabled', label: 'Anonymizer', type: 'toggle' },
+    { param: 'anonymizer.strength', label: 'Disguise Strength', min: 0, max: 1, step: 0.05 },
+    { param: 'anonymizer.variation', label: 'Disguise Variation', min: 0, max: 1, step: 0.05 },
+    { param: 'anonymizer.variation_s', label: 'Variation Period (s)', min: 1, max: 120, step: 1 },
+    { param: 'whisper.enabled', label: 'Whisper', type: 'toggle' },
+    { param: 'whisper.amount', label: 'Whisper Amount', min: 0, max: 1, step: 0.05 },
+    { param: 'vocoder.enabled', label: 'Vocoder', type: 'toggle' },
+    { param: 'vocoder.carrier', label: 'Carrier', type: 'select', options: ['saw', 'pulse', 'noise'] },
+    { param: 'vocoder.carrier_note', label: 'Carrier Note', min: 24, max: 96, step: 1 },
+    { param: 'vocoder.bands', label: 'Bands', min: 4, max: 32, step: 1 },
+    { param: 'vocoder.sibilance', label: 'Sibilance', min: 0, max: 1, step: 0.05 },
+    { param: 'vocoder.mix', label: 'Vocoder Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'ring_mod.enabled', label: 'Ring Mod', type: 'toggle' },
+    { param: 'ring_mod.frequency', label: 'Ring Freq (Hz)', min: 20, max: 2000, step: 10 },
+    { param: 'ring_mod.mix', label: 'Ring Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'distortion.enabled', label: 'Distortion', type: 'toggle' },
+    { param: 'distortion.shape', label: 'Distortion Shape', type: 'select', options: ['soft_clip', 'tube', 'fold'] },
+    { param: 'distortion.drive_db', label: 'Drive (dB)', min: 0, max: 48, step: 1 },
+    { param: 'distortion.output_db', label: 'Distortion Output (dB)', min: -48, max: 12, step: 1 },
+    { param: 'distortion.mix', label: 'Distortion Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'distortion.oversampling', label: 'Oversampling', type: 'select', options: ['off', 'x2', 'x4'] },
+    { param: 'bitcrusher.enabled', label: 'Bitcrusher', type: 'toggle' },
+    { param: 'bitcrusher.bits', label: 'Bit Depth', min: 1, max: 16, step: 0.5 },
+    { param: 'bitcrusher.sample_rate', label: 'Crush Rate (Hz)', min: 100, max: 44100, step: 100 },
+    { param: 'bitcrusher.mix', label: 'Crush Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'band_limit.enabled', label: 'Band Limit', type: 'toggle' },
+    { param: 'band_limit.low_cut', label: 'Low Cut (Hz)', min: 20, max: 2000, step: 10 },
+    { param: 'band_limit.high_cut', label: 'High Cut (Hz)', min: 1000, max: 16000, step: 100 },
+    { param: 'band_limit.drive', label: 'Drive', min: 1, max: 10, step: 0.1 },
+    { param: 'plosive.enabled', label: 'Plosive Suppression', type: 'toggle' },
+    { param: 'plosive.cutoff', label: 'Pop Cutoff (Hz)', min: 60, max: 300, step: 10 },
+    { param: 'plosive.threshold_db', label: 'Pop Threshold (dB)', min: -60, max: 0, step: 1 },
+    { param: 'plosive.max_reduction_db', label: 'Pop Max Reduction (dB)', min: 0, max: 30, step: 1 },
+    { param: 'deesser.enabled', label: 'De-esser', type: 'toggle' },
+    { param: 'deesser.frequency', label: 'De-ess Frequency (Hz)', min: 2000, max: 16000, step: 100 },
+    { param: 'deesser.threshold_db', label: 'De-ess Threshold (dB)', min: -60, max: 0, step: 1 },
+    { param: 'deesser.max_reduction_db', label: 'De-ess Max Reduction (dB)', min: 0, max: 24, step: 1 },
+    { param: 'agc.enabled', label: 'AGC', type: 'toggle' },
+    { param: 'agc.target_lufs', label: 'Target (LUFS)', min: -40, max: -6, step: 1 },
+    { param: 'agc.max_gain_db', label: 'Max Gain (dB)', min: 0, max: 40, step: 1 },
+    { param: 'agc.max_cut_db', label: 'Max Cut (dB)', min: 0, max: 40, step: 1 },
+    { param: 'agc.mode', label: 'AGC Mode', type: 'select', options: ['slow', 'fast'] },
+    { param: 'echo.enabled', label: 'Echo Cancellation', type: 'toggle' },
+    { param: 'echo.step_size', label: 'Echo Adaptation', min: 0.05, max: 1, step: 0.05 },
+    { param: 'echo.tail_ms', label: 'Echo Tail (ms)', min: 10, max: 180, step: 5 },
+    { param: 'echo.double_talk_db', label: 'Double-Talk (dB)', min: 0, max: 20, step: 1 },
+    { param: 'vad.enabled', label: 'Voice Activity', type: 'toggle' },
+    { param: 'vad.threshold_db', label: 'VAD Threshold (dB)', min: 3, max: 30, step: 1 },
+    { param: 'vad.hangover_ms', label: 'VAD Hangover (ms)', min: 0, max: 1000, step: 10 },
+    { param: 'feedback.enabled', label: 'Feedback Suppression', type: 'toggle' },
+    { param: 'feedback.sensitivity_db', label: 'Sensitivity (dB)', min: 6, max: 40, step: 1 },
+    { param: 'feedback.max_depth_db', label: 'Max Notch Depth (dB)', min: 6, max: 40, step: 1 },
+    { param: 'feedback.q', label: 'Notch Q', min: 2, max: 100, step: 1 },
+    { param: 'compressor.enabled', label: 'Compressor', type: 'toggle' },
+    { param: 'compressor.threshold_db', label: 'Threshold (dB)', min: -60, max: 0, step: 1 },
+    { param: 'compressor.ratio', label: 'Ratio', min: 1, max: 20, step: 0.5 },
+    { param: 'compressor.knee_db', label: 'Knee (dB)', min: 0, max: 24, step: 1 },
+    { param: 'compressor.attack_ms', label: 'Attack (ms)', min: 0.1, max: 100, step: 0.1 },
+    { param: 'compressor.release_ms', label: 'Release (ms)', min: 10, max: 1000, step: 10 },
+    { param: 'compressor.makeup_db', label: 'Makeup (dB)', min: 0, max: 24, step: 0.5 },
+    { param: 'vibrato.enabled', label: 'Vibrato', type: 'toggle' },
+    { param: 'vibrato.lfo.waveform', label: 'Vibrato Waveform', type: 'select', options: ['sine', 'triangle', 'square', 'random'] },
+    { param: 'vibrato.lfo.rate_hz', label: 'Vibrato Rate (Hz)', min: 0.05, max: 20, step: 0.05 },
+    { param: 'vibrato.lfo.depth', label: 'Vibrato Depth', min: 0, max: 1, step: 0.05 },
+    { param: 'vibrato.lfo.phase_deg', label: 'Vibrato Phase (deg)', min: 0, max: 360, step: 15 },
+    { param: 'chorus.enabled', label: 'Chorus', type: 'toggle' },
+    { param: 'chorus.lfo.waveform', label: 'Chorus Waveform', type: 'select', options: ['sine', 'triangle', 'square', 'random'] },
+    { param: 'chorus.lfo.rate_hz', label: 'Chorus Rate (Hz)', min: 0.05, max: 20, step: 0.05 },
+    { param: 'chorus.lfo.depth', label: 'Chorus Depth', min: 0, max: 1, step: 0.05 },
+    { param: 'chorus.lfo.phase_deg', label: 'Chorus Phase (deg)', min: 0, max: 360, step: 15 },
+    { param: 'chorus.delay_ms', label: 'Chorus Delay (ms)', min: 1, max: 30, step: 0.5 },
+    { param: 'chorus.voices', label: 'Chorus Voices', min: 1, max: 4, step: 1 },
+    { param: 'chorus.mix', label: 'Chorus Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'flanger.enabled', label: 'Flanger', type: 'toggle' },
+    { param: 'flanger.lfo.waveform', label: 'Flanger Waveform', type: 'select', options: ['sine', 'triangle', 'square', 'random'] },
+    { param: 'flanger.lfo.rate_hz', label: 'Flanger Rate (Hz)', min: 0.05, max: 20, step: 0.05 },
+    { param: 'flanger.lfo.depth', label: 'Flanger Depth', min: 0, max: 1, step: 0.05 },
+    { param: 'flanger.lfo.phase_deg', label: 'Flanger Phase (deg)', min: 0, max: 360, step: 15 },
+    { param: 'flanger.delay_ms', label: 'Flanger Delay (ms)', min: 0.1, max: 10, step: 0.1 },
+    { param: 'flanger.feedback', label: 'Flanger Feedback', min: -0.95, max: 0.95, step: 0.05 },
+    { param: 'flanger.mix', label: 'Flanger Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'tremolo.enabled', label: 'Tremolo', type: 'toggle' },
+    { param: 'tremolo.lfo.waveform', label: 'Tremolo Waveform', type: 'select', options: ['sine', 'triangle', 'square', 'random'] },
+    { param: 'tremolo.lfo.rate_hz', label: 'Tremolo Rate (Hz)', min: 0.05, max: 20, step: 0.05 },
+    { param: 'tremolo.lfo.depth', label: 'Tremolo Depth', min: 0, max: 1, step: 0.05 },
+    { param: 'tremolo.lfo.phase_deg', label: 'Tremolo Phase (deg)', min: 0, max: 360, step: 15 },
+    { param: 'convolution.enabled', label: 'Convolution', type: 'toggle' },
+    { param: 'convolution.mix', label: 'Convolution Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'convolution.gain_db', label: 'Convolution Gain (dB)', min: -24, max: 24, step: 0.5 },
+    { param: 'harmonizer.enabled', label: 'Harmonizer', type: 'toggle' },
+    { param: 'harmonizer.mode', label: 'Intervals', type: 'select', options: ['fixed', 'scale'] },
+    { param: 'harmonizer.key', label: 'Harmony Key', type: 'select', options: ['c', 'c_sharp', 'd', 'd_sharp', 'e', 'f', 'f_sharp', 'g', 'g_sharp', 'a', 'a_sharp', 'b'] },
+    { param: 'harmonizer.scale', label: 'Harmony Scale', type: 'select', options: ['chromatic', 'major', 'minor', 'harmonic_minor', 'major_pentatonic', 'minor_pentatonic', 'blues'] },
+    { param: 'harmonizer.dry_level', label: 'Dry Level', min: 0, max: 1, step: 0.05 },
+    ...[0, 1, 2, 3].flatMap(i => [
+        { param: `harmonizer.voices.${i}.enabled`, label: `Harmony ${i + 1}`, type: 'toggle' },
+        { param: `harmonizer.voices.${i}.interval`, label: `Harmony ${i + 1} Interval`, min: -14, max: 14, step: 1 },
+        { param: `harmonizer.voices.${i}.level`, label: `Harmony ${i + 1} Level`, min: 0, max: 1, step: 0.05 },
+        { param: `harmonizer.voices.${i}.pan`, label: `Harmony ${i + 1} Pan`, min: -1, max: 1, step: 0.1 },
+    ]),
+    { param: 'reverb.enabled', label: 'Reverb', type: 'toggle' },
+    { param: 'reverb.style', label: 'Reverb Style', type: 'select', options: ['room', 'plate', 'hall'] },
+    { param: 'reverb.size', label: 'Size', min: 0, max: 1, step: 0.05 },
+    { param: 'reverb.decay_s', label: 'Decay (s)', min: 0.1, max: 20, step: 0.1 },
+    { param: 'reverb.damping', label: 'Damping', min: 0, max: 1, step: 0.05 },
+    { param: 'reverb.pre_delay_ms', label: 'Pre-Delay (ms)', min: 0, max: 250, step: 5 },
+    { param: 'reverb.mix', label: 'Reverb Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'delay.enabled', label: 'Delay', type: 'toggle' },
+    { param: 'delay.time_ms', label: 'Delay Time (ms)', min: 1, max: 2000, step: 1 },
+    { param: 'delay.sync', label: 'Tempo Sync', type: 'toggle' },
+    { param: 'delay.bpm', label: 'BPM', min: 20, max: 300, step: 1 },
+    { param: 'delay.note', label: 'Note Value', type: 'select', options: ['whole', 'half', 'quarter', 'dotted_quarter', 'triplet_quarter', 'eighth', 'dotted_eighth', 'triplet_eighth', 'sixteenth'] },
+    { param: 'delay.feedback', label: 'Delay Feedback', min: 0, max: 0.95, step: 0.05 },
+    { param: 'delay.high_cut', label: 'Feedback High-Cut (Hz)', min: 200, max: 16000, step: 100 },
+    { param: 'delay.mix', label: 'Delay Mix', min: 0, max: 1, step: 0.05 },
+    { param: 'delay.ping_pong', label: 'Ping-Pong', type: 'toggle' },
+    { param: 'limiter.enabled', label: 'Limiter', type: 'toggle' },
+    { param: 'limiter.ceiling_db', label: 'Ceiling (dBTP)', min: -24, max: 0, step: 0.1 },
+    { param: 'limiter.release_ms', label: 'Limiter Release (ms)', min: 10, max: 1000, step: 10 },
+];
+
+// Equalizer band types and the ranges of their per-band sliders
+const EQ_BAND_TYPES = ['low_shelf', 'high_shelf', 'peaking', 'low_pass', 'high_pass', 'notch'];
+const EQ_MAX_BANDS = 8;
+const EQ_RANGE_DB = 24;
+
+// Note names for pitch readouts, from C
+const NOTE_NAMES = ['C', 'C#', 'D', 'D#', 'E', 'F', 'F#', 'G', 'G#', 'A', 'A#', 'B'];
+
+// Built-in voice characters, each shaped by the intensity and tone macros
+const VOICES = ['none', 'robot', 'chipmunk', 'deep', 'alien', 'radio', 'telephone', 'whisper'];
+
 class AudioVisualizer {
     constructor() {
         this.canvas = document.getElementById('visualizer');
@@ -10,7 +172,21 @@ class AudioVisualizer {
         this.sampleRate = 44100;
         this.fftSize = 1024;
         
+        this.controls = {};
+        this.eqBands = [];
+        this.eqRows = null;
+        this.eqResponse = null;
+        
         this.setupCanvas();
+        this.setupControls();
+        this.setupEqControls();
+        this.setupNoiseControls();
+        this.setupFeedbackControls();
+        this.setupImpulseControls();
+        this.setupTargetControls();
+        this.setupAnonymizerControls();
+        this.setupMorphControls();
+        this.setupVoiceControls();
         this.connectWebSocket();
         this.startAnimation();
     }
@@ -25,6 +201,505 @@ class AudioVisualizer {
         });
     }
     
+    setupControls() {
+        const panel = document.getElementById('controls');
+        
+        for (const control of CONTROLS) {
+            const row = document.createElement('div');
+            row.className = 'control';
+            
+            const label = document.createElement('label');
+            label.textContent = control.label;
+            row.appendChild(label);
+            
+            const input = document.createElement(control.type === 'select' ? 'select' : 'input');
+            if (control.type === 'select') {
+                for (const option of control.options) {
+                    const item = document.createElement('option');
+                    item.value = option;
+                    item.textContent = option;
+                    input.appendChild(item);
+                }
+                input.addEventListener('change', () => this.sendControl(control.param, input.value));
+                row.appendChild(input);
+            } else if (control.type === 'toggle') {
+                input.type = 'checkbox';
+                input.addEventListener('change', () => this.sendControl(control.param, input.checked));
+                row.appendChild(input);
+            } else {
+                input.type = 'range';
+                input.min = control.min;
+                input.max = control.max;
+                input.step = control.step;
+                const value = document.createElement('span');
+                value.className = 'value';
+                input.addEventListener('input', () => {
+                    value.textContent = input.value;
+                    this.sendControl(control.param, parseFloat(input.value));
+                });
+                row.appendChild(input);
+                row.appendChild(value);
+                control.valueLabel = value;
+            }
+            
+            this.controls[control.param] = { control, input };
+            panel.appendChild(row);
+        }
+    }
+    
+    setupEqControls() {
+        this.eqPanel = document.createElement('div');
+        this.eqPanel.id = 'eq-controls';
+        document.getElementById('controls').appendChild(this.eqPanel);
+    }
+    
+    // Rebuilt whenever the number of bands changes
+    buildEqRows(bands) {
+        this.eqPanel.innerHTML = '';
+        this.eqRows = [];
+        
+        const header = document.createElement('div');
+        header.className = 'control';
+        const label = document.createElement('label');
+        label.textContent = 'Equalizer';
+        header.appendChild(label);
+        this.eqToggle = document.createElement('input');
+        this.eqToggle.type = 'checkbox';
+        this.eqToggle.addEventListener('change', () => this.sendControl('eq.enabled', this.eqToggle.checked));
+        header.appendChild(this.eqToggle);
+        const add = document.createElement('button');
+        add.textContent = 'Add Band';
+        add.disabled = bands.length >= EQ_MAX_BANDS;
+        add.addEventListener('click', () => this.sendControl('eq.bands',
+            [...this.eqBands, { band_type: 'peaking', frequency: 1000, gain_db: 0, q: 1 }]));
+        header.appendChild(add);
+        this.eqPanel.appendChild(header);
+        
+        bands.forEach((band, index) => {
+            const path = `eq.bands.${index}`;
+            const makeRow = (text, input) => {
+                const row = document.createElement('div');
+                row.className = 'control';
+                const rowLabel = document.createElement('label');
+                rowLabel.textContent = text;
+                row.appendChild(rowLabel);
+                row.appendChild(input);
+                this.eqPanel.appendChild(row);
+                return row;
+            };
+            const makeSlider = (min, max, step, send) => {
+                const slider = document.createElement('input');
+                slider.type = 'range';
+                slider.min = min;
+                slider.max = max;
+                slider.step = step;
+                slider.addEventListener('input', () => send(parseFloat(slider.value)));
+                return slider;
+            };
+            
+            const type = document.createElement('select');
+            for (const option of EQ_BAND_TYPES) {
+                const item = document.createElement('option');
+                item.value = option;
+                item.textContent = option;
+                type.appendChild(item);
+            }
+            type.addEventListener('change', () => this.sendControl(`${path}.band_type`, type.value));
+            const typeRow = makeRow(`Band ${index + 1}`, type);
+            const remove = document.createElement('button');
+            remove.textContent = '×';
+            remove.addEventListener('click', () => this.sendControl('eq.bands', this.eqBands.filter((_, i) => i !== index)));
+            typeRow.appendChild(remove);
+            
+            // Frequency slider on a log scale, 20 Hz - 20 kHz
+            const frequency = makeSlider(Math.log10(20), Math.log10(20000), 0.01,
+                value => this.sendControl(`${path}.frequency`, Math.round(10 ** value)));
+            const gain = makeSlider(-EQ_RANGE_DB, EQ_RANGE_DB, 0.5, value => this.sendControl(`${path}.gain_db`, value));
+            const q = makeSlider(0.1, 10, 0.1, value => this.sendControl(`${path}.q`, value));
+            makeRow('Freq', frequency);
+            makeRow('Gain (dB)', gain);
+            makeRow('Q', q);
+            
+            this.eqRows.push({ type, frequency, gain, q });
+        });
+    }
+    
+    updateEqControls(eq, response) {
+        this.eqBands = eq.bands;
+        this.eqResponse = response;
+        if (!this.eqRows || this.eqRows.length !== eq.bands.length) {
+            this.buildEqRows(eq.bands);
+        }
+        
+        const active = document.activeElement;
+        if (active !== this.eqToggle) this.eqToggle.checked = eq.enabled;
+        eq.bands.forEach((band, index) => {
+            const row = this.eqRows[index];
+            if (active !== row.type) row.type.value = band.band_type;
+            if (active !== row.frequency) row.frequency.value = Math.log10(band.frequency);
+            if (active !== row.gain) row.gain.value = band.gain_db;
+            if (active !== row.q) row.q.value = band.q;
+        });
+    }
+    
+    setupNoiseControls() {
+        const panel = document.getElementById('noise-controls');
+        const row = document.createElement('div');
+        row.className = 'control';
+        
+        this.noiseStatus = document.createElement('label');
+        this.noiseStatus.textContent = 'Noise Profile: --';
+        row.appendChild(this.noiseStatus);
+        
+        const learn = document.createElement('button');
+        learn.textContent = 'Learn';
+        learn.title = 'Record 3 seconds of room tone (stay quiet)';
+        learn.addEventListener('click', () => this.send({ type: 'learn_noise', seconds: 3 }));
+        row.appendChild(learn);
+        
+        const clear = document.createElement('button');
+        clear.textContent = 'Clear';
+        clear.addEventListener('click', () => this.sendControl('denoise.profile', null));
+        row.appendChild(clear);
+        
+        panel.appendChild(row);
+    }
+    
+    updateNoiseControls(status) {
+        if (status.noise_learning !== null && status.noise_learning !== undefined) {
+            this.noiseStatus.textContent = `Learning: ${Math.round(status.noise_learning * 100)}%`;
+        } else {
+            this.noiseStatus.textContent = `Noise Profile: ${status.noise_profile ? 'learned' : 'none'}`;
+        }
+    }
+    
+    setupFeedbackControls() {
+        const panel = document.getElementById('feedback-controls');
+        const row = document.createElement('div');
+        row.className = 'control';
+        
+        this.feedbackStatus = document.createElement('label');
+        this.feedbackStatus.textContent = 'Notches: --';
+        row.appendChild(this.feedbackStatus);
+        
+        const reset = document.createElement('button');
+        reset.textContent = 'Reset';
+        reset.title = 'Remove all feedback notches';
+        reset.addEventListener('click', () => this.send({ type: 'reset_feedback' }));
+        row.appendChild(reset);
+        
+        panel.appendChild(row);
+    }
+    
+    updateFeedbackControls(status) {
+        if (!status.settings.feedback.enabled) {
+            this.feedbackStatus.textContent = 'Notches: --';
+        } else if (status.feedback_notches.length === 0) {
+            this.feedbackStatus.textContent = 'Notches: none';
+        } else {
+            this.feedbackStatus.textContent = 'Notches: ' + status.feedback_notches
+                .map(notch => `${Math.round(notch.frequency)} Hz -${notch.depth_db.toFixed(0)} dB`)
+                .join(', ');
+        }
+    }
+    
+    setupImpulseControls() {
+        const panel = document.getElementById('impulse-controls');
+        const row = document.createElement('div');
+        row.className = 'control';
+        
+        this.impulseStatus = document.createElement('label');
+        this.impulseStatus.textContent = 'Impulse: --';
+        row.appendChild(this.impulseStatus);
+        
+        this.impulseInput = document.createElement('input');
+        this.impulseInput.type = 'text';
+        this.impulseInput.placeholder = 'ir/telephone.wav';
+        row.appendChild(this.impulseInput);
+        
+        const load = document.createElement('button');
+        load.textContent = 'Load';
+        load.title = 'Convolve with this impulse response WAV (path on the server)';
+        load.addEventListener('click', () => {
+            const file = this.impulseInput.value.trim();
+            if (!file) return;
+            this.sendControl('convolution.impulse', file);
+            this.sendControl('convolution.enabled', true);
+        });
+        row.appendChild(load);
+        
+        panel.appendChild(row);
+    }
+    
+    updateImpulseControls(status) {
+        const file = status.settings.convolution.impulse;
+        if (!file) {
+            this.impulseStatus.textContent = 'Impulse: none';
+        } else if (status.impulse_ms !== null && status.impulse_ms !== undefined) {
+            this.impulseStatus.textContent = `Impulse: ${file} (${Math.round(status.impulse_ms)} ms)`;
+        } else {
+            this.impulseStatus.textContent = `Impulse: ${file}${status.settings.convolution.enabled ? ' (not loaded)' : ''}`;
+        }
+    }
+    
+    setupTargetControls() {
+        const panel = document.getElementById('target-controls');
+        const row = document.createElement('div');
+        row.className = 'control';
+        
+        this.targetStatus = document.createElement('label');
+        this.targetStatus.textContent = 'Target voice: --';
+        row.appendChild(this.targetStatus);
+        
+        this.targetInput = document.createElement('input');
+        this.targetInput.type = 'text';
+        this.targetInput.placeholder = 'voices/target.wav';
+        row.appendChild(this.targetInput);
+        
+        const load = document.createElement('button');
+        load.textContent = 'Load';
+        load.title = 'Convert the voice towards this recording (path on the server)';
+        load.addEventListener('click', () => {
+            const file = this.targetInput.value.trim();
+            if (!file) return;
+            this.sendControl('conversion.target', file);
+            this.sendControl('conversion.enabled', true);
+        });
+        row.appendChild(load);
+        
+        panel.appendChild(row);
+    }
+    
+    updateTargetControls(status) {
+        const file = status.settings.conversion.target;
+        if (!file) {
+            this.targetStatus.textContent = 'Target voice: none';
+        } else if (status.target_pitch !== null && status.target_pitch !== undefined) {
+            const speaker = status.speaker_pitch !== null ? `${Math.round(status.speaker_pitch)} Hz` : '--';
+            this.targetStatus.textContent = `Target voice: ${file} (${Math.round(status.target_pitch)} Hz, you ${speaker})`;
+        } else {
+            this.targetStatus.textContent = `Target voice: ${file}${status.settings.conversion.enabled ? ' (not loaded)' : ''}`;
+        }
+    }
+    
+    setupAnonymizerControls() {
+        const panel = document.getElementById('anonymizer-controls');
+        const row = document.createElement('div');
+        row.className = 'control';
+        
+        this.anonymizerStatus = document.createElement('label');
+        this.anonymizerStatus.textContent = 'Seed: --';
+        row.appendChild(this.anonymizerStatus);
+        
+        this.seedInput = document.createElement('input');
+        this.seedInput.type = 'number';
+        this.seedInput.min = 0;
+        this.seedInput.step = 1;
+        this.seedInput.placeholder = 'seed';
+        row.appendChild(this.seedInput);
+        
+        const use = document.createElement('button');
+        use.textContent = 'Use';
+        use.title = 'Disguise the voice with this seed';
+        use.addEventListener('click', () => {
+            const seed = parseInt(this.seedInput.value, 10);
+            if (Number.isNaN(seed) || seed < 0) return;
+            this.sendControl('anonymizer.seed', seed);
+            this.sendControl('anonymizer.enabled', true);
+        });
+        row.appendChild(use);
+        
+        const fresh = document.createElement('button');
+        fresh.textContent = 'New';
+        fresh.title = 'Pick a new random seed';
+        fresh.addEventListener('click', () => this.sendControl('anonymizer.seed', null));
+        row.appendChild(fresh);
+        
+        panel.appendChild(row);
+    }
+    
+    updateAnonymizerControls(status) {
+        const anonymizer = status.settings.anonymizer;
+        if (!anonymizer.enabled || anonymizer.seed === null) {
+            this.anonymizerStatus.textContent = 'Seed: --';
+        } else if (status.disguise) {
+            const d = status.disguise;
+            this.anonymizerStatus.textContent = `Seed: ${anonymizer.seed} (pitch ${d.pitch_semitones.toFixed(1)}, formants ${d.formant_semitones.toFixed(1)}, warp ${d.warp.toFixed(2)})`;
+        } else {
+            this.anonymizerStatus.textContent = `Seed: ${anonymizer.seed}`;
+        }
+    }
+    
+    setupMorphControls() {
+        const panel = document.getElementById('morph-controls');
+        const makeRow = (labelText, input) => {
+            const row = document.createElement('div');
+            row.className = 'control';
+            const label = document.createElement('label');
+            label.textContent = labelText;
+            row.appendChild(label);
+            row.appendChild(input);
+            panel.appendChild(row);
+            return row;
+        };
+        
+        this.morphA = document.createElement('input');
+        this.morphA.type = 'text';
+        this.morphA.placeholder = 'presets/a.json';
+        makeRow('Preset A', this.morphA);
+        
+        this.morphB = document.createElement('input');
+        this.morphB.type = 'text';
+        this.morphB.placeholder = 'presets/b.json';
+        const bRow = makeRow('Preset B', this.morphB);
+        
+        const load = document.createElement('button');
+        load.textContent = 'Load';
+        load.title = 'Morph between these presets (paths on the server)';
+        load.addEventListener('click', () => {
+            const a = this.morphA.value.trim();
+            const b = this.morphB.value.trim();
+            if (!a || !b) return;
+            this.send({ type: 'load_morph', a, b });
+        });
+        bRow.appendChild(load);
+        
+        this.morphSlider = document.createElement('input');
+        this.morphSlider.type = 'range';
+        this.morphSlider.min = 0;
+        this.morphSlider.max = 1;
+        this.morphSlider.step = 0.01;
+        this.morphSlider.disabled = true;
+        this.morphSlider.addEventListener('input', () => this.send({ type: 'morph', amount: parseFloat(this.morphSlider.value) }));
+        this.morphLabel = makeRow('Morph: --', this.morphSlider).firstChild;
+        
+        const stop = document.createElement('button');
+        stop.textContent = '×';
+        stop.title = 'Stop morphing and keep the current blend';
+        stop.addEventListener('click', () => this.send({ type: 'stop_morph' }));
+        this.morphSlider.parentNode.appendChild(stop);
+    }
+    
+    updateMorphControls(status) {
+        const amount = status.morph_amount;
+        const running = amount !== null && amount !== undefined;
+        this.morphSlider.disabled = !running;
+        this.morphLabel.textContent = running ? `Morph: A ${Math.round((1 - amount) * 100)}% B ${Math.round(amount * 100)}%` : 'Morph: --';
+        if (running && document.activeElement !== this.morphSlider) {
+            this.morphSlider.value = amount;
+        }
+    }
+    
+    setupVoiceControls() {
+        const panel = document.getElementById('voice-controls');
+        
+        const makeRow = (labelText, input) => {
+            const row = document.createElement('div');
+            row.className = 'control';
+            const label = document.createElement('label');
+            label.textContent = labelText;
+            row.appendChild(label);
+            row.appendChild(input);
+            panel.appendChild(row);
+        };
+        
+        this.voiceSelect = document.createElement('select');
+        for (const voice of VOICES) {
+            const item = document.createElement('option');
+            item.value = voice;
+            item.textContent = voice;
+            this.voiceSelect.appendChild(item);
+        }
+        makeRow('Voice', this.voiceSelect);
+        
+        const makeSlider = (min, max, value) => {
+            const slider = document.createElement('input');
+            slider.type = 'range';
+            slider.min = min;
+            slider.max = max;
+            slider.step = 0.05;
+            slider.value = value;
+            return slider;
+        };
+        this.intensitySlider = makeSlider(0, 1, 0.5);
+        this.toneSlider = makeSlider(-1, 1, 0);
+        makeRow('Intensity', this.intensitySlider);
+        makeRow('Tone', this.toneSlider);
+        
+        const sendVoice = () => this.sendVoice();
+        this.voiceSelect.addEventListener('change', sendVoice);
+        this.intensitySlider.addEventListener('input', sendVoice);
+        this.toneSlider.addEventListener('input', sendVoice);
+    }
+    
+    sendVoice() {
+        if (this.socket && this.socket.readyState === WebSocket.OPEN) {
+            const voice = this.voiceSelect.value === 'none' ? null : this.voiceSelect.value;
+            const macros = {
+                intensity: parseFloat(this.intensitySlider.value),
+                tone: parseFloat(this.toneSlider.value),
+            };
+            this.socket.send(JSON.stringify({ type: 'voice', voice, macros }));
+        }
+    }
+    
+    updateVoiceControls(selection) {
+        const active = document.activeElement;
+        if (active === this.voiceSelect || active === this.intensitySlider || active === this.toneSlider) return;
+        
+        this.voiceSelect.value = selection ? selection.voice : 'none';
+        if (selection) {
+            this.intensitySlider.value = selection.macros.intensity;
+            this.toneSlider.value = selection.macros.tone;
+        }
+    }
+    
+    // Gain-reduction meter: a bar scaled to 20 dB plus the numeric value
+    updateMeter(id, label, enabled, reduction) {
+        const meter = document.getElementById(id);
+        if (!enabled) {
+            meter.textContent = `${label}: --`;
+            return;
+        }
+        const filled = Math.round(Math.min(reduction, 20) / 20 * 20);
+        meter.textContent = `${label}: ${'█'.repeat(filled)}${'░'.repeat(20 - filled)} ${reduction.toFixed(1)} dB`;
+    }
+    
+    // Frequency with the nearest note, e.g. "220.0 Hz (A3)"
+    formatPitch(frequency) {
+        const note = Math.round(69 + 12 * Math.log2(frequency / 440));
+        return `${frequency.toFixed(1)} Hz (${NOTE_NAMES[((note % 12) + 12) % 12]}${Math.floor(note / 12) - 1})`;
+    }
+    
+    sendControl(param, value) {
+        this.send({ type: 'set', param, value });
+    }
+    
+    send(message) {
+        if (this.socket && this.socket.readyState === WebSocket.OPEN) {
+            this.socket.send(JSON.stringify(message));
+        }
+    }
+    
+    updateControls(settings) {
+        for (const [param, { control, input }] of Object.entries(this.controls)) {
+            // Don't fight the user while they are dragging a slider
+            if (document.activeElement === input) continue;
+            
+            const value = param.split('.').reduce((obj, key) => obj && obj[key], settings);
+            if (value === undefined) continue;
+            
+            if (control.type === 'toggle') {
+                input.checked = Boolean(value);
+            } else if (control.type === 'select') {
+                input.value = value;
+            } else {
+                input.value = value;
+                control.valueLabel.textContent = Number(value).toFixed(1);
+            }
+        }
+    }
+    
     connectWebSocket() {
         const protocol = window.location.protocol === 'https:' ? 'wss' : 'ws';
         const host = window.location.host;
@@ -47,6 +722,39 @@ class AudioVisualizer {
                     // Debug: log spectrum data
                     console.log(`Input spectrum length: ${this.inputSpectrum.length}, first 5 values:`, this.inputSpectrum.slice(0, 5));
                     console.log(`Output spectrum length: ${this.outputSpectrum.length}, first 5 values:`, this.outputSpectrum.slice(0, 5));
+                } else if (data.type === 'voice_activity') {
+                    document.getElementById('vad').textContent = `Voice: ${data.event === 'start' ? 'speaking' : 'silent'}`;
+                    console.log(`Voice activity ${data.event} at ${data.time_s.toFixed(2)} s`);
+                } else if (data.type === 'status') {
+                    this.updateControls(data.settings);
+                    this.updateVoiceControls(data.voice);
+                    this.updateNoiseControls(data);
+                    this.updateFeedbackControls(data);
+                    this.updateImpulseControls(data);
+                    this.updateTargetControls(data);
+                    this.updateAnonymizerControls(data);
+                    this.updateMorphControls(data);
+                    this.updateEqControls(data.settings.eq, data.eq_response);
+                    document.getElementById('latency').textContent = `Latency: ${data.latency_ms.toFixed(1)} ms`;
+                    document.getElementById('gate').textContent = data.noise_floor_db !== null
+                        ? `Gate: ${data.gate_open ? 'open' : 'closed'}, floor ${data.noise_floor_db.toFixed(0)} dBFS`
+                        : 'Gate: --';
+                    document.getElementById('vad').textContent = data.voice_active !== null
+                        ? `Voice: ${data.voice_active ? 'speaking' : 'silent'}`
+                        : 'Voice: --';
+                    document.getElementById('echo').textContent = data.settings.echo.enabled
+                        ? `Echo: ${data.echo_delay_ms !== null ? data.echo_delay_ms.toFixed(0) + ' ms' : 'measuring'}, ${data.echo_erle_db !== null ? '-' + data.echo_erle_db.toFixed(0) + ' dB' : 'adapting'}${data.double_talk ? ', double-talk' : ''}`
+                        : 'Echo: --';
+                    document.getElementById('agc').textContent = data.agc_gain_db !== null
+                        ? `AGC: ${data.loudness_lufs !== null ? data.loudness_lufs.toFixed(1) : '--'} LUFS in, ${data.agc_gain_db >= 0 ? '+' : ''}${data.agc_gain_db.toFixed(1)} dB`
+                        : 'AGC: --';
+                    this.updateMeter('plosive-meter', 'Pop GR', data.settings.plosive.enabled, data.plosive_reduction_db);
+                    this.updateMeter('deesser-meter', 'De-ess GR', data.settings.deesser.enabled, data.deesser_reduction_db);
+                    this.updateMeter('compressor-meter', 'Comp GR', data.settings.compressor.enabled, data.compressor_reduction_db);
+                    this.updateMeter('limiter-meter', 'Limit GR', data.settings.limiter.enabled, data.limiter_reduction_db);
+                    document.getElementById('pitch').textContent = data.detected_pitch
+                        ? `Pitch: ${this.formatPitch(data.detected_pitch)}${data.corrected_pitch ? ' → ' + this.formatPitch(data.corrected_pitch) : ''}`
+                        : 'Pitch: --';
                 }
             } catch (e) {
                 console.error('Error parsing WebSocket data:', e);
@@ -85,6 +793,7 @@ class AudioVisualizer {
         
         // Draw output spectrum (bottom half)
         this.drawSpectrum(this.outputSpectrum, height / 2, height / 2, 'OUTPUT');
+        this.drawEqResponse(height / 2, height / 2);
         
         // Draw center line
         this.ctx.strokeStyle = '#333';
@@ -128,6 +837,40 @@ class AudioVisualizer {
         this.drawFrequencyScale(yOffset, sectionHeight);
     }
     
+    // Combined EQ response over the output spectrum, ±EQ_RANGE_DB around the middle
+    drawEqResponse(yOffset, sectionHeight) {
+        if (!this.eqResponse || this.eqResponse.length === 0) return;
+        
+        const { width } = this.canvas;
+        const middle = yOffset + sectionHeight / 2;
+        const scale = (sectionHeight * 0.4) / EQ_RANGE_DB;
+        
+        this.ctx.strokeStyle = '#555';
+        this.ctx.lineWidth = 1;
+        this.ctx.setLineDash([4, 4]);
+        this.ctx.beginPath();
+        this.ctx.moveTo(0, middle);
+        this.ctx.lineTo(width, middle);
+        this.ctx.stroke();
+        this.ctx.setLineDash([]);
+        
+        this.ctx.strokeStyle = '#fff';
+        this.ctx.lineWidth = 2;
+        this.ctx.beginPath();
+        this.eqResponse.forEach((db, i) => {
+            const x = (i / this.eqResponse.length) * width;
+            const clamped = Math.max(-EQ_RANGE_DB, Math.min(EQ_RANGE_DB, db));
+            const y = middle - clamped * scale;
+            if (i === 0) this.ctx.moveTo(x, y);
+            else this.ctx.lineTo(x, y);
+        });
+        this.ctx.stroke();
+        
+        this.ctx.fillStyle = '#fff';
+        this.ctx.font = '12px monospace';
+        this.ctx.fillText(`EQ ±${EQ_RANGE_DB} dB`, 10, yOffset + 45);
+    }
+    
     getFrequencyColor(freq) {
         if (freq < 250) return '#ff4444';      // Red for bass
         if (freq < 500) return '#ffff44';      // Yellow for low-mid